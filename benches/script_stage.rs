@@ -0,0 +1,85 @@
+//! Measures the cost of running scripts over scenes of growing entity counts.
+//! Most scripts only pass the scene on, one moves every entity, the way scripts touch a part of the scene.
+//! Run with `cargo bench --bench script_stage`
+use std::time::Instant;
+
+use ie::prelude::*;
+use pyo3::Python;
+
+/** The amount of scripts that pass the scene on in each benchmarked scene */
+const SCRIPTS: usize = 30;
+/** The amount of frames measured per entity count */
+const FRAMES: u32 = 20;
+
+const SCRIPT: &str = "
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    return scene
+";
+
+const MOVE_SCRIPT: &str = "
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    for entity in scene['entities']:
+        entity['position']['x'] += 1
+    return scene
+";
+
+/** Build a scene with the given amount of sprite entities */
+fn scene(script_path: &str, move_script_path: &str, entity_count: usize) -> json::JsonValue {
+    let mut scene = json::object! {
+        scripts: [],
+        entities: [],
+    };
+    for _ in 0..SCRIPTS {
+        scene["scripts"].push(script_path).unwrap();
+    }
+    scene["scripts"].push(move_script_path).unwrap();
+    for i in 0..entity_count {
+        scene["entities"]
+            .push(json::object! {
+                position: { x: i, y: i },
+                scale: { x: 1, y: 1 },
+                sprite: {
+                    texture: "default_sprite",
+                    dest_size: { x: 16, y: 16 },
+                },
+            })
+            .unwrap();
+    }
+    return scene;
+}
+
+fn main() -> IslandResult<()> {
+    let script_path = std::env::temp_dir().join("ie_bench_script.py");
+    std::fs::write(&script_path, SCRIPT).unwrap();
+    let script_path = script_path.to_str().unwrap();
+    let move_script_path = std::env::temp_dir().join("ie_bench_move_script.py");
+    std::fs::write(&move_script_path, MOVE_SCRIPT).unwrap();
+    let move_script_path = move_script_path.to_str().unwrap();
+
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+
+    println!("{:>10} {:>14} {:>18}", "entities", "frame (ms)", "per entity (us)");
+    for entity_count in [10, 100, 250, 500, 1000] {
+        let mut runner = HeadlessRunner::new(py, IEAppDescriptor::default(), 1.0 / 60.0)?;
+        runner.world.load_scene_json(scene(script_path, move_script_path, entity_count))?;
+
+        let start = Instant::now();
+        runner.run(FRAMES)?;
+        let frame = start.elapsed() / FRAMES;
+
+        println!(
+            "{:>10} {:>14.3} {:>18.3}",
+            entity_count,
+            frame.as_secs_f64() * 1000.0,
+            frame.as_secs_f64() * 1_000_000.0 / entity_count as f64
+        );
+    }
+    return Ok(());
+}
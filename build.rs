@@ -0,0 +1,419 @@
+//! Generates the python type stubs of the `ie` module from the pyo3 bindings, so they always match the engine
+use std::{env, fs, path::Path};
+
+use syn::{
+    punctuated::Punctuated, Attribute, Fields, FnArg, ImplItem, ImplItemMethod, Item, Lit, Meta, NestedMeta, Pat,
+    ReturnType, Token, Type,
+};
+
+/** The files with the classes that scripts can use, in the order they appear in the stubs */
+const SOURCES: [&str; 3] = ["src/ecs/python.rs", "src/ecs/scene.rs", "src/ecs/ctx.rs"];
+
+/** A python class found in the sources */
+struct Class {
+    // The name of the rust struct
+    rust_name: String,
+    // The name of the class in python
+    name: String,
+    doc: Option<String>,
+    // The rendered attributes and methods
+    members: Vec<String>,
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    for source in SOURCES.iter() {
+        println!("cargo:rerun-if-changed={}", source);
+    }
+
+    let files = SOURCES
+        .iter()
+        .map(|source| {
+            let code = fs::read_to_string(source).unwrap_or_else(|e| panic!("Failed to read {}: {}", source, e));
+            return syn::parse_file(&code).unwrap_or_else(|e| panic!("Failed to parse {}: {}", source, e));
+        })
+        .collect::<Vec<_>>();
+    let stubs = generate(&files);
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join("ie.pyi"), stubs).expect("Failed to write the python stubs");
+}
+
+/** Render the stubs of the classes and the module docs in the files */
+fn generate(files: &[syn::File]) -> String {
+    let mut classes = vec![];
+    let mut module_doc = None;
+    // Collect the classes first, so methods can refer to classes that are defined later
+    for item in files.iter().flat_map(|file| file.items.iter()) {
+        match item {
+            Item::Struct(item) => {
+                if let Some(name) = pyclass_name(&item.attrs, &item.ident.to_string()) {
+                    classes.push(Class {
+                        rust_name: item.ident.to_string(),
+                        name,
+                        doc: doc(&item.attrs),
+                        members: vec![],
+                    });
+                }
+            }
+            Item::Fn(item) if has_attr(&item.attrs, "pymodule") => module_doc = doc(&item.attrs),
+            _ => {}
+        }
+    }
+    let names = classes
+        .iter()
+        .map(|class| (class.rust_name.clone(), class.name.clone()))
+        .collect::<Vec<_>>();
+    let types = TypeNames { classes: &names };
+
+    for item in files.iter().flat_map(|file| file.items.iter()) {
+        match item {
+            Item::Struct(item) => {
+                let members = fields(&item.fields, &types);
+                if let Some(class) = classes.iter_mut().find(|class| item.ident == class.rust_name) {
+                    class.members.extend(members);
+                }
+            }
+            Item::Impl(item) => {
+                let rust_name = match &*item.self_ty {
+                    Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+                    _ => None,
+                };
+                let class = match classes.iter_mut().find(|class| Some(&class.rust_name) == rust_name.as_ref()) {
+                    Some(class) => class,
+                    None => continue,
+                };
+                let protocol = item.trait_.is_some() && has_attr(&item.attrs, "pyproto");
+                if !protocol && !has_attr(&item.attrs, "pymethods") {
+                    continue;
+                }
+                for impl_item in item.items.iter() {
+                    match impl_item {
+                        ImplItem::Method(method) if protocol => {
+                            class.members.extend(protocol_method(method, &class.name, &types));
+                        }
+                        ImplItem::Method(method) => class.members.push(method_stub(method, &class.name, &types)),
+                        ImplItem::Const(constant) if has_attr(&constant.attrs, "classattr") => {
+                            let python_type = types.python(&constant.ty, &class.name);
+                            class.members.push(format!("    {}: ClassVar[{}]", constant.ident, python_type));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // The input names are class attributes generated by a macro, the first argument is the class
+            Item::Macro(item) if item.mac.path.is_ident("name_constants") => {
+                let names = item
+                    .mac
+                    .parse_body_with(Punctuated::<syn::Ident, Token![,]>::parse_terminated)
+                    .expect("The name constants are a class followed by a list of names");
+                let mut names = names.iter();
+                let rust_name = names.next().map(|name| name.to_string());
+                if let Some(class) = classes.iter_mut().find(|class| Some(&class.rust_name) == rust_name.as_ref()) {
+                    class.members.extend(names.map(|name| format!("    {}: ClassVar[str]", name)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut result = String::new();
+    if let Some(module_doc) = module_doc {
+        result += &format!("{}\n", docstring(&module_doc, ""));
+    }
+    result += &format!(
+        "# The type stubs of the scripting api of ie {}, generated from the engine source by its build script.\n",
+        env::var("CARGO_PKG_VERSION").unwrap_or_default()
+    );
+    result += "# Don't edit them by hand, write them out with `cargo run --bin ie-stubs` instead.\n";
+    result += "from typing import Any, ClassVar, Dict, List, Optional, Tuple, Union\n\n";
+    result += "__version__: str\n";
+    for class in classes.iter() {
+        result += &format!("\n\nclass {}:\n", class.name);
+        if let Some(doc) = &class.doc {
+            result += &format!("{}\n", docstring(doc, "    "));
+        }
+        if class.members.is_empty() && class.doc.is_none() {
+            result += "    ...\n";
+        }
+        for member in class.members.iter() {
+            result += &format!("{}\n", member);
+        }
+    }
+    return result;
+}
+
+/** Maps rust types to python type annotations */
+struct TypeNames<'a> {
+    // The python names of the rust classes
+    classes: &'a [(String, String)],
+}
+
+impl TypeNames<'_> {
+    /** Get the python annotation of a rust type, `Self` being the class */
+    fn python(&self, ty: &Type, class: &str) -> String {
+        let path = match ty {
+            Type::Reference(reference) => return self.python(&reference.elem, class),
+            Type::Tuple(tuple) if tuple.elems.is_empty() => return "None".into(),
+            Type::Tuple(tuple) => {
+                let elements = tuple.elems.iter().map(|ty| self.python(ty, class)).collect::<Vec<_>>();
+                return format!("Tuple[{}]", elements.join(", "));
+            }
+            Type::Path(path) => &path.path,
+            _ => return "Any".into(),
+        };
+        let segment = match path.segments.last() {
+            Some(segment) => segment,
+            None => return "Any".into(),
+        };
+        let argument = match &segment.arguments {
+            syn::PathArguments::AngleBracketed(arguments) => arguments.args.iter().find_map(|argument| {
+                return match argument {
+                    syn::GenericArgument::Type(ty) => Some(self.python(ty, class)),
+                    _ => None,
+                };
+            }),
+            _ => None,
+        };
+        let argument = argument.unwrap_or_else(|| "Any".into());
+
+        let name = segment.ident.to_string();
+        return match name.as_str() {
+            "PyResult" | "Py" => argument,
+            "Option" => format!("Optional[{}]", argument),
+            "Vec" => format!("List[{}]", argument),
+            "f32" | "f64" => "float".into(),
+            "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "usize" | "isize" => "int".into(),
+            "bool" => "bool".into(),
+            "String" | "str" => "str".into(),
+            "EntityId" => format!("Union[{}, int]", self.class_name("PyEntity")),
+            "PyDict" => "Dict[str, Any]".into(),
+            "PyList" => "List[Any]".into(),
+            "Self" => class.into(),
+            _ => self.class_name(&name),
+        };
+    }
+
+    /** Get the python name of a rust class, `Any` for other types */
+    fn class_name(&self, rust_name: &str) -> String {
+        return self
+            .classes
+            .iter()
+            .find(|(rust, _)| rust == rust_name)
+            .map(|(_, name)| name.clone())
+            .unwrap_or_else(|| "Any".into());
+    }
+}
+
+/** Get the python name of a struct if it is a pyclass */
+fn pyclass_name(attrs: &[Attribute], rust_name: &str) -> Option<String> {
+    let attr = attrs.iter().find(|attr| attr.path.is_ident("pyclass"))?;
+    let name = match attr.parse_meta() {
+        Ok(Meta::List(list)) => list.nested.iter().find_map(|nested| {
+            return match nested {
+                NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("name") => match &value.lit {
+                    Lit::Str(name) => Some(name.value()),
+                    _ => None,
+                },
+                _ => None,
+            };
+        }),
+        _ => None,
+    };
+    return Some(name.unwrap_or_else(|| rust_name.into()));
+}
+
+/** Render the fields with pyo3 getters or setters */
+fn fields(fields: &Fields, types: &TypeNames) -> Vec<String> {
+    let mut result = vec![];
+    for field in fields.iter() {
+        let options = match field.attrs.iter().find(|attr| attr.path.is_ident("pyo3")).map(Attribute::parse_meta) {
+            Some(Ok(Meta::List(list))) => list.nested,
+            _ => continue,
+        };
+        let has_option = |option: &str| {
+            return options.iter().any(|nested| {
+                return matches!(nested, NestedMeta::Meta(Meta::Path(path)) if path.is_ident(option));
+            });
+        };
+        let name = match &field.ident {
+            Some(name) => name,
+            None => continue,
+        };
+        let python_type = types.python(&field.ty, "Any");
+        if has_option("set") {
+            result.push(format!("    {}: {}", name, python_type));
+        } else if has_option("get") {
+            result.push(format!("    @property\n    def {}(self) -> {}: ...", name, python_type));
+        }
+    }
+    return result;
+}
+
+/** Render a method of a pymethods block */
+fn method_stub(method: &ImplItemMethod, class: &str, types: &TypeNames) -> String {
+    let defaults = argument_defaults(&method.attrs);
+    let is_static = has_attr(&method.attrs, "staticmethod");
+    let is_new = has_attr(&method.attrs, "new");
+
+    let mut parameters = vec![];
+    if !is_static {
+        parameters.push("self".to_string());
+    }
+    for (name, ty) in arguments(method) {
+        let python_type = types.python(ty, class);
+        match defaults.iter().find(|(argument, _)| *argument == name) {
+            Some((_, default)) => parameters.push(format!("{}: {} = {}", name, python_type, default)),
+            None => parameters.push(format!("{}: {}", name, python_type)),
+        }
+    }
+
+    let (name, returns) = if is_new {
+        ("__init__".to_string(), "None".to_string())
+    } else {
+        (method.sig.ident.to_string(), return_type(method, class, types))
+    };
+    let mut result = String::new();
+    if is_static {
+        result += "    @staticmethod\n";
+    }
+    result += &format!("    def {}({}) -> {}:", name, parameters.join(", "), returns);
+    return result + &body(&doc(&method.attrs));
+}
+
+/** Render a method of a pyproto block, the protocols take the instance as the first argument */
+fn protocol_method(method: &ImplItemMethod, class: &str, types: &TypeNames) -> Vec<String> {
+    let name = method.sig.ident.to_string();
+    let mut arguments = arguments(method);
+    if !matches!(method.sig.inputs.first(), Some(FnArg::Receiver(_))) && !arguments.is_empty() {
+        arguments.remove(0);
+    }
+
+    // Python has a method for each comparison
+    if name == "__richcmp__" {
+        return vec![
+            "    def __eq__(self, other: object) -> bool: ...".into(),
+            "    def __ne__(self, other: object) -> bool: ...".into(),
+        ];
+    }
+    // A single argument is the other operand, like `__add__(self, other)`
+    let parameters = match arguments.as_slice() {
+        [] => "self".to_string(),
+        [(_, ty)] => format!("self, other: {}", types.python(ty, class)),
+        arguments => {
+            let arguments = arguments
+                .iter()
+                .map(|(name, ty)| format!("{}: {}", name, types.python(ty, class)))
+                .collect::<Vec<_>>();
+            format!("self, {}", arguments.join(", "))
+        }
+    };
+    let returns = return_type(method, class, types);
+    return vec![format!("    def {}({}) -> {}:{}", name, parameters, returns, body(&doc(&method.attrs)))];
+}
+
+/** Get the python arguments of a method, without the receiver and the gil token */
+fn arguments(method: &ImplItemMethod) -> Vec<(String, &Type)> {
+    return method
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|input| {
+            let input = match input {
+                FnArg::Typed(input) => input,
+                FnArg::Receiver(_) => return None,
+            };
+            let name = match &*input.pat {
+                Pat::Ident(pat) => pat.ident.to_string(),
+                _ => return None,
+            };
+            let is_gil = matches!(&*input.ty, Type::Path(path) if path.path.segments.last().map(|segment| segment.ident == "Python").unwrap_or(false));
+            if is_gil {
+                return None;
+            }
+            return Some((name, &*input.ty));
+        })
+        .collect();
+}
+
+/** Get the python annotation of the return type of a method */
+fn return_type(method: &ImplItemMethod, class: &str, types: &TypeNames) -> String {
+    return match &method.sig.output {
+        ReturnType::Default => "None".into(),
+        ReturnType::Type(_, ty) => types.python(ty, class),
+    };
+}
+
+/** Get the default values from the `#[args(name = "value")]` attribute of a method, as python literals */
+fn argument_defaults(attrs: &[Attribute]) -> Vec<(String, String)> {
+    let list = match attrs.iter().find(|attr| attr.path.is_ident("args")).map(Attribute::parse_meta) {
+        Some(Ok(Meta::List(list))) => list,
+        _ => return vec![],
+    };
+    return list
+        .nested
+        .iter()
+        .filter_map(|nested| {
+            return match nested {
+                NestedMeta::Meta(Meta::NameValue(value)) => match (&value.lit, value.path.get_ident()) {
+                    (Lit::Str(default), Some(name)) => {
+                        let default = match default.value().as_str() {
+                            "true" => "True".to_string(),
+                            "false" => "False".to_string(),
+                            value => value.to_string(),
+                        };
+                        Some((name.to_string(), default))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+        })
+        .collect();
+}
+
+/** Check if the attributes contain one with the name */
+fn has_attr(attrs: &[Attribute], name: &str) -> bool {
+    return attrs.iter().any(|attr| attr.path.is_ident(name));
+}
+
+/** Get the doc comment from the attributes, with the lines trimmed */
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| {
+            return match attr.parse_meta() {
+                Ok(Meta::NameValue(value)) => match value.lit {
+                    Lit::Str(doc) => Some(doc.value()),
+                    _ => None,
+                },
+                _ => None,
+            };
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let lines = lines.trim().lines().map(str::trim).collect::<Vec<_>>();
+    if lines.is_empty() {
+        return None;
+    }
+    return Some(lines.join("\n"));
+}
+
+/** Render the body of a method, its docstring or an ellipsis */
+fn body(doc: &Option<String>) -> String {
+    return match doc {
+        Some(doc) => format!("\n{}", docstring(doc, "        ")),
+        None => " ...".into(),
+    };
+}
+
+/** Render a docstring at the indentation */
+fn docstring(doc: &str, indent: &str) -> String {
+    // A quote at the end would run into the closing quotes
+    if !doc.contains('\n') && !doc.ends_with('"') {
+        return format!("{}\"\"\"{}\"\"\"", indent, doc);
+    }
+    let lines = doc.lines().map(|line| format!("{}{}", indent, line)).collect::<Vec<_>>();
+    return format!("{}\"\"\"\n{}\n{}\"\"\"", indent, lines.join("\n"), indent);
+}
@@ -1,3 +1,4 @@
+import ie
 
 SPEED = 10
 
@@ -10,25 +11,25 @@ def update(ctx, scene):
     # Control the entity if is has a position and a scale component
     for entity in scene["entities"]:
         if "position" in entity and "scale" in entity: 
-            if ctx.is_key_down("W"):
+            if ctx.is_key_down(ie.Key.W):
                 entity["position"]["y"] -= SPEED
-            elif ctx.is_key_down("S"):
+            elif ctx.is_key_down(ie.Key.S):
                 entity["position"]["y"] += SPEED
 
-            if ctx.is_key_down("A"):
+            if ctx.is_key_down(ie.Key.A):
                 entity["position"]["x"] -= SPEED
-            elif ctx.is_key_down("D"):
+            elif ctx.is_key_down(ie.Key.D):
                 entity["position"]["x"] += SPEED
     
     # Camera movement
-    if ctx.is_key_down("Up"):
+    if ctx.is_key_down(ie.Key.Up):
         scene["camera"]["position"]["y"] -= 10
-    elif ctx.is_key_down("Down"):
+    elif ctx.is_key_down(ie.Key.Down):
         scene["camera"]["position"]["y"] += 10
 
-    if ctx.is_key_down("Left"):
+    if ctx.is_key_down(ie.Key.Left):
         scene["camera"]["position"]["x"] -= 10
-    if ctx.is_key_down("Right"):
+    if ctx.is_key_down(ie.Key.Right):
         scene["camera"]["position"]["x"] += 10
     
     return scene
\ No newline at end of file
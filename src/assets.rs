@@ -1,10 +1,16 @@
 use std::path::PathBuf;
 
-use error::IslandError;
+use error::{ErrorKind, IslandError};
 use json::JsonValue;
 use macroquad::prelude::*;
 
-use crate::{error, error::IslandResult};
+use crate::{
+    atlas::{blit, Atlas, AtlasOptions, AtlasSprite},
+    ecs::{systems::JsonConvert, validate::Problems},
+    error,
+    error::IslandResult,
+    info,
+};
 
 /** The default sprite */
 const DEFAILT_SPRITE: &[u8] = include_bytes!("default_sprite.png");
@@ -12,13 +18,54 @@ const DEFAILT_SPRITE: &[u8] = include_bytes!("default_sprite.png");
 /** The singleton for storing sprites */
 static mut SPRITES: Option<hashbrown::HashMap<String, Texture2D>> = None;
 
+/** The singleton for storing the file and filter of each sprite in the asset descriptor */
+static mut SPRITE_FILES: Option<hashbrown::HashMap<String, (String, FilterMode)>> = None;
+
+/** The singleton for storing the atlas the sprites are packed into and the textures of its pages */
+static mut ATLAS: Option<(Atlas, Vec<Texture2D>)> = None;
+
+/** The singleton for storing the animation clips of the asset descriptor */
+static mut ANIMATIONS: Option<hashbrown::HashMap<String, AnimationClip>> = None;
+
+/** The singleton for storing the tilesets of the asset descriptor */
+static mut TILESETS: Option<hashbrown::HashMap<String, Tileset>> = None;
+
 /** Get the sprites singleton */
 pub fn sprites() -> &'static mut hashbrown::HashMap<String, Texture2D> {
     unsafe {
-        if SPRITES.is_none() {
-            SPRITES = Some(hashbrown::HashMap::new());
-        }
-        return SPRITES.as_mut().unwrap();
+        let sprites = &mut *std::ptr::addr_of_mut!(SPRITES);
+        return sprites.get_or_insert_with(hashbrown::HashMap::new);
+    };
+}
+
+/** Get the file and filter of each sprite in the asset descriptor, by sprite name */
+pub fn sprite_files() -> &'static mut hashbrown::HashMap<String, (String, FilterMode)> {
+    unsafe {
+        let sprite_files = &mut *std::ptr::addr_of_mut!(SPRITE_FILES);
+        return sprite_files.get_or_insert_with(hashbrown::HashMap::new);
+    };
+}
+
+/** Get the atlas singleton, none if the asset descriptor doesn't pack the sprites */
+fn atlas() -> &'static mut Option<(Atlas, Vec<Texture2D>)> {
+    unsafe {
+        return &mut *std::ptr::addr_of_mut!(ATLAS);
+    };
+}
+
+/** Get the animation clips of the asset descriptor, by clip name */
+pub fn animations() -> &'static mut hashbrown::HashMap<String, AnimationClip> {
+    unsafe {
+        let animations = &mut *std::ptr::addr_of_mut!(ANIMATIONS);
+        return animations.get_or_insert_with(hashbrown::HashMap::new);
+    };
+}
+
+/** Get the tilesets of the asset descriptor, by tileset name */
+pub fn tilesets() -> &'static mut hashbrown::HashMap<String, Tileset> {
+    unsafe {
+        let tilesets = &mut *std::ptr::addr_of_mut!(TILESETS);
+        return tilesets.get_or_insert_with(hashbrown::HashMap::new);
     };
 }
 
@@ -28,16 +75,11 @@ where
     T: Into<PathBuf> + Clone,
 {
     if let Some(asset_descriptor) = asset_descriptor {
-        // Read the asset descriptor
-        let asset_descriptor = std::fs::read_to_string(asset_descriptor.into()).map_err(|e| {
-            return IslandError::new(format!("Failed to read asset descriptor: {}", e));
-        })?;
-        let asset_descriptor = json::parse(&asset_descriptor).map_err(|e| {
-            return IslandError::new(format!("Failed to parse asset descriptor: {}", e));
-        })?;
+        let asset_descriptor = read_asset_descriptor(asset_descriptor)?;
 
-        // Load the sprites
+        // Load the sprites, animations and tilesets
         load_sprites(Some(&asset_descriptor)).await?;
+        load_headless_parts(&asset_descriptor)?;
         return Ok(());
     }
 
@@ -47,13 +89,323 @@ where
     return Ok(());
 }
 
-/** Load the sprites */
+/** Load only the animation clips and tilesets of the asset descriptor, they don't need a window unlike the sprites */
+pub(crate) fn load_headless_assets<T>(asset_descriptor: T) -> IslandResult<()>
+where
+    T: Into<PathBuf> + Clone,
+{
+    return load_headless_parts(&read_asset_descriptor(asset_descriptor)?);
+}
+
+/** Load the parts of a read asset descriptor that don't need a window */
+fn load_headless_parts(asset_descriptor: &JsonValue) -> IslandResult<()> {
+    load_animations(asset_descriptor)?;
+    load_tilesets(asset_descriptor)?;
+    return Ok(());
+}
+
+/** Read, parse and check the asset descriptor */
+fn read_asset_descriptor<T>(asset_descriptor: T) -> IslandResult<JsonValue>
+where
+    T: Into<PathBuf> + Clone,
+{
+    let asset_descriptor = std::fs::read_to_string(asset_descriptor.into()).map_err(|e| {
+        return IslandError::with_source(ErrorKind::Io, "Failed to read asset descriptor", e);
+    })?;
+    let asset_descriptor = json::parse(&asset_descriptor).map_err(|e| {
+        return IslandError::with_source(ErrorKind::Json, "Failed to parse asset descriptor", e);
+    })?;
+    validate_asset_descriptor(&asset_descriptor).into_result(ErrorKind::Asset, "Asset descriptor")?;
+    return Ok(asset_descriptor);
+}
+
+/** Check the shape of the asset descriptor */
+fn validate_asset_descriptor(asset_descriptor: &JsonValue) -> Problems {
+    let mut problems = Problems::new();
+    if !asset_descriptor.is_object() {
+        problems.push("asset descriptor", "expected an object");
+        return problems;
+    }
+    if asset_descriptor.has_key("sprites") && !asset_descriptor["sprites"].is_array() {
+        problems.push("sprites", "expected a list of sprites");
+    }
+    for (i, sprite) in asset_descriptor["sprites"].members().enumerate() {
+        let path = format!("sprites[{}]", i);
+        if !sprite.is_object() {
+            problems.push(&path, "expected a sprite object");
+            continue;
+        }
+        problems.expect_string(&format!("{}.name", path), &sprite["name"]);
+        problems.expect_string(&format!("{}.path", path), &sprite["path"]);
+        match sprite["filter"].as_str() {
+            Some("linear") | Some("nearest") => {}
+            _ => problems.push(
+                &format!("{}.filter", path),
+                format!("expected \"linear\" or \"nearest\", found {}", sprite["filter"].dump()),
+            ),
+        }
+    }
+    if asset_descriptor.has_key("tilesets") && !asset_descriptor["tilesets"].is_array() {
+        problems.push("tilesets", "expected a list of tilesets");
+    }
+    for (i, tileset) in asset_descriptor["tilesets"].members().enumerate() {
+        validate_tileset(&mut problems, &format!("tilesets[{}]", i), tileset);
+    }
+    let atlas = &asset_descriptor["atlas"];
+    if atlas.is_object() {
+        for field in ["page_size", "padding"].iter() {
+            if !atlas[*field].is_null() && atlas[*field].as_u32().is_none() {
+                problems.push(
+                    &format!("atlas.{}", field),
+                    format!("expected a whole number, found {}", atlas[*field].dump()),
+                );
+            }
+        }
+        // Pages are uploaded as textures, which can't be larger than u16::MAX
+        match atlas["page_size"].as_u32() {
+            Some(0) => problems.push("atlas.page_size", "expected a number above 0"),
+            Some(size) if size > u16::MAX as u32 => {
+                problems.push("atlas.page_size", format!("expected at most {}, found {}", u16::MAX, size))
+            }
+            _ => {}
+        }
+    } else if !atlas.is_null() && !atlas.is_boolean() {
+        problems.push("atlas", format!("expected a bool or atlas object, found {}", atlas.dump()));
+    }
+    if asset_descriptor.has_key("animations") && !asset_descriptor["animations"].is_array() {
+        problems.push("animations", "expected a list of animations");
+    }
+    for (i, animation) in asset_descriptor["animations"].members().enumerate() {
+        validate_animation_clip(&mut problems, &format!("animations[{}]", i), animation);
+    }
+    return problems;
+}
+
+/** Check the shape of an animation clip */
+fn validate_animation_clip(problems: &mut Problems, path: &str, animation: &JsonValue) {
+    if !animation.is_object() {
+        problems.push(path, "expected an animation object");
+        return;
+    }
+    problems.expect_string(&format!("{}.name", path), &animation["name"]);
+    if !animation["fps"].as_f32().map(|fps| fps > 0.0).unwrap_or(false) {
+        problems.push(
+            &format!("{}.fps", path),
+            format!("expected a number above 0, found {}", animation["fps"].dump()),
+        );
+    }
+    if !animation["mode"].is_null() && animation["mode"].as_str().and_then(AnimationMode::from_name).is_none() {
+        problems.push(
+            &format!("{}.mode", path),
+            format!("expected \"loop\", \"ping_pong\" or \"once\", found {}", animation["mode"].dump()),
+        );
+    }
+    match (animation.has_key("grid"), animation.has_key("frames")) {
+        (true, false) => {
+            let grid_path = format!("{}.grid", path);
+            problems.expect_fields(&grid_path, &animation["grid"], &["x", "y", "w", "h"]);
+            for field in ["columns", "count"].iter() {
+                let value = &animation["grid"][*field];
+                if !value.as_usize().map(|value| value > 0).unwrap_or(false) {
+                    problems.push(
+                        &format!("{}.{}", grid_path, field),
+                        format!("expected a whole number above 0, found {}", value.dump()),
+                    );
+                }
+            }
+        }
+        (false, true) => {
+            let frames = &animation["frames"];
+            if !frames.is_array() || frames.is_empty() {
+                problems.push(&format!("{}.frames", path), "expected a list of at least one rect");
+            }
+            for (i, frame) in frames.members().enumerate() {
+                problems.expect_fields(&format!("{}.frames[{}]", path, i), frame, &["x", "y", "w", "h"]);
+            }
+        }
+        _ => problems.push(path, "expected either a frame grid or a list of frames"),
+    }
+}
+
+/** Check the shape of a tileset */
+fn validate_tileset(problems: &mut Problems, path: &str, tileset: &JsonValue) {
+    if !tileset.is_object() {
+        problems.push(path, "expected a tileset object");
+        return;
+    }
+    problems.expect_string(&format!("{}.name", path), &tileset["name"]);
+    problems.expect_string(&format!("{}.sprite", path), &tileset["sprite"]);
+    problems.expect_fields(&format!("{}.tile_size", path), &tileset["tile_size"], &["x", "y"]);
+    for field in ["columns", "count"].iter() {
+        if !tileset[*field].as_u32().map(|value| value > 0).unwrap_or(false) {
+            problems.push(
+                &format!("{}.{}", path, field),
+                format!("expected a whole number above 0, found {}", tileset[*field].dump()),
+            );
+        }
+    }
+    problems.expect_optional_number(&format!("{}.margin", path), &tileset["margin"]);
+    problems.expect_optional_number(&format!("{}.spacing", path), &tileset["spacing"]);
+    if !tileset["tiles"].is_null() && !tileset["tiles"].is_object() {
+        problems.push(
+            &format!("{}.tiles", path),
+            format!("expected an object of tile properties by tile id, found {}", tileset["tiles"].dump()),
+        );
+    }
+    for (id, properties) in tileset["tiles"].entries() {
+        let tile_path = format!("{}.tiles.{}", path, id);
+        if id.parse::<u32>().is_err() {
+            problems.push(&tile_path, "expected the key to be a tile id");
+        }
+        if !properties.is_object() {
+            problems.push(&tile_path, format!("expected an object of properties, found {}", properties.dump()));
+        }
+    }
+}
+
+/** How an animation clip plays */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationMode {
+    /** Start over after the last frame */
+    Loop,
+    /** Play backwards after the last frame, then forwards again after the first */
+    PingPong,
+    /** Stop on the last frame */
+    Once,
+}
+
+impl AnimationMode {
+    /** Get the mode from its name in the asset descriptor */
+    pub fn from_name(name: &str) -> Option<Self> {
+        return match name {
+            "loop" => Some(Self::Loop),
+            "ping_pong" => Some(Self::PingPong),
+            "once" => Some(Self::Once),
+            _ => None,
+        };
+    }
+}
+
+/** An animation clip from the asset descriptor, the frames are source rects of the entity's sprite */
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub frames: Vec<Rect>,
+    pub fps: f32,
+    pub mode: AnimationMode,
+}
+
+impl AnimationClip {
+    /**
+    Read a clip from its json in the asset descriptor.
+    A grid has `columns` frames of `w` by `h` per row starting at `x` and `y`, read left to right then top to bottom.
+    */
+    pub fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let frames = if json.has_key("grid") {
+            let grid = &json["grid"];
+            let cell = grid.as_rect()?;
+            let columns = grid["columns"].as_usize().unwrap_or(1).max(1);
+            (0..grid["count"].as_usize().unwrap_or(1))
+                .map(|i| {
+                    let (column, row) = ((i % columns) as f32, (i / columns) as f32);
+                    return Rect::new(cell.x + column * cell.w, cell.y + row * cell.h, cell.w, cell.h);
+                })
+                .collect()
+        } else {
+            json["frames"].members().map(|frame| frame.as_rect()).collect::<IslandResult<Vec<_>>>()?
+        };
+        return Ok(Self {
+            frames,
+            fps: json["fps"].as_f32().unwrap_or(1.0),
+            mode: json["mode"].as_str().and_then(AnimationMode::from_name).unwrap_or(AnimationMode::Loop),
+        });
+    }
+}
+
+/**
+A tileset from the asset descriptor, a sprite cut into a grid of tiles.
+Tiles are numbered from 0, left to right then top to bottom.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tileset {
+    // The name of the sprite the tiles are cut from
+    pub sprite: String,
+    // The size of a tile on the sprite
+    pub tile_size: Vec2,
+    pub columns: u32,
+    // The amount of tiles
+    pub count: u32,
+    // The pixels around the grid
+    pub margin: f32,
+    // The pixels between the tiles
+    pub spacing: f32,
+    // The properties of the tiles that have any, like `collision`, by tile id
+    pub tiles: hashbrown::HashMap<u32, JsonValue>,
+}
+
+impl Tileset {
+    /** Read a tileset from its json in the asset descriptor */
+    pub fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let sprite = json["sprite"]
+            .as_str()
+            .ok_or_else(|| IslandError::with_kind(ErrorKind::Asset, format!("Tileset has no sprite: {}", json)))?;
+        let mut tiles = hashbrown::HashMap::new();
+        for (id, properties) in json["tiles"].entries() {
+            let id = id.parse::<u32>().map_err(|e| {
+                return IslandError::with_source(ErrorKind::Asset, format!("Invalid tile id \"{}\"", id), e);
+            })?;
+            tiles.insert(id, properties.clone());
+        }
+        return Ok(Self {
+            sprite: sprite.into(),
+            tile_size: json["tile_size"].as_vec2()?,
+            columns: json["columns"].as_u32().unwrap_or(1).max(1),
+            count: json["count"].as_u32().unwrap_or(0),
+            margin: json["margin"].as_f32().unwrap_or(0.0),
+            spacing: json["spacing"].as_f32().unwrap_or(0.0),
+            tiles,
+        });
+    }
+
+    /** Get the part of the sprite that is the tile */
+    pub fn source_rect(&self, id: u32) -> Rect {
+        let (column, row) = ((id % self.columns) as f32, (id / self.columns) as f32);
+        return Rect::new(
+            self.margin + column * (self.tile_size.x + self.spacing),
+            self.margin + row * (self.tile_size.y + self.spacing),
+            self.tile_size.x,
+            self.tile_size.y,
+        );
+    }
+
+    /** Get the properties of the tile, none if it has none */
+    pub fn properties(&self, id: u32) -> Option<&JsonValue> {
+        return self.tiles.get(&id);
+    }
+
+    /** Check if the tile has its `collision` property set */
+    pub fn is_solid(&self, id: u32) -> bool {
+        return self
+            .properties(id)
+            .and_then(|properties| properties["collision"].as_bool())
+            .unwrap_or(false);
+    }
+}
+
+/**
+Load the sprites.
+If the asset descriptor has an `atlas`, the sprites are packed into atlas pages instead of a texture each.
+*/
 async fn load_sprites(asset_descriptor: Option<&JsonValue>) -> IslandResult<()> {
     let mut sprites = hashbrown::HashMap::new();
+    let mut atlas_sprites = vec![];
     // Add default sprite
     let default = Texture2D::from_file_with_format(DEFAILT_SPRITE, None);
     default.set_filter(FilterMode::Nearest);
     sprites.insert("default_sprite".into(), default);
+    let atlas_options = asset_descriptor
+        .map(|asset_descriptor| &asset_descriptor["atlas"])
+        .filter(|atlas| atlas.is_object() || atlas.as_bool() == Some(true))
+        .map(AtlasOptions::from_json);
     if let Some(asset_descriptor) = asset_descriptor {
         // Get the sprites values
         let sprites_descripor = asset_descriptor["sprites"].members();
@@ -72,9 +424,9 @@ async fn load_sprites(asset_descriptor: Option<&JsonValue>) -> IslandResult<()>
 
             if let (Ok(name), Ok(path), Ok(filter)) = (name, path, filter) {
                 // Decide the filter
-                let filter = match filter {
-                    &"linear" => FilterMode::Linear,
-                    &"nearest" => FilterMode::Nearest,
+                let filter = match *filter {
+                    "linear" => FilterMode::Linear,
+                    "nearest" => FilterMode::Nearest,
                     _ => {
                         error!("Invalid filter, defaulting to nearest");
                         FilterMode::Nearest
@@ -82,15 +434,69 @@ async fn load_sprites(asset_descriptor: Option<&JsonValue>) -> IslandResult<()>
                 };
 
                 // Add the sprite
-                sprites.insert((*name).into(), load_sprite(path, filter).await?);
+                if atlas_options.is_some() {
+                    atlas_sprites.push(AtlasSprite {
+                        name: (*name).into(),
+                        image: load_sprite_image(path).await,
+                        filter,
+                    });
+                } else {
+                    sprites.insert((*name).into(), load_sprite(path, filter).await?);
+                }
+                sprite_files().insert((*name).into(), ((*path).into(), filter));
             }
         }
     }
+
+    // Pack the atlas and upload its pages
+    *atlas() = atlas_options.map(|options| {
+        let atlas = Atlas::pack(atlas_sprites, options);
+        let textures = atlas
+            .pages
+            .iter()
+            .map(|page| {
+                let texture =
+                    Texture2D::from_rgba8(page.image.width() as u16, page.image.height() as u16, page.image.as_raw());
+                texture.set_filter(page.filter);
+                return texture;
+            })
+            .collect::<Vec<_>>();
+        info!("Packed {} sprites into {} atlas pages", atlas.regions.len(), atlas.pages.len());
+        return (atlas, textures);
+    });
     // Set the singleton
     unsafe { SPRITES = Some(sprites) };
     return Ok(());
 }
 
+/** Load the tilesets, replacing the ones that were loaded before */
+fn load_tilesets(asset_descriptor: &JsonValue) -> IslandResult<()> {
+    let mut loaded = hashbrown::HashMap::new();
+    for tileset in asset_descriptor["tilesets"].members() {
+        let name = tileset["name"].as_str().unwrap_or_default();
+        let tileset = Tileset::from_json(tileset).map_err(|e| {
+            return e.context(format!("Failed to load tileset \"{}\"", name));
+        })?;
+        loaded.insert(name.to_string(), tileset);
+    }
+    *tilesets() = loaded;
+    return Ok(());
+}
+
+/** Load the animation clips, replacing the ones that were loaded before */
+fn load_animations(asset_descriptor: &JsonValue) -> IslandResult<()> {
+    let mut clips = hashbrown::HashMap::new();
+    for animation in asset_descriptor["animations"].members() {
+        let name = animation["name"].as_str().unwrap_or_default();
+        let clip = AnimationClip::from_json(animation).map_err(|e| {
+            return e.context(format!("Failed to load animation \"{}\"", name));
+        })?;
+        clips.insert(name.to_string(), clip);
+    }
+    *animations() = clips;
+    return Ok(());
+}
+
 /** A wrapper function for laoding a sprote */
 async fn load_sprite(path: &str, filter: FilterMode) -> IslandResult<Texture2D> {
     if let Ok(texture) = load_texture(path).await {
@@ -101,11 +507,117 @@ async fn load_sprite(path: &str, filter: FilterMode) -> IslandResult<Texture2D>
     return Ok(Texture2D::from_file_with_format(DEFAILT_SPRITE, None));
 }
 
+/** Load and decode the image of a sprite for the atlas, the default sprite if it can't be */
+async fn load_sprite_image(path: &str) -> image::RgbaImage {
+    let image = load_file(path)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| image::load_from_memory(&bytes).map_err(|e| e.to_string()));
+    return match image {
+        Ok(image) => image.to_rgba8(),
+        Err(e) => {
+            error!("Failed to load sprite \"{}\": {}", path, e);
+            image::load_from_memory(DEFAILT_SPRITE).unwrap().to_rgba8()
+        }
+    };
+}
+
+/**
+Reload a sprite from its file in the asset descriptor.
+The old texture is kept if the file can't be read or decoded, like while it is still being written.
+*/
+pub fn reload_sprite(name: &str) -> IslandResult<()> {
+    let (path, filter) = sprite_files().get(name).cloned().ok_or_else(|| {
+        return IslandError::with_kind(ErrorKind::Asset, format!("No sprite file for \"{}\"", name));
+    })?;
+    let bytes = std::fs::read(&path).map_err(|e| {
+        return IslandError::with_source(ErrorKind::Io, format!("Failed to read sprite \"{}\"", path), e);
+    })?;
+    let image = image::load_from_memory(&bytes)
+        .map_err(|e| {
+            return IslandError::with_source(ErrorKind::Asset, format!("Failed to decode sprite \"{}\"", path), e);
+        })?
+        .to_rgba8();
+
+    // Draw over the sprite on its atlas page if it still fits, otherwise it gets a texture of its own
+    if let Some((atlas, textures)) = atlas() {
+        if let Some(region) = atlas.regions.get(name).copied() {
+            if region.rect.w == image.width() as f32 && region.rect.h == image.height() as f32 {
+                let page = &mut atlas.pages[region.page].image;
+                blit(page, &image, region.rect.x as u32, region.rect.y as u32, atlas.options.padding);
+                textures[region.page].update(&Image {
+                    bytes: page.as_raw().clone(),
+                    width: page.width() as u16,
+                    height: page.height() as u16,
+                });
+                info!("Reloaded sprite \"{}\"", name);
+                return Ok(());
+            }
+            atlas.regions.remove(name);
+        }
+    }
+
+    // Swap the texture, sprite components look it up by name every frame
+    let texture = Texture2D::from_rgba8(image.width() as u16, image.height() as u16, &image.into_raw());
+    texture.set_filter(filter);
+    if let Some(old) = sprites().insert(name.into(), texture) {
+        old.delete();
+    }
+    info!("Reloaded sprite \"{}\"", name);
+    return Ok(());
+}
+
+/** A sprite to draw, the texture it is on and the part of the texture that is the sprite */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteHandle {
+    pub texture: Texture2D,
+    // The pixels of the sprite on the texture, all of it unless the sprite is on an atlas page
+    pub rect: Rect,
+}
+
+impl SpriteHandle {
+    /** Get the rect to draw for a source rect relative to the sprite, the whole sprite if none */
+    pub fn source(&self, source_rec: Option<Rect>) -> Rect {
+        return match source_rec {
+            Some(source) => Rect::new(self.rect.x + source.x, self.rect.y + source.y, source.w, source.h),
+            None => self.rect,
+        };
+    }
+}
+
 /** Get a sprite */
-pub fn get_sprite(name: &str) -> &'static Texture2D {
-    if let Some(texture) = sprites().get(name) {
-        return texture;
+pub fn get_sprite(name: &str) -> SpriteHandle {
+    if let Some((atlas, textures)) = atlas() {
+        if let Some(region) = atlas.regions.get(name) {
+            return SpriteHandle {
+                texture: textures[region.page],
+                rect: region.rect,
+            };
+        }
+    }
+    let texture = match sprites().get(name) {
+        Some(texture) => *texture,
+        None => {
+            error!("No such sprite: {}", name);
+            *sprites().get("default_sprite").unwrap()
+        }
+    };
+    return SpriteHandle {
+        texture,
+        rect: Rect::new(0.0, 0.0, texture.width(), texture.height()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atlas_pages_have_to_fit_in_a_texture() {
+        for (page_size, valid) in [(0, false), (1, true), (65535, true), (65536, false)].iter() {
+            let descriptor = json::object! { atlas: { page_size: *page_size } };
+            let result = validate_asset_descriptor(&descriptor).into_result(ErrorKind::Asset, "Asset descriptor");
+            assert_eq!(result.is_ok(), *valid, "page size {}", page_size);
+        }
     }
-    error!("No such sprite: {}", name);
-    return sprites().get("default_sprite").unwrap();
 }
@@ -0,0 +1,326 @@
+//! Packs sprites into atlas pages on the CPU, so they can be drawn without switching textures
+use image::RgbaImage;
+use json::JsonValue;
+use macroquad::prelude::{FilterMode, Rect};
+
+/** The settings of the atlas packer */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasOptions {
+    // The largest width and height of a page in pixels
+    pub page_size: u32,
+    // The pixels around each sprite, filled with its edge so filtering doesn't bleed in the neighbours
+    pub padding: u32,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        return Self {
+            page_size: 2048,
+            padding: 1,
+        };
+    }
+}
+
+impl AtlasOptions {
+    /** Read the options from the `atlas` field of the asset descriptor, `true` uses the defaults */
+    pub fn from_json(json: &JsonValue) -> Self {
+        let default = Self::default();
+        return Self {
+            page_size: json["page_size"].as_u32().unwrap_or(default.page_size),
+            padding: json["padding"].as_u32().unwrap_or(default.padding),
+        };
+    }
+}
+
+/** A sprite to pack */
+#[derive(Debug, Clone)]
+pub struct AtlasSprite {
+    pub name: String,
+    pub image: RgbaImage,
+    // Sprites are only packed on a page with the same filter
+    pub filter: FilterMode,
+}
+
+/** A packed page of the atlas */
+#[derive(Debug, Clone)]
+pub struct AtlasPage {
+    pub image: RgbaImage,
+    pub filter: FilterMode,
+}
+
+/** Where a sprite is in the atlas */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    // The index of the page
+    pub page: usize,
+    // The pixels of the sprite on the page, without the padding
+    pub rect: Rect,
+}
+
+/** Sprites packed into pages */
+#[derive(Debug, Clone, Default)]
+pub struct Atlas {
+    // The options the sprites were packed with
+    pub options: AtlasOptions,
+    pub pages: Vec<AtlasPage>,
+    pub regions: hashbrown::HashMap<String, AtlasRegion>,
+}
+
+impl Atlas {
+    /**
+    Pack the sprites into as few pages as the page size allows, placing them on shelves from the tallest down.
+    Sprites with different filters go on different pages, and a sprite larger than a page gets a page of its own.
+    Pages are cropped to the sprites on them. The layout only depends on the sizes, names and filters of the sprites.
+    */
+    pub fn pack(sprites: Vec<AtlasSprite>, options: AtlasOptions) -> Self {
+        let mut atlas = Self {
+            options,
+            ..Default::default()
+        };
+
+        // Group the sprites by filter, in the order the filters first appear
+        let mut groups: Vec<(FilterMode, Vec<AtlasSprite>)> = vec![];
+        for sprite in sprites {
+            match groups.iter_mut().find(|(filter, _)| *filter == sprite.filter) {
+                Some((_, group)) => group.push(sprite),
+                None => groups.push((sprite.filter, vec![sprite])),
+            }
+        }
+
+        for (filter, mut group) in groups {
+            group.sort_by(|a, b| {
+                return b
+                    .image
+                    .height()
+                    .cmp(&a.image.height())
+                    .then(b.image.width().cmp(&a.image.width()))
+                    .then(a.name.cmp(&b.name));
+            });
+
+            // Place the sprites, then draw the pages now that their sizes are known
+            let first_page = atlas.pages.len();
+            let mut pages: Vec<PageLayout> = vec![];
+            let mut placed = vec![];
+            for sprite in group.iter() {
+                let (width, height) = (
+                    sprite.image.width() + 2 * options.padding,
+                    sprite.image.height() + 2 * options.padding,
+                );
+                let (page, x, y) = place(&mut pages, width, height, options.page_size);
+                placed.push((page, x + options.padding, y + options.padding));
+            }
+            for page in pages.iter() {
+                atlas.pages.push(AtlasPage {
+                    image: RgbaImage::new(page.width.max(1), page.height.max(1)),
+                    filter,
+                });
+            }
+            for (sprite, (page, x, y)) in group.into_iter().zip(placed) {
+                let page = first_page + page;
+                blit(&mut atlas.pages[page].image, &sprite.image, x, y, options.padding);
+                atlas.regions.insert(sprite.name, AtlasRegion {
+                    page,
+                    rect: Rect::new(x as f32, y as f32, sprite.image.width() as f32, sprite.image.height() as f32),
+                });
+            }
+        }
+        return atlas;
+    }
+}
+
+/** The shelves of a page being packed */
+#[derive(Debug, Default)]
+struct PageLayout {
+    shelves: Vec<Shelf>,
+    // The used size of the page
+    width: u32,
+    height: u32,
+    // If the page holds a single sprite larger than the page size
+    oversized: bool,
+}
+
+/** A row of sprites on a page */
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    // The x the next sprite on the shelf goes at
+    x: u32,
+}
+
+/** Find a spot for a padded sprite, adding a shelf or page if none fits. Returns the page and top left corner. */
+fn place(pages: &mut Vec<PageLayout>, width: u32, height: u32, page_size: u32) -> (usize, u32, u32) {
+    if width > page_size || height > page_size {
+        pages.push(PageLayout {
+            width,
+            height,
+            oversized: true,
+            ..Default::default()
+        });
+        return (pages.len() - 1, 0, 0);
+    }
+
+    for (index, page) in pages.iter_mut().enumerate().filter(|(_, page)| !page.oversized) {
+        // Fit the sprite next to the others on a shelf
+        if let Some(shelf) = page
+            .shelves
+            .iter_mut()
+            .find(|shelf| shelf.height >= height && shelf.x + width <= page_size)
+        {
+            let x = shelf.x;
+            shelf.x += width;
+            page.width = page.width.max(shelf.x);
+            return (index, x, shelf.y);
+        }
+        // Start a shelf below the others
+        if page.height + height <= page_size {
+            let y = page.height;
+            page.shelves.push(Shelf { y, height, x: width });
+            page.width = page.width.max(width);
+            page.height += height;
+            return (index, 0, y);
+        }
+    }
+
+    pages.push(PageLayout {
+        shelves: vec![Shelf {
+            y: 0,
+            height,
+            x: width,
+        }],
+        width,
+        height,
+        oversized: false,
+    });
+    return (pages.len() - 1, 0, 0);
+}
+
+/** Copy an image onto a page at x and y, extending its edge pixels into the padding around it */
+pub(crate) fn blit(page: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32, padding: u32) {
+    if image.width() == 0 || image.height() == 0 {
+        return;
+    }
+    for offset_y in 0..image.height() + 2 * padding {
+        for offset_x in 0..image.width() + 2 * padding {
+            let source_x = offset_x.saturating_sub(padding).min(image.width() - 1);
+            let source_y = offset_y.saturating_sub(padding).min(image.height() - 1);
+            page.put_pixel(
+                x + offset_x - padding,
+                y + offset_y - padding,
+                *image.get_pixel(source_x, source_y),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /** A sprite of one color */
+    fn sprite(name: &str, width: u32, height: u32, filter: FilterMode) -> AtlasSprite {
+        return AtlasSprite {
+            name: name.into(),
+            image: RgbaImage::from_pixel(width, height, Rgba([width as u8, height as u8, 0, 255])),
+            filter,
+        };
+    }
+
+    fn options(page_size: u32, padding: u32) -> AtlasOptions {
+        return AtlasOptions { page_size, padding };
+    }
+
+    #[test]
+    fn sprites_go_on_shelves_from_the_tallest_down() {
+        let sprites = vec![
+            sprite("small", 4, 4, FilterMode::Nearest),
+            sprite("tall", 4, 8, FilterMode::Nearest),
+            sprite("wide", 8, 4, FilterMode::Nearest),
+            sprite("medium", 6, 6, FilterMode::Nearest),
+        ];
+        let atlas = Atlas::pack(sprites, options(16, 0));
+        let rect = |name: &str| atlas.regions[name].rect;
+
+        assert_eq!(atlas.pages.len(), 1);
+        assert_eq!(rect("tall"), Rect::new(0.0, 0.0, 4.0, 8.0));
+        assert_eq!(rect("medium"), Rect::new(4.0, 0.0, 6.0, 6.0));
+        // The wide sprite doesn't fit next to the others, so it starts a shelf
+        assert_eq!(rect("wide"), Rect::new(0.0, 8.0, 8.0, 4.0));
+        assert_eq!(rect("small"), Rect::new(10.0, 0.0, 4.0, 4.0));
+        // The page is cropped to the sprites
+        assert_eq!(atlas.pages[0].image.dimensions(), (14, 12));
+    }
+
+    #[test]
+    fn sprites_that_do_not_fit_spill_onto_a_new_page() {
+        let sprites = (0..5).map(|i| sprite(&i.to_string(), 8, 8, FilterMode::Nearest)).collect();
+        let atlas = Atlas::pack(sprites, options(16, 0));
+
+        assert_eq!(atlas.pages.len(), 2);
+        let pages = (0..5).map(|i| atlas.regions[&i.to_string()].page).collect::<Vec<_>>();
+        assert_eq!(pages, [0, 0, 0, 0, 1]);
+        assert_eq!(atlas.regions["4"].rect, Rect::new(0.0, 0.0, 8.0, 8.0));
+        assert_eq!(atlas.pages[1].image.dimensions(), (8, 8));
+    }
+
+    #[test]
+    fn sprites_are_grouped_by_filter() {
+        let sprites = vec![
+            sprite("nearest", 4, 4, FilterMode::Nearest),
+            sprite("linear", 4, 4, FilterMode::Linear),
+            sprite("other nearest", 4, 4, FilterMode::Nearest),
+        ];
+        let atlas = Atlas::pack(sprites, options(64, 0));
+
+        assert_eq!(atlas.pages.len(), 2);
+        assert_eq!(atlas.pages[0].filter, FilterMode::Nearest);
+        assert_eq!(atlas.pages[1].filter, FilterMode::Linear);
+        assert_eq!(atlas.regions["nearest"].page, 0);
+        assert_eq!(atlas.regions["other nearest"].page, 0);
+        assert_eq!(atlas.regions["linear"].page, 1);
+    }
+
+    #[test]
+    fn the_padding_repeats_the_edge_of_the_sprite() {
+        let mut image = RgbaImage::new(2, 2);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 0, Rgba([0, 255, 0, 255]));
+        image.put_pixel(0, 1, Rgba([0, 0, 255, 255]));
+        image.put_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        let sprites = vec![AtlasSprite {
+            name: "sprite".into(),
+            image: image.clone(),
+            filter: FilterMode::Linear,
+        }];
+        let atlas = Atlas::pack(sprites, options(64, 2));
+        let page = &atlas.pages[0].image;
+
+        assert_eq!(atlas.regions["sprite"].rect, Rect::new(2.0, 2.0, 2.0, 2.0));
+        assert_eq!(page.dimensions(), (6, 6));
+        for y in 0..6 {
+            for x in 0..6 {
+                let source = image.get_pixel(x.clamp(2, 3) - 2, y.clamp(2, 3) - 2);
+                assert_eq!(page.get_pixel(x, y), source, "pixel {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn sprites_larger_than_a_page_get_a_page_of_their_own() {
+        let sprites = vec![
+            sprite("small", 4, 4, FilterMode::Nearest),
+            sprite("huge", 40, 10, FilterMode::Nearest),
+            sprite("other small", 4, 4, FilterMode::Nearest),
+        ];
+        let atlas = Atlas::pack(sprites, options(16, 1));
+
+        assert_eq!(atlas.pages.len(), 2);
+        let huge = atlas.regions["huge"];
+        assert_eq!(huge.rect, Rect::new(1.0, 1.0, 40.0, 10.0));
+        assert_eq!(atlas.pages[huge.page].image.dimensions(), (42, 12));
+        // Nothing else is packed next to it
+        assert_ne!(atlas.regions["small"].page, huge.page);
+        assert_eq!(atlas.regions["small"].page, atlas.regions["other small"].page);
+    }
+}
@@ -0,0 +1,34 @@
+//! Writes the python type stubs of the `ie` module, so editors and mypy can check scripts
+//!
+//! `cargo run --bin ie-stubs -- [directory]` writes `ie.pyi` into the directory, `stubs` by default.
+//! With `--check` nothing is written, it fails if the stubs in the directory are out of date.
+use std::{path::PathBuf, process};
+
+use ie::ecs::python::STUBS;
+
+fn main() {
+    let mut check = false;
+    let mut directory = PathBuf::from("stubs");
+    for argument in std::env::args().skip(1) {
+        match argument.as_str() {
+            "--check" => check = true,
+            _ => directory = argument.into(),
+        }
+    }
+    let path = directory.join("ie.pyi");
+
+    if check {
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(STUBS) {
+            eprintln!("{} is out of date, run `cargo run --bin ie-stubs`", path.display());
+            process::exit(1);
+        }
+        return;
+    }
+
+    let result = std::fs::create_dir_all(&directory).and_then(|_| std::fs::write(&path, STUBS));
+    if let Err(e) = result {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+        process::exit(1);
+    }
+    println!("Wrote {}", path.display());
+}
@@ -0,0 +1,55 @@
+//! Imports a map made in Tiled as a scene
+//!
+//! `cargo run --bin ie-tiled -- <map.json> <scene.json> [assets.json]` writes the map's entities to the scene.
+//! With an asset descriptor its tilesets are added to it, replacing the sprites and tilesets with the same names.
+use std::{io, process};
+
+use ie::{
+    ecs::systems::sorted_json,
+    error::{ErrorKind, IslandError, IslandResult},
+    tiled::TiledMap,
+};
+
+fn main() {
+    let arguments = std::env::args().skip(1).collect::<Vec<_>>();
+    if arguments.len() < 2 || arguments.len() > 3 {
+        eprintln!("Usage: ie-tiled <map.json> <scene.json> [assets.json]");
+        process::exit(2);
+    }
+    if let Err(e) = import(&arguments[0], &arguments[1], arguments.get(2)) {
+        eprintln!("{:#}", e);
+        process::exit(1);
+    }
+}
+
+/** Import the map, then write the scene and update the asset descriptor */
+fn import(map: &str, scene: &str, assets: Option<&String>) -> IslandResult<()> {
+    let map = TiledMap::import(map)?;
+    // Sorted like saved scenes, so imported scenes diff cleanly
+    write(scene, &sorted_json(&map.scene()))?;
+    println!("Wrote {}", scene);
+
+    if let Some(assets) = assets {
+        let mut asset_descriptor = match std::fs::read_to_string(assets) {
+            Ok(file) => json::parse(&file).map_err(|e| {
+                return IslandError::with_source(ErrorKind::Json, format!("Failed to parse \"{}\"", assets), e);
+            })?,
+            // A missing descriptor is created, other errors would lose the one that is there
+            Err(e) if e.kind() == io::ErrorKind::NotFound => json::object! {},
+            Err(e) => {
+                return Err(IslandError::with_source(ErrorKind::Io, format!("Failed to read \"{}\"", assets), e));
+            }
+        };
+        map.merge_assets(&mut asset_descriptor)?;
+        write(assets, &asset_descriptor)?;
+        println!("Added {} tilesets to {}", map.tilesets.len(), assets);
+    }
+    return Ok(());
+}
+
+/** Write a json file */
+fn write(path: &str, json: &json::JsonValue) -> IslandResult<()> {
+    return std::fs::write(path, format!("{}\n", json.pretty(4))).map_err(|e| {
+        return IslandError::with_source(ErrorKind::Io, format!("Failed to write \"{}\"", path), e);
+    });
+}
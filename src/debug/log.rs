@@ -5,7 +5,7 @@ static mut LOGGER: IslandLogger = IslandLogger::new();
 #[macro_export]
 macro_rules! info {
     ($($arg:tt)*) => {
-        crate::debug::log::logger().info(format!($($arg)+));
+        $crate::debug::log::logger().info(format!($($arg)+))
     };
 }
 
@@ -13,7 +13,7 @@ macro_rules! info {
 #[macro_export]
 macro_rules! warn {
     ($($arg:tt)*) => {
-        crate::debug::log::logger().warn(format!($($arg)+));
+        $crate::debug::log::logger().warn(format!($($arg)+))
     };
 }
 
@@ -21,13 +21,13 @@ macro_rules! warn {
 #[macro_export]
 macro_rules! error {
     ($($arg:tt)*) => {
-        crate::debug::log::logger().error(format!($($arg)+));
+        $crate::debug::log::logger().error(format!($($arg)+))
     };
 }
 
 /** Get the logger */
 pub fn logger() -> &'static mut IslandLogger {
-    return unsafe { &mut LOGGER };
+    return unsafe { &mut *std::ptr::addr_of_mut!(LOGGER) };
 }
 
 /** The logger for highground */
@@ -51,7 +51,7 @@ impl IslandLogger {
     where
         T: Into<String>,
     {
-        self.log = format!("{}{}", self.log, format!("[INFO]: {}\n", log.into()));
+        self.log.push_str(&format!("[INFO]: {}\n", log.into()));
     }
 
     /** Log a warning log */
@@ -59,7 +59,7 @@ impl IslandLogger {
     where
         T: Into<String>,
     {
-        self.log = format!("{}{}", self.log, format!("[WARNING]: {}\n", log.into()));
+        self.log.push_str(&format!("[WARNING]: {}\n", log.into()));
     }
 
     /** Log a error log */
@@ -67,6 +67,12 @@ impl IslandLogger {
     where
         T: Into<String>,
     {
-        self.log = format!("{}{}", self.log, format!("[ERROR]: {}\n", log.into()));
+        self.log.push_str(&format!("[ERROR]: {}\n", log.into()));
+    }
+}
+
+impl Default for IslandLogger {
+    fn default() -> Self {
+        return Self::new();
     }
 }
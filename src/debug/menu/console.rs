@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use egui::Align;
 use macroquad::prelude::{is_key_pressed, KeyCode};
 
-use crate::{ecs::World, error, info, warn, debug::log::logger};
+use crate::{ecs::{storage::Entity, World}, error, info, warn, debug::log::logger};
 
 type CommandList = HashMap<String, Box<dyn FnMut(String, &mut World)>>;
 
@@ -66,7 +66,42 @@ impl DebugConsole {
                 if let Ok(entity) =
                     json::parse(&args).map_err(|e| error!("Failed to spawn entity: {}", e))
                 {
-                    world.spawn_entity(entity).unwrap();
+                    match world.spawn_entity(entity) {
+                        Ok(entity) => info!("Spawned entity {}", entity),
+                        Err(e) => error!("{:#}", e),
+                    }
+                }
+            }),
+        );
+        list.insert(
+            "despawn".into(),
+            Box::new(|args, world| {
+                if let Ok(id) = args
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|e| error!("Failed to despawn entity: {}", e))
+                {
+                    if !world.despawn_entity(Entity::from_id(id)) {
+                        error!("Failed to despawn entity, no entity with id {}", id);
+                    }
+                }
+            }),
+        );
+
+        list.insert(
+            "save_scene".into(),
+            Box::new(|args, world| {
+                let path = match args.trim() {
+                    "" => world.scene_path.clone(),
+                    path => Some(path.into()),
+                };
+                match path {
+                    Some(path) => {
+                        if let Err(e) = world.save_scene(path) {
+                            error!("{:#}", e);
+                        }
+                    }
+                    None => error!("Failed to save scene, no path given and the scene has no file"),
                 }
             }),
         );
@@ -103,3 +138,9 @@ impl DebugConsole {
         }
     }
 }
+
+impl Default for DebugConsole {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
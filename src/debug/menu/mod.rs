@@ -10,6 +10,8 @@ pub mod console;
 pub struct DebugMenu {
     drawing: bool,
     console: DebugConsole,
+    // The amount of failed scripts the menu has shown, to open it when another one fails
+    failed_scripts: usize,
 }
 
 impl DebugMenu {
@@ -18,15 +20,23 @@ impl DebugMenu {
         return Self {
             drawing: false,
             console: DebugConsole::new(),
+            failed_scripts: 0,
         };
     }
 
     /** Draw the debug menu */
     pub fn draw(&mut self, world: &mut World) {
+        // Open the menu when a script fails
+        if world.failed_scripts.len() > self.failed_scripts {
+            self.drawing = true;
+        }
+        self.failed_scripts = world.failed_scripts.len();
+
         // Draw the log
         if self.drawing {
             egui_macroquad::ui(|ctx| {
                 self.console.draw(world, ctx);
+                draw_failed_scripts(world, ctx);
             });
             // Draw the egui menu's
             egui_macroquad::draw();
@@ -38,3 +48,30 @@ impl DebugMenu {
         }
     }
 }
+
+impl Default for DebugMenu {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/** Draw the scripts that were disabled after failing, with their tracebacks */
+fn draw_failed_scripts(world: &World, ctx: &egui::CtxRef) {
+    if world.failed_scripts.is_empty() {
+        return;
+    }
+    egui::Window::new("Failed scripts").scroll(true).show(ctx, |ui| {
+        ui.label("These scripts are disabled, press F4 to reload them");
+        for failure in &world.failed_scripts {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("{} failed in {}", failure.path, failure.stage),
+            );
+            egui::CollapsingHeader::new(failure.error.to_string())
+                .id_source(&failure.path)
+                .show(ui, |ui| {
+                    ui.monospace(failure.error.traceback().unwrap_or("No traceback"));
+                });
+        }
+    });
+}
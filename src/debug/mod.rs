@@ -1,3 +1,4 @@
 //! A module for handeling debug functionality
 pub mod menu;
-pub mod log;
\ No newline at end of file
+pub mod log;
+pub mod watch;
\ No newline at end of file
@@ -0,0 +1,89 @@
+//! Noticing changed files by polling their modification times
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/**
+Polls the modification times of a set of files.
+Polling needs no platform file events, so it works the same everywhere.
+*/
+pub struct FileWatcher {
+    // The last seen modification time of each watched file
+    mtimes: hashbrown::HashMap<PathBuf, Option<SystemTime>>,
+    // The seconds between polls
+    pub interval: f32,
+    // The seconds since the last poll
+    elapsed: f32,
+}
+
+impl FileWatcher {
+    /** Construct a watcher that polls every `interval` seconds */
+    pub fn new(interval: f32) -> Self {
+        return Self {
+            mtimes: hashbrown::HashMap::new(),
+            interval,
+            elapsed: 0.0,
+        };
+    }
+
+    /**
+    Watch exactly these files.
+    New files start out unchanged, files that are not in the list anymore are forgotten.
+    */
+    pub fn set_files<I>(&mut self, files: I)
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        let files = files.into_iter().collect::<hashbrown::HashSet<_>>();
+        self.mtimes.retain(|path, _| files.contains(path));
+        for path in files {
+            if !self.mtimes.contains_key(&path) {
+                let mtime = modified(&path);
+                self.mtimes.insert(path, mtime);
+            }
+        }
+    }
+
+    /** Take the current modification time of a watched file, so its last change is not reported */
+    pub fn refresh(&mut self, path: &Path) {
+        if let Some(last) = self.mtimes.get_mut(path) {
+            *last = modified(path);
+        }
+    }
+
+    /** Advance the clock by the frame time, returns true and restarts the clock if it is time to poll */
+    pub fn due(&mut self, frame_time: f32) -> bool {
+        self.elapsed += frame_time;
+        if self.elapsed < self.interval {
+            return false;
+        }
+        self.elapsed = 0.0;
+        return true;
+    }
+
+    /** Get the files that changed since they were last checked */
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+        for (path, last) in self.mtimes.iter_mut() {
+            // Missing files are skipped, editors can briefly remove a file while saving it
+            let mtime = modified(path);
+            if mtime.is_some() && mtime != *last {
+                *last = mtime;
+                changed.push(path.clone());
+            }
+        }
+        return changed;
+    }
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        return Self::new(0.5);
+    }
+}
+
+/** Get the modification time of a file, none if it can't be read */
+fn modified(path: &Path) -> Option<SystemTime> {
+    return std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+}
@@ -0,0 +1,469 @@
+//! Named input actions and axes, bound to keys, mouse buttons and gamepads
+use std::path::{Path, PathBuf};
+
+use json::JsonValue;
+use macroquad::prelude::{KeyCode, MouseButton};
+
+use crate::{
+    error::{ErrorKind, IslandError, IslandResult},
+    info, warn,
+};
+
+use super::{
+    ctx::{string_to_gamepad_axis, string_to_gamepad_button, string_to_keycode, string_to_mouse_button},
+    input::{GamepadAxis, GamepadButton, InputSource},
+    systems::sorted_json,
+    validate::Problems,
+};
+
+/** The gamepad the actions read from */
+const ACTION_GAMEPAD: usize = 0;
+
+/** What a binding reads from */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BindingInput {
+    Key(KeyCode),
+    MouseButton(MouseButton),
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis),
+}
+
+/**
+An input bound to an action or axis, written as a key name like `"Space"`,
+`"mouse:Left"` for mouse buttons and `"gamepad:South"` or `"gamepad:LeftX"` for gamepad buttons and axes.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    // The name the binding was written as
+    name: String,
+    input: BindingInput,
+}
+
+impl Binding {
+    /** Parse a binding from its name, none if it names no input */
+    pub fn parse(name: &str) -> Option<Self> {
+        let input = if let Some(button) = name.strip_prefix("mouse:") {
+            BindingInput::MouseButton(string_to_mouse_button(button)?)
+        } else if let Some(gamepad) = name.strip_prefix("gamepad:") {
+            match string_to_gamepad_button(gamepad) {
+                Some(button) => BindingInput::GamepadButton(button),
+                None => BindingInput::GamepadAxis(string_to_gamepad_axis(gamepad)?),
+            }
+        } else {
+            BindingInput::Key(string_to_keycode(name)?)
+        };
+        return Some(Self {
+            name: name.into(),
+            input,
+        });
+    }
+
+    /** Get the name of the binding */
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    /** Get what the binding reads from */
+    pub fn input(&self) -> BindingInput {
+        return self.input;
+    }
+
+    /** Check if the input is pressed on the current frame */
+    pub fn is_pressed(&self, input: &dyn InputSource) -> bool {
+        return match self.input {
+            BindingInput::Key(key) => input.is_key_pressed(key),
+            BindingInput::MouseButton(button) => input.is_mouse_button_pressed(button),
+            BindingInput::GamepadButton(button) => input.is_gamepad_button_pressed(ACTION_GAMEPAD, button),
+            BindingInput::GamepadAxis(_) => false,
+        };
+    }
+
+    /** Check if the input is released on the current frame */
+    pub fn is_released(&self, input: &dyn InputSource) -> bool {
+        return match self.input {
+            BindingInput::Key(key) => input.is_key_released(key),
+            BindingInput::MouseButton(button) => input.is_mouse_button_released(button),
+            BindingInput::GamepadButton(button) => input.is_gamepad_button_released(ACTION_GAMEPAD, button),
+            BindingInput::GamepadAxis(_) => false,
+        };
+    }
+
+    /** Check if the input is held down */
+    pub fn is_down(&self, input: &dyn InputSource) -> bool {
+        return match self.input {
+            BindingInput::Key(key) => input.is_key_down(key),
+            BindingInput::MouseButton(button) => input.is_mouse_button_down(button),
+            BindingInput::GamepadButton(button) => input.is_gamepad_button_down(ACTION_GAMEPAD, button),
+            BindingInput::GamepadAxis(_) => false,
+        };
+    }
+
+    /** Get the value of the input, 1 or 0 for buttons */
+    pub fn value(&self, input: &dyn InputSource) -> f32 {
+        return match self.input {
+            BindingInput::GamepadAxis(axis) => input.gamepad_axis(ACTION_GAMEPAD, axis),
+            _ if self.is_down(input) => 1.0,
+            _ => 0.0,
+        };
+    }
+}
+
+/** The bindings of an axis, its value is the positive inputs minus the negative ones */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AxisBindings {
+    pub negative: Vec<Binding>,
+    pub positive: Vec<Binding>,
+}
+
+/**
+Maps action and axis names to their bindings, so scripts don't depend on physical inputs.
+It is loaded from a json file like
+`{"actions": {"jump": ["Space", "gamepad:South"]}, "axes": {"move_x": {"negative": ["A"], "positive": ["D", "gamepad:LeftX"]}}}`.
+Rebinds can be saved to a settings file in the same format, which overrides the defaults when loading.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ActionMap {
+    actions: hashbrown::HashMap<String, Vec<Binding>>,
+    axes: hashbrown::HashMap<String, AxisBindings>,
+    // The file the bindings are saved to
+    settings: Option<PathBuf>,
+}
+
+impl ActionMap {
+    /** Construct an action map without actions or axes */
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /**
+    Load the action map from a file.
+    The bindings in the settings file replace the defaults, if it exists, and rebinds are saved to it.
+    */
+    pub fn load<T>(path: T, settings: Option<T>) -> IslandResult<Self>
+    where
+        T: AsRef<Path>,
+    {
+        let mut result = Self::from_json(&read_json(path.as_ref())?)?;
+        if let Some(settings) = settings {
+            let settings = settings.as_ref();
+            if settings.exists() {
+                let overrides = Self::from_json(&read_json(settings)?)?;
+                result.apply_overrides(overrides);
+                info!("Loaded input settings: {:?}", settings);
+            }
+            result.settings = Some(settings.into());
+        }
+        return Ok(result);
+    }
+
+    /** Construct an action map from its json */
+    pub fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let mut problems = Problems::new();
+        if !json.is_object() {
+            problems.push("action map", "expected an object");
+        }
+        if json.has_key("actions") && !json["actions"].is_object() {
+            problems.push("actions", "expected an object of action bindings");
+        }
+        if json.has_key("axes") && !json["axes"].is_object() {
+            problems.push("axes", "expected an object of axis bindings");
+        }
+
+        let mut result = Self::new();
+        for (name, bindings) in json["actions"].entries() {
+            let path = format!("actions.{}", name);
+            let bindings = parse_bindings(&mut problems, &path, bindings);
+            if bindings.iter().any(|binding| matches!(binding.input, BindingInput::GamepadAxis(_))) {
+                problems.push(&path, "gamepad axes can only be bound to axes");
+            }
+            result.actions.insert(name.into(), bindings);
+        }
+        for (name, axis) in json["axes"].entries() {
+            let path = format!("axes.{}", name);
+            if !axis.is_object() {
+                problems.push(&path, "expected an object with negative and positive bindings");
+                continue;
+            }
+            result.axes.insert(name.into(), AxisBindings {
+                negative: parse_bindings(&mut problems, &format!("{}.negative", path), &axis["negative"]),
+                positive: parse_bindings(&mut problems, &format!("{}.positive", path), &axis["positive"]),
+            });
+        }
+        problems.into_result(ErrorKind::Input, "Action map")?;
+        return Ok(result);
+    }
+
+    /** Get the json of the action map */
+    pub fn to_json(&self) -> JsonValue {
+        let names = |bindings: &[Binding]| {
+            return bindings.iter().map(|binding| binding.name.clone()).collect::<Vec<_>>();
+        };
+        let mut result = json::object! { actions: {}, axes: {} };
+        for (name, bindings) in self.actions.iter() {
+            result["actions"][name.as_str()] = names(bindings).into();
+        }
+        for (name, axis) in self.axes.iter() {
+            result["axes"][name.as_str()] = json::object! {
+                negative: names(&axis.negative),
+                positive: names(&axis.positive),
+            };
+        }
+        return result;
+    }
+
+    /** Get the settings file rebinds are saved to */
+    pub fn settings_path(&self) -> Option<&Path> {
+        return self.settings.as_deref();
+    }
+
+    /** Set the settings file rebinds are saved to */
+    pub fn set_settings_path<T>(&mut self, path: Option<T>)
+    where
+        T: Into<PathBuf>,
+    {
+        self.settings = path.map(Into::into);
+    }
+
+    /** Get the bindings of an action, none if there is no such action */
+    pub fn action_bindings(&self, action: &str) -> Option<&[Binding]> {
+        return self.actions.get(action).map(Vec::as_slice);
+    }
+
+    /** Get the bindings of an axis, none if there is no such axis */
+    pub fn axis_bindings(&self, axis: &str) -> Option<&AxisBindings> {
+        return self.axes.get(axis);
+    }
+
+    /** Check if any input of the action is pressed on the current frame, none if there is no such action */
+    pub fn is_action_pressed(&self, input: &dyn InputSource, action: &str) -> Option<bool> {
+        let bindings = self.actions.get(action)?;
+        return Some(bindings.iter().any(|binding| binding.is_pressed(input)));
+    }
+
+    /** Check if any input of the action is released on the current frame, none if there is no such action */
+    pub fn is_action_released(&self, input: &dyn InputSource, action: &str) -> Option<bool> {
+        let bindings = self.actions.get(action)?;
+        return Some(bindings.iter().any(|binding| binding.is_released(input)));
+    }
+
+    /** Check if any input of the action is held down, none if there is no such action */
+    pub fn is_action_down(&self, input: &dyn InputSource, action: &str) -> Option<bool> {
+        let bindings = self.actions.get(action)?;
+        return Some(bindings.iter().any(|binding| binding.is_down(input)));
+    }
+
+    /** Get the value of an axis from -1 to 1, none if there is no such axis */
+    pub fn axis(&self, input: &dyn InputSource, axis: &str) -> Option<f32> {
+        let axis = self.axes.get(axis)?;
+        let sum = |bindings: &[Binding]| bindings.iter().map(|binding| binding.value(input)).sum::<f32>();
+        return Some((sum(&axis.positive) - sum(&axis.negative)).clamp(-1.0, 1.0));
+    }
+
+    /** Replace the bindings of an action, call `save_settings` to keep them */
+    pub fn rebind_action(&mut self, action: &str, bindings: Vec<Binding>) -> IslandResult<()> {
+        let current = self.actions.get_mut(action).ok_or_else(|| {
+            return IslandError::with_kind(ErrorKind::Input, format!("Unknown action \"{}\"", action));
+        })?;
+        if let Some(binding) = bindings.iter().find(|binding| matches!(binding.input, BindingInput::GamepadAxis(_))) {
+            return Err(IslandError::with_kind(ErrorKind::Input, format!(
+                "Can't bind gamepad axis \"{}\" to action \"{}\", axes can only be bound to axes",
+                binding.name, action
+            )));
+        }
+        *current = bindings;
+        return Ok(());
+    }
+
+    /** Replace the bindings of an axis, call `save_settings` to keep them */
+    pub fn rebind_axis(&mut self, axis: &str, bindings: AxisBindings) -> IslandResult<()> {
+        let current = self.axes.get_mut(axis).ok_or_else(|| {
+            return IslandError::with_kind(ErrorKind::Input, format!("Unknown axis \"{}\"", axis));
+        })?;
+        *current = bindings;
+        return Ok(());
+    }
+
+    /** Save the bindings to the settings file, nothing is saved if there is none */
+    pub fn save_settings(&self) -> IslandResult<()> {
+        let path = match &self.settings {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let settings = sorted_json(&self.to_json());
+        std::fs::write(path, format!("{}\n", settings.pretty(4))).map_err(|e| {
+            return IslandError::with_source(ErrorKind::Io, format!("Failed to save input settings {:?}", path), e);
+        })?;
+        info!("Saved input settings: {:?}", path);
+        return Ok(());
+    }
+
+    /** Replace the bindings of the actions and axes the overrides have */
+    fn apply_overrides(&mut self, overrides: ActionMap) {
+        for (name, bindings) in overrides.actions {
+            match self.actions.get_mut(&name) {
+                Some(current) => *current = bindings,
+                None => warn!("Input settings bind unknown action \"{}\"", name),
+            }
+        }
+        for (name, bindings) in overrides.axes {
+            match self.axes.get_mut(&name) {
+                Some(current) => *current = bindings,
+                None => warn!("Input settings bind unknown axis \"{}\"", name),
+            }
+        }
+    }
+}
+
+/** Read a json file */
+fn read_json(path: &Path) -> IslandResult<JsonValue> {
+    let file = std::fs::read_to_string(path).map_err(|e| {
+        return IslandError::with_source(ErrorKind::Io, format!("Failed to load {:?}", path), e);
+    })?;
+    return json::parse(&file).map_err(|e| {
+        return IslandError::with_source(ErrorKind::Json, format!("Failed to parse {:?}", path), e);
+    });
+}
+
+/** Parse a list of binding names, pushing the problems of invalid ones */
+fn parse_bindings(problems: &mut Problems, path: &str, json: &JsonValue) -> Vec<Binding> {
+    if !json.is_null() && !json.is_array() {
+        problems.push(path, "expected a list of bindings");
+        return vec![];
+    }
+    let mut result = vec![];
+    for (i, name) in json.members().enumerate() {
+        match name.as_str().and_then(Binding::parse) {
+            Some(binding) => result.push(binding),
+            None => problems.push(&format!("{}[{}]", path, i), format!("unknown input {}", name)),
+        }
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::input::ScriptedInput;
+
+    /** The bindings the tests load */
+    fn defaults() -> JsonValue {
+        return json::object! {
+            actions: { jump: ["Space", "gamepad:South"], fire: ["mouse:Left"] },
+            axes: { move_x: { negative: ["A"], positive: ["D", "gamepad:LeftX"] } },
+        };
+    }
+
+    /** Get a temporary directory named after the test, emptied */
+    fn test_directory(test: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("ie_test_{}", test));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        return directory;
+    }
+
+    #[test]
+    fn bindings_read_keys_mouse_buttons_and_gamepads() {
+        let actions = ActionMap::from_json(&defaults()).unwrap();
+        let input = ScriptedInput::new();
+        input.connect_gamepad(0);
+        input.press_gamepad_button(0, GamepadButton::A);
+        input.press_mouse_button(MouseButton::Left);
+        assert_eq!(actions.is_action_pressed(&input, "jump"), Some(true));
+        assert_eq!(actions.is_action_down(&input, "fire"), Some(true));
+        assert_eq!(actions.is_action_down(&input, "crouch"), None);
+
+        input.end_frame();
+        input.release_gamepad_button(0, GamepadButton::A);
+        assert_eq!(actions.is_action_pressed(&input, "jump"), Some(false));
+        assert_eq!(actions.is_action_released(&input, "jump"), Some(true));
+    }
+
+    #[test]
+    fn problems_are_reported_with_their_path() {
+        let json = json::object! {
+            actions: { jump: ["Space", "Spacebar"], look: ["gamepad:RightX"] },
+            axes: { move_x: { negative: "A", positive: ["D"] }, move_y: ["W"] },
+        };
+        let message = format!("{:#}", ActionMap::from_json(&json).unwrap_err());
+        assert!(message.contains("4 problem(s)"), "{}", message);
+        for problem in [
+            "actions.jump[1]: unknown input Spacebar",
+            "actions.look: gamepad axes can only be bound to axes",
+            "axes.move_x.negative: expected a list of bindings",
+            "axes.move_y: expected an object with negative and positive bindings",
+        ]
+        .iter()
+        {
+            assert!(message.contains(problem), "{} missing from {}", problem, message);
+        }
+    }
+
+    #[test]
+    fn settings_override_the_default_bindings() {
+        let directory = test_directory("action_settings");
+        let (path, settings) = (directory.join("actions.json"), directory.join("settings.json"));
+        std::fs::write(&path, defaults().dump()).unwrap();
+        std::fs::write(&settings, json::object! {
+            actions: { jump: ["K"], crouch: ["C"] },
+            axes: {},
+        }.dump()).unwrap();
+
+        let actions = ActionMap::load(&path, Some(&settings)).unwrap();
+        let names = |bindings: &[Binding]| bindings.iter().map(|binding| binding.name().to_string()).collect::<Vec<_>>();
+        assert_eq!(names(actions.action_bindings("jump").unwrap()), ["K"]);
+        // Actions and axes the settings don't bind keep their defaults, unknown ones are not added
+        assert_eq!(names(actions.action_bindings("fire").unwrap()), ["mouse:Left"]);
+        assert_eq!(names(&actions.axis_bindings("move_x").unwrap().positive), ["D", "gamepad:LeftX"]);
+        assert!(actions.action_bindings("crouch").is_none());
+        assert_eq!(actions.settings_path(), Some(settings.as_path()));
+    }
+
+    #[test]
+    fn rebinds_are_saved_to_the_settings_and_loaded_back() {
+        let directory = test_directory("action_rebinds");
+        let (path, settings) = (directory.join("actions.json"), directory.join("settings.json"));
+        std::fs::write(&path, defaults().dump()).unwrap();
+
+        let mut actions = ActionMap::load(&path, Some(&settings)).unwrap();
+        let bindings = |names: &[&str]| names.iter().map(|name| Binding::parse(name).unwrap()).collect::<Vec<_>>();
+        actions.rebind_action("jump", bindings(&["J", "gamepad:North"])).unwrap();
+        actions
+            .rebind_axis("move_x", AxisBindings {
+                negative: bindings(&["Left"]),
+                positive: bindings(&["Right"]),
+            })
+            .unwrap();
+        assert!(actions.rebind_action("crouch", bindings(&["C"])).is_err());
+        assert!(actions.rebind_action("jump", bindings(&["gamepad:LeftY"])).is_err());
+        assert!(actions.rebind_axis("move_y", AxisBindings::default()).is_err());
+        actions.save_settings().unwrap();
+
+        let loaded = ActionMap::load(&path, Some(&settings)).unwrap();
+        assert_eq!(loaded.action_bindings("jump"), actions.action_bindings("jump"));
+        assert_eq!(loaded.axis_bindings("move_x"), actions.axis_bindings("move_x"));
+        assert_eq!(loaded.to_json(), actions.to_json());
+        // The defaults are left alone
+        assert_eq!(ActionMap::load(&path, None).unwrap().to_json(), ActionMap::from_json(&defaults()).unwrap().to_json());
+    }
+
+    #[test]
+    fn axes_are_clamped_to_one() {
+        let actions = ActionMap::from_json(&defaults()).unwrap();
+        let input = ScriptedInput::new();
+        input.connect_gamepad(0);
+        assert_eq!(actions.axis(&input, "move_x"), Some(0.0));
+        assert_eq!(actions.axis(&input, "move_y"), None);
+
+        input.press(KeyCode::D);
+        input.set_gamepad_axis(0, GamepadAxis::LeftX, 0.5);
+        assert_eq!(actions.axis(&input, "move_x"), Some(1.0));
+
+        input.set_gamepad_axis(0, GamepadAxis::LeftX, -0.25);
+        assert_eq!(actions.axis(&input, "move_x"), Some(0.75));
+
+        input.release(KeyCode::D);
+        input.press(KeyCode::A);
+        input.set_gamepad_axis(0, GamepadAxis::LeftX, -1.0);
+        assert_eq!(actions.axis(&input, "move_x"), Some(-1.0));
+    }
+}
@@ -0,0 +1,594 @@
+//! The builtin component types of island engine
+use json::JsonValue;
+use macroquad::prelude::{Color, Rect, Vec2, WHITE};
+
+use crate::error::{IslandError, IslandResult};
+
+use super::systems::{color_to_json, f32_to_json, rect_to_json, vec2_to_json, JsonConvert};
+
+/**
+A component that can be stored in the world.
+Components are loaded from and saved to the entity's json object under their name.
+*/
+pub trait Component: Sized + 'static {
+    /** The key of the component in an entity's json object */
+    const NAME: &'static str;
+
+    /** Parse the component from its json value */
+    fn from_json(json: &JsonValue) -> IslandResult<Self>;
+    /** Convert the component into its json value */
+    fn to_json(&self) -> JsonValue;
+}
+
+/** The position of an entity */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position(pub Vec2);
+
+impl Component for Position {
+    const NAME: &'static str = "position";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        return Ok(Self(json.as_vec2()?));
+    }
+
+    fn to_json(&self) -> JsonValue {
+        return vec2_to_json(self.0);
+    }
+}
+
+/** The scale of an entity */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scale(pub Vec2);
+
+impl Component for Scale {
+    const NAME: &'static str = "scale";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        return Ok(Self(json.as_vec2()?));
+    }
+
+    fn to_json(&self) -> JsonValue {
+        return vec2_to_json(self.0);
+    }
+}
+
+/** The sprite of an entity */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    // The name of the texture in the sprites map
+    pub texture: String,
+    // The size the sprite is drawn at before scaling
+    pub dest_size: Vec2,
+    // The part of the texture to draw
+    pub source_rec: Option<Rect>,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // The rotation in radians, clockwise around the pivot
+    pub rotation: f32,
+    // The point of the sprite that is placed at the position and rotated around, from (0, 0) at the top left to (1, 1) at the bottom right
+    pub pivot: Vec2,
+    // The color the sprite is tinted with, including its alpha
+    pub color: Color,
+    // The layer the sprite is drawn on, higher layers are drawn on top
+    pub layer: i32,
+}
+
+impl Component for Sprite {
+    const NAME: &'static str = "sprite";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let texture = json["texture"]
+            .as_str()
+            .ok_or_else(|| IslandError::new(format!("Sprite has no texture: {}", json)))?;
+        let source_rec = if json.has_key("source_rec") {
+            Some(json["source_rec"].as_rect()?)
+        } else {
+            None
+        };
+
+        return Ok(Self {
+            texture: texture.into(),
+            dest_size: json["dest_size"].as_vec2()?,
+            source_rec,
+            flip_x: json["flip_x"].as_bool().unwrap_or(false),
+            flip_y: json["flip_y"].as_bool().unwrap_or(false),
+            rotation: json["rotation"].as_f32().unwrap_or(0.0),
+            pivot: if json.has_key("pivot") {
+                json["pivot"].as_vec2()?
+            } else {
+                Vec2::ZERO
+            },
+            color: if json.has_key("color") {
+                json["color"].as_color()?
+            } else {
+                WHITE
+            },
+            layer: json["layer"].as_i32().unwrap_or(0),
+        });
+    }
+
+    fn to_json(&self) -> JsonValue {
+        let mut json = json::object! {
+            texture: self.texture.clone(),
+            dest_size: vec2_to_json(self.dest_size),
+        };
+        if let Some(source_rec) = self.source_rec {
+            json["source_rec"] = rect_to_json(source_rec);
+        }
+        json["flip_x"] = self.flip_x.into();
+        json["flip_y"] = self.flip_y.into();
+        // The drawing options are only saved if they are set, so scenes that don't use them stay the same
+        if self.rotation != 0.0 {
+            json["rotation"] = f32_to_json(self.rotation);
+        }
+        if self.pivot != Vec2::ZERO {
+            json["pivot"] = vec2_to_json(self.pivot);
+        }
+        if self.color != WHITE {
+            json["color"] = color_to_json(self.color);
+        }
+        if self.layer != 0 {
+            json["layer"] = self.layer.into();
+        }
+        return json;
+    }
+}
+
+/** The name of an entity, used to look it up from gameplay code */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name(pub String);
+
+impl Component for Name {
+    const NAME: &'static str = "name";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let name = json
+            .as_str()
+            .ok_or_else(|| IslandError::new(format!("Name has to be a string: {}", json)))?;
+        return Ok(Self(name.into()));
+    }
+
+    fn to_json(&self) -> JsonValue {
+        return self.0.clone().into();
+    }
+}
+
+/** The tag of an entity, shared by entities of the same kind */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag(pub String);
+
+impl Component for Tag {
+    const NAME: &'static str = "tag";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let tag = json
+            .as_str()
+            .ok_or_else(|| IslandError::new(format!("Tag has to be a string: {}", json)))?;
+        return Ok(Self(tag.into()));
+    }
+
+    fn to_json(&self) -> JsonValue {
+        return self.0.clone().into();
+    }
+}
+
+/** The path of the python script attached to an entity, run for each entity that has it */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script(pub String);
+
+impl Component for Script {
+    const NAME: &'static str = "script";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let path = json
+            .as_str()
+            .ok_or_else(|| IslandError::new(format!("Script has to be a path string: {}", json)))?;
+        return Ok(Self(path.into()));
+    }
+
+    fn to_json(&self) -> JsonValue {
+        return self.0.clone().into();
+    }
+}
+
+/**
+The animation of an entity's sprite, playing a clip from the asset descriptor.
+The world advances it every frame and sets the source rect of the sprite to the current frame.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    // The name of the clip
+    pub clip: String,
+    // The index of the current frame
+    pub frame: usize,
+    // The seconds the current frame has been shown for
+    pub time: f32,
+    pub playing: bool,
+    // If a ping-pong clip is playing backwards
+    pub backwards: bool,
+}
+
+impl Animation {
+    /** Construct an animation playing a clip from the start */
+    pub fn new<T>(clip: T) -> Self
+    where
+        T: Into<String>,
+    {
+        return Self {
+            clip: clip.into(),
+            frame: 0,
+            time: 0.0,
+            playing: true,
+            backwards: false,
+        };
+    }
+}
+
+impl Component for Animation {
+    const NAME: &'static str = "animation";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let clip = json["clip"]
+            .as_str()
+            .ok_or_else(|| IslandError::new(format!("Animation has no clip: {}", json)))?;
+        return Ok(Self {
+            frame: json["frame"].as_usize().unwrap_or(0),
+            time: json["time"].as_f32().unwrap_or(0.0),
+            playing: json["playing"].as_bool().unwrap_or(true),
+            backwards: json["backwards"].as_bool().unwrap_or(false),
+            ..Self::new(clip)
+        });
+    }
+
+    fn to_json(&self) -> JsonValue {
+        let mut json = json::object! {
+            clip: self.clip.clone(),
+            frame: self.frame,
+            time: f32_to_json(self.time),
+            playing: self.playing,
+        };
+        if self.backwards {
+            json["backwards"] = true.into();
+        }
+        return json;
+    }
+}
+
+/** The shape of a collider, relative to the entity's position and offset before scaling */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColliderShape {
+    /** An axis aligned box with its top left corner at the position */
+    Rect { size: Vec2 },
+    /** A circle around the position */
+    Circle { radius: f32 },
+    /** A convex polygon, the points are relative to the position */
+    Polygon { points: Vec<Vec2> },
+}
+
+/**
+Check if the points go around a convex polygon with an area, in either direction.
+The overlap tests of colliders only work for convex polygons.
+*/
+pub fn is_convex(points: &[Vec2]) -> bool {
+    let mut points = points.to_vec();
+    points.dedup();
+    while points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() < 3 {
+        return false;
+    }
+    let mut sign = 0.0;
+    let mut turned = 0.0;
+    for i in 0..points.len() {
+        let edge = points[(i + 1) % points.len()] - points[i];
+        let next = points[(i + 2) % points.len()] - points[(i + 1) % points.len()];
+        let cross = edge.perp_dot(next);
+        if cross != 0.0 {
+            if sign != 0.0 && cross.signum() != sign {
+                return false;
+            }
+            sign = cross.signum();
+        }
+        turned += edge.angle_between(next).abs();
+    }
+    // A polygon that crosses itself turns around more than once
+    return sign != 0.0 && (turned - std::f32::consts::TAU).abs() < 1e-3;
+}
+
+/**
+The collider of an entity, for contact events and the collision queries of scripts.
+Colliders only detect overlaps, they don't push entities apart.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    // Moves the shape away from the entity's position, before scaling
+    pub offset: Vec2,
+}
+
+impl Component for Collider {
+    const NAME: &'static str = "collider";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let shape = match json["shape"].as_str() {
+            Some("rect") => ColliderShape::Rect {
+                size: json["size"].as_vec2()?,
+            },
+            Some("circle") => ColliderShape::Circle {
+                radius: json["radius"]
+                    .as_f32()
+                    .filter(|radius| *radius >= 0.0)
+                    .ok_or_else(|| IslandError::new(format!("Circle collider has no radius of 0 or more: {}", json)))?,
+            },
+            Some("polygon") => {
+                let points = json["points"]
+                    .members()
+                    .map(|point| point.as_vec2())
+                    .collect::<IslandResult<Vec<_>>>()?;
+                if !is_convex(&points) {
+                    return Err(IslandError::new(format!("Polygon collider has to be convex: {}", json)));
+                }
+                ColliderShape::Polygon { points }
+            }
+            _ => return Err(IslandError::new(format!("Collider has no valid shape: {}", json))),
+        };
+        return Ok(Self {
+            shape,
+            offset: if json.has_key("offset") {
+                json["offset"].as_vec2()?
+            } else {
+                Vec2::ZERO
+            },
+        });
+    }
+
+    fn to_json(&self) -> JsonValue {
+        let mut json = match &self.shape {
+            ColliderShape::Rect { size } => json::object! {
+                shape: "rect",
+                size: vec2_to_json(*size),
+            },
+            ColliderShape::Circle { radius } => json::object! {
+                shape: "circle",
+                radius: f32_to_json(*radius),
+            },
+            ColliderShape::Polygon { points } => json::object! {
+                shape: "polygon",
+                points: points.iter().map(|point| vec2_to_json(*point)).collect::<Vec<_>>(),
+            },
+        };
+        if self.offset != Vec2::ZERO {
+            json["offset"] = vec2_to_json(self.offset);
+        }
+        return json;
+    }
+}
+
+/** The bit of a tile's gid that flips it horizontally, as Tiled stores it */
+pub const TILE_FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+/** The bit of a tile's gid that flips it vertically */
+pub const TILE_FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+/** The bit of a tile's gid that swaps its x and y axes, applied before the other flips */
+pub const TILE_FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/** The bits of a tile's gid that are the tile, without the flips */
+pub const TILE_GID_MASK: u32 = !(TILE_FLIPPED_HORIZONTALLY | TILE_FLIPPED_VERTICALLY | TILE_FLIPPED_DIAGONALLY);
+
+/**
+A grid of tiles drawn with its top left corner at the entity's position.
+Tiles are global ids, 0 is empty and the others belong to the tileset with the highest first gid at or below them.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tilemap {
+    // The size of a tile before scaling
+    pub tile_size: Vec2,
+    // The size of the map in tiles
+    pub width: usize,
+    pub height: usize,
+    // The tilesets of the asset descriptor the tiles come from
+    pub tilesets: Vec<TilesetRef>,
+    // The layers, drawn in order
+    pub layers: Vec<TileLayer>,
+}
+
+/** A tileset used by a tilemap */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TilesetRef {
+    // The gid of the first tile of the tileset
+    pub first_gid: u32,
+    // The name of the tileset in the asset descriptor
+    pub name: String,
+}
+
+/** A layer of a tilemap */
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    // The gids of the tiles row by row, with the flip bits
+    pub data: Vec<u32>,
+    // The layer it is drawn on among the sprites
+    pub layer: i32,
+    pub visible: bool,
+    pub opacity: f32,
+}
+
+impl Tilemap {
+    /** Get the gid of the tile in a layer with the flip bits, 0 if empty or outside the map */
+    pub fn tile(&self, layer: usize, column: usize, row: usize) -> u32 {
+        if column >= self.width || row >= self.height {
+            return 0;
+        }
+        return self
+            .layers
+            .get(layer)
+            .and_then(|layer| layer.data.get(row * self.width + column))
+            .copied()
+            .unwrap_or(0);
+    }
+
+    /** Get the tileset of a gid and the id of the tile in it, none for empty tiles */
+    pub fn resolve(&self, gid: u32) -> Option<(&TilesetRef, u32)> {
+        let gid = gid & TILE_GID_MASK;
+        if gid == 0 {
+            return None;
+        }
+        return self
+            .tilesets
+            .iter()
+            .filter(|tileset| tileset.first_gid <= gid)
+            .max_by_key(|tileset| tileset.first_gid)
+            .map(|tileset| (tileset, gid - tileset.first_gid));
+    }
+}
+
+impl Component for Tilemap {
+    const NAME: &'static str = "tilemap";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        let width = json["width"]
+            .as_usize()
+            .ok_or_else(|| IslandError::new(format!("Tilemap has no width: {}", json["width"])))?;
+        let height = json["height"]
+            .as_usize()
+            .ok_or_else(|| IslandError::new(format!("Tilemap has no height: {}", json["height"])))?;
+        let tilesets = json["tilesets"]
+            .members()
+            .map(|tileset| {
+                return Ok(TilesetRef {
+                    first_gid: tileset["first_gid"]
+                        .as_u32()
+                        .ok_or_else(|| IslandError::new(format!("Tileset has no first gid: {}", tileset)))?,
+                    name: tileset["name"]
+                        .as_str()
+                        .ok_or_else(|| IslandError::new(format!("Tileset has no name: {}", tileset)))?
+                        .into(),
+                });
+            })
+            .collect::<IslandResult<Vec<_>>>()?;
+        let tiles = width
+            .checked_mul(height)
+            .ok_or_else(|| IslandError::new(format!("Tilemap of {}x{} tiles is too large", width, height)))?;
+        let layers = json["layers"]
+            .members()
+            .map(|layer| {
+                let data = layer["data"]
+                    .members()
+                    .map(|gid| gid.as_u32().ok_or_else(|| IslandError::new(format!("Invalid tile: {}", gid))))
+                    .collect::<IslandResult<Vec<_>>>()?;
+                if data.len() != tiles {
+                    return Err(IslandError::new(format!(
+                        "Tile layer has {} tiles, the map has {}",
+                        data.len(),
+                        tiles
+                    )));
+                }
+                return Ok(TileLayer {
+                    name: layer["name"].as_str().unwrap_or_default().into(),
+                    data,
+                    layer: layer["layer"].as_i32().unwrap_or(0),
+                    visible: layer["visible"].as_bool().unwrap_or(true),
+                    opacity: layer["opacity"].as_f32().unwrap_or(1.0),
+                });
+            })
+            .collect::<IslandResult<Vec<_>>>()?;
+
+        return Ok(Self {
+            tile_size: json["tile_size"].as_vec2()?,
+            width,
+            height,
+            tilesets,
+            layers,
+        });
+    }
+
+    fn to_json(&self) -> JsonValue {
+        let tilesets = self
+            .tilesets
+            .iter()
+            .map(|tileset| {
+                return json::object! {
+                    first_gid: tileset.first_gid,
+                    name: tileset.name.clone(),
+                };
+            })
+            .collect::<Vec<_>>();
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let mut json = json::object! {
+                    name: layer.name.clone(),
+                    data: layer.data.clone(),
+                };
+                if layer.layer != 0 {
+                    json["layer"] = layer.layer.into();
+                }
+                if !layer.visible {
+                    json["visible"] = false.into();
+                }
+                if layer.opacity != 1.0 {
+                    json["opacity"] = f32_to_json(layer.opacity);
+                }
+                return json;
+            })
+            .collect::<Vec<_>>();
+        return json::object! {
+            tile_size: vec2_to_json(self.tile_size),
+            width: self.width,
+            height: self.height,
+            tilesets: tilesets,
+            layers: layers,
+        };
+    }
+}
+
+/** The camera of a scene */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub position: Vec2,
+    pub zoom: Vec2,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        return Self {
+            position: Vec2::ZERO,
+            zoom: Vec2::ONE,
+        };
+    }
+}
+
+impl Component for Camera {
+    const NAME: &'static str = "camera";
+
+    fn from_json(json: &JsonValue) -> IslandResult<Self> {
+        return Ok(Self {
+            position: json["position"].as_vec2()?,
+            zoom: json["zoom"].as_vec2()?,
+        });
+    }
+
+    fn to_json(&self) -> JsonValue {
+        return json::object! {
+            position: vec2_to_json(self.position),
+            zoom: vec2_to_json(self.zoom),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tilemaps_too_large_to_count_fail_to_load() {
+        let json = json::object! {
+            tile_size: { x: 16, y: 16 },
+            width: usize::MAX,
+            height: 2,
+            tilesets: [],
+            layers: [{ name: "ground", data: [] }],
+        };
+        assert!(Tilemap::from_json(&json).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Direct conversion between json values and python objects
+use json::JsonValue;
+use pyo3::{
+    exceptions::PyTypeError,
+    prelude::*,
+    types::{PyBool, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple},
+};
+
+use super::python::engine_type_to_json;
+
+/**
+Convert a json value into a python object.
+Numbers written with a fraction or an exponent become floats and other numbers ints, the way `json.loads` parses
+them. Json numbers don't keep the type of the value they were made from, so whole floats the engine writes, like the
+coordinates of components, come out as ints.
+*/
+pub(crate) fn json_to_py(py: Python, json: &JsonValue) -> PyResult<PyObject> {
+    return Ok(match json {
+        JsonValue::Null => py.None(),
+        JsonValue::Short(value) => value.as_str().to_object(py),
+        JsonValue::String(value) => value.to_object(py),
+        JsonValue::Boolean(value) => value.to_object(py),
+        JsonValue::Number(number) => match number.as_parts() {
+            (true, mantissa, 0) => mantissa.to_object(py),
+            (false, mantissa, 0) if mantissa <= i64::MAX as u64 => (-(mantissa as i64)).to_object(py),
+            _ => json.as_f64().unwrap_or(f64::NAN).to_object(py),
+        },
+        JsonValue::Object(object) => {
+            let dict = PyDict::new(py);
+            for (key, value) in object.iter() {
+                dict.set_item(key, json_to_py(py, value)?)?;
+            }
+            dict.to_object(py)
+        }
+        JsonValue::Array(array) => {
+            let list = PyList::empty(py);
+            for value in array.iter() {
+                list.append(json_to_py(py, value)?)?;
+            }
+            list.to_object(py)
+        }
+    });
+}
+
+/** Convert a python object made of dicts, lists and primitives into a json value */
+pub(crate) fn py_to_json(obj: &PyAny) -> PyResult<JsonValue> {
+    if obj.is_none() {
+        return Ok(JsonValue::Null);
+    }
+    // Bool has to be checked before int, as it is a subclass of it
+    if let Ok(value) = obj.downcast::<PyBool>() {
+        return Ok(value.is_true().into());
+    }
+    if obj.downcast::<PyLong>().is_ok() {
+        return Ok(match obj.extract::<i64>() {
+            Ok(value) => value.into(),
+            Err(_) => obj.extract::<f64>()?.into(),
+        });
+    }
+    if obj.downcast::<PyFloat>().is_ok() {
+        return Ok(obj.extract::<f64>()?.into());
+    }
+    if let Ok(value) = obj.downcast::<PyString>() {
+        return Ok(value.to_str()?.into());
+    }
+    if let Ok(dict) = obj.downcast::<PyDict>() {
+        let mut result = JsonValue::new_object();
+        for (key, value) in dict.iter() {
+            let key = key.downcast::<PyString>().map_err(|_| {
+                return PyTypeError::new_err(format!("json object keys have to be strings, got {}", key));
+            })?;
+            result[key.to_str()?] = py_to_json(value)?;
+        }
+        return Ok(result);
+    }
+    if let Ok(list) = obj.downcast::<PyList>() {
+        let mut result = JsonValue::new_array();
+        for value in list.iter() {
+            result.push(py_to_json(value)?).unwrap();
+        }
+        return Ok(result);
+    }
+    if let Ok(tuple) = obj.downcast::<PyTuple>() {
+        let mut result = JsonValue::new_array();
+        for value in tuple.iter() {
+            result.push(py_to_json(value)?).unwrap();
+        }
+        return Ok(result);
+    }
+    if let Some(value) = engine_type_to_json(obj) {
+        return Ok(value);
+    }
+    return Err(PyTypeError::new_err(format!(
+        "{} can't be converted to json",
+        obj.get_type().name()?
+    )));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::python::PyVec2;
+
+    #[test]
+    fn numbers_keep_the_type_json_loads_gives_them() {
+        Python::with_gil(|py| {
+            let numbers = json_to_py(py, &json::parse("[1, -3, 1.0, 2.5, 1e2, 18446744073709551615, -9223372036854775807]").unwrap()).unwrap();
+            let types = numbers
+                .as_ref(py)
+                .iter()
+                .unwrap()
+                .map(|number| number.unwrap().get_type().name().unwrap().to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(types, ["int", "int", "float", "float", "float", "int", "int"]);
+            assert_eq!(numbers.as_ref(py).get_item(5).unwrap().extract::<u64>().unwrap(), u64::MAX);
+            assert_eq!(numbers.as_ref(py).get_item(6).unwrap().extract::<i64>().unwrap(), -i64::MAX);
+        });
+    }
+
+    #[test]
+    fn values_survive_the_round_trip() {
+        Python::with_gil(|py| {
+            let json = json::object! {
+                name: "player",
+                position: { x: 1, y: 2.5 },
+                tags: ["a", "b"],
+                visible: true,
+                parent: null,
+            };
+            let object = json_to_py(py, &json).unwrap();
+            assert!(object.as_ref(py).downcast::<PyDict>().is_ok());
+            assert_eq!(py_to_json(object.as_ref(py)).unwrap(), json);
+        });
+    }
+
+    #[test]
+    fn python_values_convert_to_json() {
+        Python::with_gil(|py| {
+            let object = py
+                .eval("{'flag': True, 'pair': (1, 'two'), 'big': 2 ** 70}", None, None)
+                .unwrap();
+            let json = py_to_json(object).unwrap();
+            // Bools are ints in python, they have to stay bools
+            assert_eq!(json["flag"], true);
+            assert_eq!(json["pair"], json::array![1, "two"]);
+            assert_eq!(json["big"].as_f64(), Some(2f64.powi(70)));
+
+            let vec = PyCell::new(py, PyVec2 { x: 1.0, y: 2.0 }).unwrap();
+            assert_eq!(py_to_json(vec).unwrap(), json::object! { x: 1.0, y: 2.0 });
+        });
+    }
+
+    #[test]
+    fn unconvertible_values_are_type_errors() {
+        Python::with_gil(|py| {
+            for code in ["{1: 'a'}", "{1, 2}", "object()"].iter() {
+                let error = py_to_json(py.eval(code, None, None).unwrap()).unwrap_err();
+                assert!(error.is_instance::<PyTypeError>(py), "{} raised {}", code, error);
+            }
+        });
+    }
+}
@@ -1,35 +1,89 @@
+use std::{cell::RefCell, rc::Rc};
+
 use json::JsonValue;
 use macroquad::prelude::*;
-use pyo3::prelude::*;
+use pyo3::{
+    exceptions::{PyIOError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
 
-use crate::{error, info, warn};
+use crate::{
+    assets::{animations, tilesets},
+    error, info, warn,
+};
 
-use super::WorldState;
+use super::{
+    actions::{ActionMap, AxisBindings, Binding},
+    components::{Camera, Name, Position, Scale, Tag, Tilemap},
+    convert::json_to_py,
+    input::{GamepadAxis, GamepadButton, InputSource},
+    python::{EntityId, PyEntity, PyRayHit, PyRect, PyVec2, PyWorldState},
+    scene::{PySceneEntity, StageScene},
+    storage::Entity,
+    systems::{collision::Colliders, render::screen_to_world},
+    WorldState,
+};
 
 /** The context that gets passed into the python scripts */
-#[pyclass]
+#[pyclass(unsendable)]
 pub(crate) struct HighgroundCtx {
     pub(crate) world_state: WorldState,
+    // The delta time of the stage, the fixed step during fixed updates
+    pub(crate) delta_time: f32,
+    pub(crate) frame_time: f32,
+    pub(crate) interpolation_alpha: f32,
+    // The source the input queries read from
+    pub(crate) input: Rc<dyn InputSource>,
+    // The input actions and axes, shared with the world
+    pub(crate) actions: Rc<RefCell<ActionMap>>,
+    // The scene camera, for converting the mouse position to the world
+    pub(crate) camera: Camera,
+    // The scene of the running stage
+    pub(crate) stage: Rc<RefCell<StageScene>>,
+    // The values that are kept when switching scenes
+    pub(crate) globals: PyObject,
     pub(crate) spawned_entities: Vec<JsonValue>,
+    pub(crate) despawned_entities: Vec<u64>,
+    pub(crate) saved_scenes: Vec<String>,
+    pub(crate) changed_scene: Option<String>,
+    // The id of the entity the script is attached to, for entity scripts
+    pub(crate) entity: Option<u64>,
+    // The dict kept for the entity script instance between calls
+    pub(crate) state: Option<PyObject>,
+    // The entities whose animation clip ended this frame, with the name of the clip
+    pub(crate) finished_animations: Vec<(u64, String)>,
+    // The clips the script started, with if they restart when already playing
+    pub(crate) played_animations: Vec<(u64, String, bool)>,
+    // The colliders as they were when the stage started
+    pub(crate) colliders: Rc<Colliders>,
 }
 
 #[pymethods]
 impl HighgroundCtx {
     // Timing info
 
-    /** Get the delta time */
+    /** Get the delta time, the fixed step inside `fixed_update` */
     pub fn delta_time(&self) -> PyResult<f32> {
-        return Ok(get_frame_time());
+        return Ok(self.delta_time);
     }
 
     /** Get the fps */
     pub fn fps(&self) -> PyResult<i32> {
-        return Ok(get_fps());
+        if self.frame_time <= 0.0 {
+            return Ok(0);
+        }
+        return Ok((1.0 / self.frame_time) as i32);
     }
 
     /** Get the frame time */
     pub fn frame_time(&self) -> PyResult<f32> {
-        return Ok(get_frame_time());
+        return Ok(self.frame_time);
+    }
+
+    /** Get how far the clock is between the last and the next fixed update, from 0 to 1 */
+    pub fn interpolation_alpha(&self) -> PyResult<f32> {
+        return Ok(self.interpolation_alpha);
     }
 
     // Logging functions
@@ -55,46 +109,450 @@ impl HighgroundCtx {
     // Input handeling
 
     /** Check if the key is pressed on the current frame */
-    pub fn is_key_pressed(&self, key: String) -> PyResult<bool> {
-        return Ok(is_key_pressed(string_to_keycode(&key)));
+    pub fn is_key_pressed(&self, key: &str) -> PyResult<bool> {
+        let key = string_to_keycode(key).ok_or_else(|| unknown_name("key", key))?;
+        return Ok(self.input.is_key_pressed(key));
     }
 
     /** Check if the key is released on the current frame */
-    pub fn is_key_released(&self, key: String) -> PyResult<bool> {
-        return Ok(is_key_released(string_to_keycode(&key)));
+    pub fn is_key_released(&self, key: &str) -> PyResult<bool> {
+        let key = string_to_keycode(key).ok_or_else(|| unknown_name("key", key))?;
+        return Ok(self.input.is_key_released(key));
     }
 
     /** Check if the key id held down */
-    pub fn is_key_down(&self, key: String) -> PyResult<bool> {
-        return Ok(is_key_down(string_to_keycode(&key)));
+    pub fn is_key_down(&self, key: &str) -> PyResult<bool> {
+        let key = string_to_keycode(key).ok_or_else(|| unknown_name("key", key))?;
+        return Ok(self.input.is_key_down(key));
+    }
+
+    /** Get the mouse position in pixels from the top left of the screen */
+    pub fn mouse_position(&self) -> PyResult<PyVec2> {
+        return Ok(self.input.mouse_position().into());
+    }
+
+    /** Get the position in the world under the mouse, as seen through the scene camera */
+    pub fn mouse_world_position(&self) -> PyResult<PyVec2> {
+        let position = screen_to_world(&self.camera, self.input.mouse_position(), self.input.screen_size());
+        return Ok(position.into());
+    }
+
+    /** Check if the mouse button is pressed on the current frame */
+    pub fn is_mouse_button_pressed(&self, button: &str) -> PyResult<bool> {
+        let button = string_to_mouse_button(button).ok_or_else(|| unknown_name("mouse button", button))?;
+        return Ok(self.input.is_mouse_button_pressed(button));
+    }
+
+    /** Check if the mouse button is released on the current frame */
+    pub fn is_mouse_button_released(&self, button: &str) -> PyResult<bool> {
+        let button = string_to_mouse_button(button).ok_or_else(|| unknown_name("mouse button", button))?;
+        return Ok(self.input.is_mouse_button_released(button));
+    }
+
+    /** Check if the mouse button is held down */
+    pub fn is_mouse_button_down(&self, button: &str) -> PyResult<bool> {
+        let button = string_to_mouse_button(button).ok_or_else(|| unknown_name("mouse button", button))?;
+        return Ok(self.input.is_mouse_button_down(button));
+    }
+
+    /** Get how far the mouse wheel scrolled on the current frame */
+    pub fn mouse_wheel(&self) -> PyResult<PyVec2> {
+        return Ok(self.input.mouse_wheel().into());
+    }
+
+    /** Get the text typed on the current frame */
+    pub fn text_input(&self) -> PyResult<String> {
+        return Ok(self.input.text_input());
+    }
+
+    /** Check if the gamepad is connected */
+    #[args(gamepad = "0")]
+    pub fn is_gamepad_connected(&self, gamepad: usize) -> PyResult<bool> {
+        return Ok(self.input.is_gamepad_connected(gamepad));
+    }
+
+    /** Check if the gamepad button is pressed on the current frame */
+    #[args(gamepad = "0")]
+    pub fn is_gamepad_button_pressed(&self, button: &str, gamepad: usize) -> PyResult<bool> {
+        let button = string_to_gamepad_button(button).ok_or_else(|| unknown_name("gamepad button", button))?;
+        return Ok(self.input.is_gamepad_button_pressed(gamepad, button));
+    }
+
+    /** Check if the gamepad button is released on the current frame */
+    #[args(gamepad = "0")]
+    pub fn is_gamepad_button_released(&self, button: &str, gamepad: usize) -> PyResult<bool> {
+        let button = string_to_gamepad_button(button).ok_or_else(|| unknown_name("gamepad button", button))?;
+        return Ok(self.input.is_gamepad_button_released(gamepad, button));
+    }
+
+    /** Check if the gamepad button is held down */
+    #[args(gamepad = "0")]
+    pub fn is_gamepad_button_down(&self, button: &str, gamepad: usize) -> PyResult<bool> {
+        let button = string_to_gamepad_button(button).ok_or_else(|| unknown_name("gamepad button", button))?;
+        return Ok(self.input.is_gamepad_button_down(gamepad, button));
+    }
+
+    /** Get the value of the gamepad axis, sticks go from -1 to 1 and triggers from 0 to 1 */
+    #[args(gamepad = "0")]
+    pub fn gamepad_axis(&self, axis: &str, gamepad: usize) -> PyResult<f32> {
+        let axis = string_to_gamepad_axis(axis).ok_or_else(|| unknown_name("gamepad axis", axis))?;
+        return Ok(self.input.gamepad_axis(gamepad, axis));
+    }
+
+    /** Check if any input of the action is pressed on the current frame */
+    pub fn is_action_pressed(&self, action: &str) -> PyResult<bool> {
+        let pressed = self.actions.borrow().is_action_pressed(&*self.input, action);
+        return pressed.ok_or_else(|| unknown_name("action", action));
+    }
+
+    /** Check if any input of the action is released on the current frame */
+    pub fn is_action_released(&self, action: &str) -> PyResult<bool> {
+        let released = self.actions.borrow().is_action_released(&*self.input, action);
+        return released.ok_or_else(|| unknown_name("action", action));
+    }
+
+    /** Check if any input of the action is held down */
+    pub fn is_action_down(&self, action: &str) -> PyResult<bool> {
+        let down = self.actions.borrow().is_action_down(&*self.input, action);
+        return down.ok_or_else(|| unknown_name("action", action));
+    }
+
+    /** Get the value of the axis from -1 to 1 */
+    pub fn axis(&self, axis: &str) -> PyResult<f32> {
+        let value = self.actions.borrow().axis(&*self.input, axis);
+        return value.ok_or_else(|| unknown_name("axis", axis));
+    }
+
+    /** Get the names of the inputs bound to the action */
+    pub fn action_bindings(&self, action: &str) -> PyResult<Vec<String>> {
+        let actions = self.actions.borrow();
+        let bindings = actions.action_bindings(action).ok_or_else(|| unknown_name("action", action))?;
+        return Ok(binding_names(bindings));
+    }
+
+    /** Get the names of the negative and the positive inputs bound to the axis */
+    pub fn axis_bindings(&self, axis: &str) -> PyResult<(Vec<String>, Vec<String>)> {
+        let actions = self.actions.borrow();
+        let bindings = actions.axis_bindings(axis).ok_or_else(|| unknown_name("axis", axis))?;
+        return Ok((binding_names(&bindings.negative), binding_names(&bindings.positive)));
+    }
+
+    /** Bind the action to the inputs instead, the bindings are saved to the input settings file */
+    pub fn rebind_action(&mut self, action: &str, bindings: Vec<String>) -> PyResult<()> {
+        let bindings = parse_bindings(&bindings)?;
+        let mut actions = self.actions.borrow_mut();
+        actions
+            .rebind_action(action, bindings)
+            .map_err(|e| PyValueError::new_err(format!("{:#}", e)))?;
+        actions.save_settings().map_err(|e| PyIOError::new_err(format!("{:#}", e)))?;
+        return Ok(());
+    }
+
+    /** Bind the axis to the negative and positive inputs instead, the bindings are saved to the input settings file */
+    pub fn rebind_axis(&mut self, axis: &str, negative: Vec<String>, positive: Vec<String>) -> PyResult<()> {
+        let bindings = AxisBindings {
+            negative: parse_bindings(&negative)?,
+            positive: parse_bindings(&positive)?,
+        };
+        let mut actions = self.actions.borrow_mut();
+        actions
+            .rebind_axis(axis, bindings)
+            .map_err(|e| PyValueError::new_err(format!("{:#}", e)))?;
+        actions.save_settings().map_err(|e| PyIOError::new_err(format!("{:#}", e)))?;
+        return Ok(());
     }
 
     // Scene manipulation
-    /** Spawn an entity in the scene */
-    pub fn spawn_entity(&mut self, entity_input: String) -> PyResult<()> {
-        if let Ok(entity) = json::parse(&entity_input).map_err(|e| {
+    /** Spawn an entity in the scene, returns the id it will receive */
+    pub fn spawn_entity(&mut self, entity_input: String) -> PyResult<Option<PyEntity>> {
+        if let Ok(mut entity) = json::parse(&entity_input).map_err(|e| {
             error!("Failed to spawn entity: {}", e);
         }) {
+            if !entity.is_object() {
+                error!("Failed to spawn entity, not a json object: {}", entity_input);
+                return Ok(None);
+            }
             info!("spawing: {}", entity_input);
+            // Reserve the id now so the script can refer to the entity
+            let mut stage = self.stage.borrow_mut();
+            let id = stage.registry.next_id();
+            stage.registry.reserve_ids(id + 1);
+            entity["id"] = id.into();
             self.spawned_entities.push(entity);
+            return Ok(Some(PyEntity::new(id)));
         }
+        return Ok(None);
+    }
+
+    /**
+    Despawn the entity with the id.
+    The entity is removed after the script returns, before the next script runs.
+    */
+    pub fn despawn_entity(&mut self, id: EntityId) -> PyResult<()> {
+        self.despawned_entities.push(id);
         return Ok(());
     }
 
+    /**
+    Play an animation clip from the asset descriptor on the entity.
+    A clip that is already playing keeps going unless `restart` is set.
+    The animation is set after the script returns, like despawns.
+    */
+    #[args(restart = "false")]
+    pub fn play_animation(&mut self, id: EntityId, clip: &str, restart: bool) -> PyResult<()> {
+        if !animations().contains_key(clip) {
+            return Err(PyValueError::new_err(format!("Unknown animation clip \"{}\"", clip)));
+        }
+        self.played_animations.push((id, clip.to_string(), restart));
+        return Ok(());
+    }
+
+    /** Get the entities whose animation clip ended this frame, with the name of the clip */
+    pub fn finished_animations(&self) -> PyResult<Vec<(PyEntity, String)>> {
+        return Ok(self
+            .finished_animations
+            .iter()
+            .map(|(id, clip)| (PyEntity::new(*id), clip.clone()))
+            .collect());
+    }
+
+    /** Get the entity with the id from the scene, none if it does not exist */
+    pub fn get_entity(&self, id: EntityId) -> PyResult<Option<PySceneEntity>> {
+        if !self.stage.borrow().registry.contains(Entity::from_id(id)) {
+            return Ok(None);
+        }
+        return Ok(Some(PySceneEntity::new(&self.stage, Entity::from_id(id))));
+    }
+
+    /** Find the first entity with the name */
+    pub fn find_by_name(&self, py: Python, name: String) -> PyResult<Option<PyEntity>> {
+        let stage = self.stage.borrow();
+        let entity = stage.registry.entities().iter().find(|entity| {
+            return stage.component::<Name>(py, **entity).map(|n| n.0 == name).unwrap_or(false);
+        });
+        return Ok(entity.map(|entity| PyEntity::from(*entity)));
+    }
+
+    /** Find all entities with the tag */
+    pub fn find_by_tag(&self, py: Python, tag: String) -> PyResult<Vec<PyEntity>> {
+        let stage = self.stage.borrow();
+        return Ok(stage
+            .registry
+            .entities()
+            .iter()
+            .filter(|entity| stage.component::<Tag>(py, **entity).map(|t| t.0 == tag).unwrap_or(false))
+            .map(|entity| PyEntity::from(*entity))
+            .collect());
+    }
+
+    /**
+    Get the tiles of the tilemaps at a position in the world, the topmost first.
+    Each tile is a dict of the `entity`, the `layer` name, the `column`, `row` and `gid` and the `properties` of
+    the tile in its tileset, like `collision`.
+    */
+    pub fn tiles_at(&self, py: Python, x: f32, y: f32) -> PyResult<Vec<PyObject>> {
+        let mut result = vec![];
+        for tile in self.find_tiles(py, vec2(x, y))? {
+            let properties = tilesets()
+                .get(&tile.tileset)
+                .and_then(|tileset| tileset.properties(tile.id))
+                .cloned()
+                .unwrap_or_else(JsonValue::new_object);
+            let dict = PyDict::new(py);
+            dict.set_item("entity", PyEntity::new(tile.entity).into_py(py))?;
+            dict.set_item("layer", tile.layer)?;
+            dict.set_item("column", tile.column)?;
+            dict.set_item("row", tile.row)?;
+            dict.set_item("gid", tile.gid)?;
+            dict.set_item("properties", json_to_py(py, &properties)?)?;
+            result.push(dict.into());
+        }
+        return Ok(result);
+    }
+
+    /** Check if any tile at a position in the world has its `collision` property set */
+    pub fn is_solid_at(&self, py: Python, x: f32, y: f32) -> PyResult<bool> {
+        return Ok(self.find_tiles(py, vec2(x, y))?.iter().any(|tile| {
+            return tilesets()
+                .get(&tile.tileset)
+                .map(|tileset| tileset.is_solid(tile.id))
+                .unwrap_or(false);
+        }));
+    }
+
+    /**
+    Get the entities whose collider contains a point in the world, in scene order.
+    The queries see the colliders as they were when the stage started, moving an entity does not move its collider.
+    */
+    pub fn overlap_point(&self, point: PyVec2) -> PyResult<Vec<PyEntity>> {
+        return Ok(self
+            .colliders
+            .overlap_point(point.into())
+            .into_iter()
+            .map(PyEntity::from)
+            .collect());
+    }
+
+    /** Get the entities whose collider overlaps a rect in the world, in scene order */
+    pub fn overlap_rect(&self, rect: PyRect) -> PyResult<Vec<PyEntity>> {
+        return Ok(self
+            .colliders
+            .overlap_rect(rect.into())
+            .into_iter()
+            .map(PyEntity::from)
+            .collect());
+    }
+
+    /**
+    Cast a ray from an origin along a direction and get the first collider it hits, none if it hits nothing.
+    Without a max distance the ray goes on until it leaves the colliders behind.
+    */
+    #[args(max_distance = "None")]
+    pub fn raycast(&self, origin: PyVec2, direction: PyVec2, max_distance: Option<f32>) -> PyResult<Option<PyRayHit>> {
+        let (origin, direction): (Vec2, Vec2) = (origin.into(), direction.into());
+        if direction.length_squared() == 0.0 || !direction.is_finite() {
+            return Err(PyValueError::new_err("The direction of a raycast has to be finite and can't be zero"));
+        }
+        if !origin.is_finite() {
+            return Err(PyValueError::new_err("The origin of a raycast has to be finite"));
+        }
+        if max_distance.map(|distance| !distance.is_finite()) == Some(true) {
+            return Err(PyValueError::new_err("The max distance of a raycast has to be finite, leave it out for none"));
+        }
+        return Ok(self
+            .colliders
+            .raycast(origin, direction, max_distance.unwrap_or(f32::INFINITY))
+            .map(PyRayHit::from));
+    }
+
+    /** Save the scene to a file once the current script stage has finished */
+    pub fn save_scene(&mut self, path: String) -> PyResult<()> {
+        self.saved_scenes.push(path);
+        return Ok(());
+    }
+
+    /**
+    Switch to another scene at the end of the frame.
+    The scripts of the current scene get their `exit` stage called before it is unloaded.
+    If the scene fails to load, the current scene stays and the error is handled like an exception of the script.
+    */
+    pub fn change_scene(&mut self, path: String) -> PyResult<()> {
+        self.changed_scene = Some(path);
+        return Ok(());
+    }
+
+    /** Get the dict of values that are kept when switching scenes */
+    pub fn globals(&self, py: Python) -> PyResult<PyObject> {
+        return Ok(self.globals.clone_ref(py));
+    }
+
+    /** Get the id of the entity the script is attached to, none for scene scripts */
+    pub fn entity_id(&self) -> PyResult<Option<PyEntity>> {
+        return Ok(self.entity.map(PyEntity::new));
+    }
+
+    /** Get the dict that is kept for the entity script instance between calls, none for scene scripts */
+    pub fn state(&self, py: Python) -> PyResult<Option<PyObject>> {
+        return Ok(self.state.as_ref().map(|state| state.clone_ref(py)));
+    }
+
     /** Get the world state */
-    pub fn world_state(&self) -> PyResult<String> {
-        return Ok(match self.world_state {
-            WorldState::Editor => "Editor",
-            WorldState::Menu => "Menu",
-            WorldState::Gameplay => "Gameplay",
-        }.into());
+    pub fn world_state(&self) -> PyResult<PyWorldState> {
+        return Ok(PyWorldState {
+            state: self.world_state,
+        });
+    }
+}
+
+/** A tile found at a position */
+struct FoundTile {
+    entity: u64,
+    // The name of the tile layer
+    layer: String,
+    // The layer it is drawn on among the sprites
+    draw_layer: i32,
+    column: usize,
+    row: usize,
+    // The gid with the flip bits
+    gid: u32,
+    tileset: String,
+    // The id of the tile in the tileset
+    id: u32,
+}
+
+impl HighgroundCtx {
+    /**
+    Find the tiles at a position in the tilemaps of the scene, topmost first.
+    The tilemaps the script changed are read the way it left them, like the other queries of the scene.
+    */
+    fn find_tiles(&self, py: Python, point: Vec2) -> PyResult<Vec<FoundTile>> {
+        let stage = self.stage.borrow();
+        let mut found = vec![];
+        for entity in stage.registry.entities() {
+            let map = match stage.component::<Tilemap>(py, *entity) {
+                Some(map) => map,
+                None => continue,
+            };
+            let position = stage.component::<Position>(py, *entity).map(|p| p.0).unwrap_or(Vec2::ZERO);
+            let scale = stage.component::<Scale>(py, *entity).map(|s| s.0).unwrap_or(Vec2::ONE);
+
+            let size = map.tile_size * scale;
+            let tile = ((point - position) / size).floor();
+            if !(tile.x >= 0.0 && tile.y >= 0.0) {
+                continue;
+            }
+            let (column, row) = (tile.x as usize, tile.y as usize);
+            if column >= map.width || row >= map.height {
+                continue;
+            }
+            for (index, layer) in map.layers.iter().enumerate() {
+                if !layer.visible {
+                    continue;
+                }
+                let gid = map.tile(index, column, row);
+                if let Some((tileset, tile_id)) = map.resolve(gid) {
+                    found.push(FoundTile {
+                        entity: entity.id(),
+                        layer: layer.name.clone(),
+                        draw_layer: layer.layer,
+                        column,
+                        row,
+                        gid,
+                        tileset: tileset.name.clone(),
+                        id: tile_id,
+                    });
+                }
+            }
+        }
+        // The tiles drawn last are on top, tile layers are drawn by layer and then in scene order
+        found.reverse();
+        found.sort_by_key(|tile| -tile.draw_layer);
+        return Ok(found);
     }
 }
 
-/** Convert a u32 to a keycode */
-fn string_to_keycode(key: &str) -> KeyCode {
+/** The error for a name that is not a key, button, axis or action */
+fn unknown_name(kind: &str, name: &str) -> PyErr {
+    return PyValueError::new_err(format!("Unknown {} name \"{}\"", kind, name));
+}
+
+/** Parse binding names, raising an error for the first one that names no input */
+fn parse_bindings(names: &[String]) -> PyResult<Vec<Binding>> {
+    return names
+        .iter()
+        .map(|name| Binding::parse(name).ok_or_else(|| unknown_name("input", name)))
+        .collect();
+}
+
+/** Get the names of bindings */
+fn binding_names(bindings: &[Binding]) -> Vec<String> {
+    return bindings.iter().map(|binding| binding.name().to_string()).collect();
+}
+
+/** Convert a key name to a keycode, the names are the ones of `ie.Key` */
+pub(crate) fn string_to_keycode(key: &str) -> Option<KeyCode> {
     use KeyCode::*;
-    return match key {
+    return Some(match key {
         "Space" => Space,
         "Apostrophe" => Apostrophe,
         "Comma" => Comma,
@@ -215,6 +673,54 @@ fn string_to_keycode(key: &str) -> KeyCode {
         "RightAlt" => RightAlt,
         "RightSuper" => RightSuper,
         "Menu" => Menu,
-        _ => Unknown,
-    };
+        _ => return None,
+    });
+}
+
+/** Convert a mouse button name to a mouse button, the names are the ones of `ie.MouseButton` */
+pub(crate) fn string_to_mouse_button(button: &str) -> Option<MouseButton> {
+    return Some(match button {
+        "Left" => MouseButton::Left,
+        "Right" => MouseButton::Right,
+        "Middle" => MouseButton::Middle,
+        _ => return None,
+    });
+}
+
+/** Convert a gamepad button name to a gamepad button, the names are the ones of `ie.GamepadButton` */
+pub(crate) fn string_to_gamepad_button(button: &str) -> Option<GamepadButton> {
+    use GamepadButton::*;
+    return Some(match button {
+        // The positions are aliases of the xbox names
+        "A" | "South" => A,
+        "B" | "East" => B,
+        "X" | "West" => X,
+        "Y" | "North" => Y,
+        "LeftBumper" => LeftBumper,
+        "RightBumper" => RightBumper,
+        "Back" => Back,
+        "Start" => Start,
+        "Guide" => Guide,
+        "LeftStick" => LeftStick,
+        "RightStick" => RightStick,
+        "DPadUp" => DPadUp,
+        "DPadDown" => DPadDown,
+        "DPadLeft" => DPadLeft,
+        "DPadRight" => DPadRight,
+        _ => return None,
+    });
+}
+
+/** Convert a gamepad axis name to a gamepad axis, the names are the ones of `ie.GamepadAxis` */
+pub(crate) fn string_to_gamepad_axis(axis: &str) -> Option<GamepadAxis> {
+    use GamepadAxis::*;
+    return Some(match axis {
+        "LeftX" => LeftX,
+        "LeftY" => LeftY,
+        "RightX" => RightX,
+        "RightY" => RightY,
+        "LeftTrigger" => LeftTrigger,
+        "RightTrigger" => RightTrigger,
+        _ => return None,
+    });
 }
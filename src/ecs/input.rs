@@ -0,0 +1,575 @@
+//! Input sources the world and the scripts read from
+use std::{cell::RefCell, hash::Hash};
+
+use macroquad::prelude::{KeyCode, MouseButton, Vec2};
+
+use crate::warn;
+
+/** A button of a gamepad, named after the positions on an xbox controller */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    LeftBumper,
+    RightBumper,
+    Back,
+    Start,
+    Guide,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/** An axis of a gamepad, sticks go from -1 to 1 and triggers from 0 to 1 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/** A source of input state, so the world can run without a window */
+pub trait InputSource {
+    /** Called by the world at the start of each frame, before anything reads the input */
+    fn begin_frame(&self) {}
+
+    /** Check if the key is pressed on the current frame */
+    fn is_key_pressed(&self, key: KeyCode) -> bool;
+    /** Check if the key is released on the current frame */
+    fn is_key_released(&self, key: KeyCode) -> bool;
+    /** Check if the key is held down */
+    fn is_key_down(&self, key: KeyCode) -> bool;
+
+    /** Get the size of the screen in pixels */
+    fn screen_size(&self) -> Vec2;
+    /** Get the mouse position in pixels from the top left of the screen */
+    fn mouse_position(&self) -> Vec2;
+    /** Check if the mouse button is pressed on the current frame */
+    fn is_mouse_button_pressed(&self, button: MouseButton) -> bool;
+    /** Check if the mouse button is released on the current frame */
+    fn is_mouse_button_released(&self, button: MouseButton) -> bool;
+    /** Check if the mouse button is held down */
+    fn is_mouse_button_down(&self, button: MouseButton) -> bool;
+    /** Get how far the mouse wheel scrolled on the current frame */
+    fn mouse_wheel(&self) -> Vec2;
+    /** Get the text typed on the current frame */
+    fn text_input(&self) -> String;
+
+    /** Check if the gamepad is connected, there are none by default */
+    fn is_gamepad_connected(&self, _gamepad: usize) -> bool {
+        return false;
+    }
+    /** Check if the gamepad button is pressed on the current frame */
+    fn is_gamepad_button_pressed(&self, _gamepad: usize, _button: GamepadButton) -> bool {
+        return false;
+    }
+    /** Check if the gamepad button is released on the current frame */
+    fn is_gamepad_button_released(&self, _gamepad: usize, _button: GamepadButton) -> bool {
+        return false;
+    }
+    /** Check if the gamepad button is held down */
+    fn is_gamepad_button_down(&self, _gamepad: usize, _button: GamepadButton) -> bool {
+        return false;
+    }
+    /** Get the value of the gamepad axis */
+    fn gamepad_axis(&self, _gamepad: usize, _axis: GamepadAxis) -> f32 {
+        return 0.0;
+    }
+}
+
+/** The gamepads read through gilrs, with the buttons pressed and released on the current frame */
+struct Gamepads {
+    gilrs: gilrs::Gilrs,
+    pressed: hashbrown::HashSet<(usize, GamepadButton)>,
+    released: hashbrown::HashSet<(usize, GamepadButton)>,
+}
+
+impl Gamepads {
+    /** Take the gamepad events that happened since the last frame */
+    fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            match event {
+                gilrs::EventType::ButtonPressed(button, _) => {
+                    if let Some(button) = from_gilrs_button(button) {
+                        self.pressed.insert((id.into(), button));
+                    }
+                }
+                gilrs::EventType::ButtonReleased(button, _) => {
+                    if let Some(button) = from_gilrs_button(button) {
+                        self.released.insert((id.into(), button));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /** Get the gamepad with the number, if it is connected */
+    fn gamepad(&self, gamepad: usize) -> Option<gilrs::Gamepad<'_>> {
+        return self
+            .gilrs
+            .gamepads()
+            .find(|(id, _)| usize::from(*id) == gamepad)
+            .map(|(_, gamepad)| gamepad);
+    }
+}
+
+/** Convert a gilrs button to a gamepad button, the gilrs names are the positions on the pad */
+fn from_gilrs_button(button: gilrs::Button) -> Option<GamepadButton> {
+    return match button {
+        gilrs::Button::South => Some(GamepadButton::A),
+        gilrs::Button::East => Some(GamepadButton::B),
+        gilrs::Button::West => Some(GamepadButton::X),
+        gilrs::Button::North => Some(GamepadButton::Y),
+        gilrs::Button::LeftTrigger => Some(GamepadButton::LeftBumper),
+        gilrs::Button::RightTrigger => Some(GamepadButton::RightBumper),
+        gilrs::Button::Select => Some(GamepadButton::Back),
+        gilrs::Button::Start => Some(GamepadButton::Start),
+        gilrs::Button::Mode => Some(GamepadButton::Guide),
+        gilrs::Button::LeftThumb => Some(GamepadButton::LeftStick),
+        gilrs::Button::RightThumb => Some(GamepadButton::RightStick),
+        gilrs::Button::DPadUp => Some(GamepadButton::DPadUp),
+        gilrs::Button::DPadDown => Some(GamepadButton::DPadDown),
+        gilrs::Button::DPadLeft => Some(GamepadButton::DPadLeft),
+        gilrs::Button::DPadRight => Some(GamepadButton::DPadRight),
+        _ => None,
+    };
+}
+
+/** Convert a gamepad button to the gilrs button */
+fn to_gilrs_button(button: GamepadButton) -> gilrs::Button {
+    return match button {
+        GamepadButton::A => gilrs::Button::South,
+        GamepadButton::B => gilrs::Button::East,
+        GamepadButton::X => gilrs::Button::West,
+        GamepadButton::Y => gilrs::Button::North,
+        GamepadButton::LeftBumper => gilrs::Button::LeftTrigger,
+        GamepadButton::RightBumper => gilrs::Button::RightTrigger,
+        GamepadButton::Back => gilrs::Button::Select,
+        GamepadButton::Start => gilrs::Button::Start,
+        GamepadButton::Guide => gilrs::Button::Mode,
+        GamepadButton::LeftStick => gilrs::Button::LeftThumb,
+        GamepadButton::RightStick => gilrs::Button::RightThumb,
+        GamepadButton::DPadUp => gilrs::Button::DPadUp,
+        GamepadButton::DPadDown => gilrs::Button::DPadDown,
+        GamepadButton::DPadLeft => gilrs::Button::DPadLeft,
+        GamepadButton::DPadRight => gilrs::Button::DPadRight,
+    };
+}
+
+/**
+Reads the input from the macroquad window, and the gamepads through gilrs.
+Gamepads are numbered in the order they were first connected, and the y axes of the sticks point down like the
+screen. Without gamepad support on the platform no gamepads are ever connected.
+*/
+#[derive(Default)]
+pub struct MacroquadInput {
+    // The characters typed on the current frame
+    text: RefCell<String>,
+    // The gamepads, none if gilrs could not start
+    gamepads: Option<RefCell<Gamepads>>,
+}
+
+impl MacroquadInput {
+    /** Construct input that reads from the window and the connected gamepads */
+    pub fn new() -> Self {
+        let gamepads = match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(gilrs::Error::NotImplemented(_)) => None,
+            Err(e) => {
+                warn!("Gamepads are not available: {}", e);
+                None
+            }
+        };
+        return Self {
+            text: RefCell::new(String::new()),
+            gamepads: gamepads.map(|gilrs| {
+                return RefCell::new(Gamepads {
+                    gilrs,
+                    pressed: hashbrown::HashSet::new(),
+                    released: hashbrown::HashSet::new(),
+                });
+            }),
+        };
+    }
+}
+
+impl InputSource for MacroquadInput {
+    fn begin_frame(&self) {
+        // Macroquad hands out each typed character once, so take them all for the frame
+        let mut text = vec![];
+        while let Some(character) = macroquad::prelude::get_char_pressed() {
+            text.push(character);
+        }
+        // The queue returns the last typed character first
+        *self.text.borrow_mut() = text.into_iter().rev().collect();
+        if let Some(gamepads) = &self.gamepads {
+            gamepads.borrow_mut().begin_frame();
+        }
+    }
+
+    fn is_key_pressed(&self, key: KeyCode) -> bool {
+        return macroquad::prelude::is_key_pressed(key);
+    }
+
+    fn is_key_released(&self, key: KeyCode) -> bool {
+        return macroquad::prelude::is_key_released(key);
+    }
+
+    fn is_key_down(&self, key: KeyCode) -> bool {
+        return macroquad::prelude::is_key_down(key);
+    }
+
+    fn screen_size(&self) -> Vec2 {
+        return Vec2::new(macroquad::prelude::screen_width(), macroquad::prelude::screen_height());
+    }
+
+    fn mouse_position(&self) -> Vec2 {
+        return macroquad::prelude::mouse_position().into();
+    }
+
+    fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        return macroquad::prelude::is_mouse_button_pressed(button);
+    }
+
+    fn is_mouse_button_released(&self, button: MouseButton) -> bool {
+        return macroquad::prelude::is_mouse_button_released(button);
+    }
+
+    fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        return macroquad::prelude::is_mouse_button_down(button);
+    }
+
+    fn mouse_wheel(&self) -> Vec2 {
+        return macroquad::prelude::mouse_wheel().into();
+    }
+
+    fn text_input(&self) -> String {
+        return self.text.borrow().clone();
+    }
+
+    fn is_gamepad_connected(&self, gamepad: usize) -> bool {
+        return self
+            .gamepads
+            .as_ref()
+            .map(|gamepads| gamepads.borrow().gamepad(gamepad).is_some())
+            .unwrap_or(false);
+    }
+
+    fn is_gamepad_button_pressed(&self, gamepad: usize, button: GamepadButton) -> bool {
+        return self
+            .gamepads
+            .as_ref()
+            .map(|gamepads| gamepads.borrow().pressed.contains(&(gamepad, button)))
+            .unwrap_or(false);
+    }
+
+    fn is_gamepad_button_released(&self, gamepad: usize, button: GamepadButton) -> bool {
+        return self
+            .gamepads
+            .as_ref()
+            .map(|gamepads| gamepads.borrow().released.contains(&(gamepad, button)))
+            .unwrap_or(false);
+    }
+
+    fn is_gamepad_button_down(&self, gamepad: usize, button: GamepadButton) -> bool {
+        let gamepads = match &self.gamepads {
+            Some(gamepads) => gamepads.borrow(),
+            None => return false,
+        };
+        return gamepads
+            .gamepad(gamepad)
+            .map(|gamepad| gamepad.is_pressed(to_gilrs_button(button)))
+            .unwrap_or(false);
+    }
+
+    fn gamepad_axis(&self, gamepad: usize, axis: GamepadAxis) -> f32 {
+        let gamepads = match &self.gamepads {
+            Some(gamepads) => gamepads.borrow(),
+            None => return 0.0,
+        };
+        let gamepad = match gamepads.gamepad(gamepad) {
+            Some(gamepad) => gamepad,
+            None => return 0.0,
+        };
+        return match axis {
+            GamepadAxis::LeftX => gamepad.value(gilrs::Axis::LeftStickX),
+            // Gilrs points the y axes up
+            GamepadAxis::LeftY => -gamepad.value(gilrs::Axis::LeftStickY),
+            GamepadAxis::RightX => gamepad.value(gilrs::Axis::RightStickX),
+            GamepadAxis::RightY => -gamepad.value(gilrs::Axis::RightStickY),
+            // Analog triggers are reported as the value of their button
+            GamepadAxis::LeftTrigger => gamepad
+                .button_data(gilrs::Button::LeftTrigger2)
+                .map(|data| data.value())
+                .unwrap_or(0.0),
+            GamepadAxis::RightTrigger => gamepad
+                .button_data(gilrs::Button::RightTrigger2)
+                .map(|data| data.value())
+                .unwrap_or(0.0),
+        };
+    }
+}
+
+/** The states of a kind of button in a scripted input frame */
+struct ScriptedButtons<T> {
+    down: hashbrown::HashSet<T>,
+    pressed: hashbrown::HashSet<T>,
+    released: hashbrown::HashSet<T>,
+}
+
+impl<T> ScriptedButtons<T>
+where
+    T: Copy + Eq + Hash,
+{
+    fn press(&mut self, button: T) {
+        if self.down.insert(button) {
+            self.pressed.insert(button);
+        }
+    }
+
+    fn release(&mut self, button: T) {
+        if self.down.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    fn release_all(&mut self) {
+        let down = self.down.drain().collect::<Vec<_>>();
+        self.released.extend(down);
+    }
+
+    fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+}
+
+impl<T> Default for ScriptedButtons<T> {
+    fn default() -> Self {
+        return Self {
+            down: hashbrown::HashSet::new(),
+            pressed: hashbrown::HashSet::new(),
+            released: hashbrown::HashSet::new(),
+        };
+    }
+}
+
+/** The state of a scripted input frame */
+struct ScriptedState {
+    keys: ScriptedButtons<KeyCode>,
+    mouse_buttons: ScriptedButtons<MouseButton>,
+    gamepad_buttons: ScriptedButtons<(usize, GamepadButton)>,
+    gamepad_axes: hashbrown::HashMap<(usize, GamepadAxis), f32>,
+    gamepads: hashbrown::HashSet<usize>,
+    screen_size: Vec2,
+    mouse_position: Vec2,
+    mouse_wheel: Vec2,
+    text: String,
+}
+
+impl Default for ScriptedState {
+    fn default() -> Self {
+        return Self {
+            keys: ScriptedButtons::default(),
+            mouse_buttons: ScriptedButtons::default(),
+            gamepad_buttons: ScriptedButtons::default(),
+            gamepad_axes: hashbrown::HashMap::new(),
+            gamepads: hashbrown::HashSet::new(),
+            // The default size of a macroquad window
+            screen_size: Vec2::new(800.0, 600.0),
+            mouse_position: Vec2::ZERO,
+            mouse_wheel: Vec2::ZERO,
+            text: String::new(),
+        };
+    }
+}
+
+/**
+Input driven from code instead of a window, for running the world headless.
+Presses, releases, scrolling and typed text are seen for one frame, held buttons stay down until released.
+*/
+#[derive(Default)]
+pub struct ScriptedInput {
+    state: RefCell<ScriptedState>,
+}
+
+impl ScriptedInput {
+    /** Construct input with no keys held */
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /** Press and hold a key */
+    pub fn press(&self, key: KeyCode) {
+        self.state.borrow_mut().keys.press(key);
+    }
+
+    /** Release a held key */
+    pub fn release(&self, key: KeyCode) {
+        self.state.borrow_mut().keys.release(key);
+    }
+
+    /** Release every held key, mouse button and gamepad button */
+    pub fn release_all(&self) {
+        let mut state = self.state.borrow_mut();
+        state.keys.release_all();
+        state.mouse_buttons.release_all();
+        state.gamepad_buttons.release_all();
+    }
+
+    /** Set the size of the screen the mouse moves on, 800 by 600 by default */
+    pub fn set_screen_size(&self, width: f32, height: f32) {
+        self.state.borrow_mut().screen_size = Vec2::new(width, height);
+    }
+
+    /** Move the mouse to a position in pixels from the top left of the screen */
+    pub fn move_mouse(&self, x: f32, y: f32) {
+        self.state.borrow_mut().mouse_position = Vec2::new(x, y);
+    }
+
+    /** Press and hold a mouse button */
+    pub fn press_mouse_button(&self, button: MouseButton) {
+        self.state.borrow_mut().mouse_buttons.press(button);
+    }
+
+    /** Release a held mouse button */
+    pub fn release_mouse_button(&self, button: MouseButton) {
+        self.state.borrow_mut().mouse_buttons.release(button);
+    }
+
+    /** Scroll the mouse wheel on the current frame */
+    pub fn scroll(&self, x: f32, y: f32) {
+        self.state.borrow_mut().mouse_wheel += Vec2::new(x, y);
+    }
+
+    /** Type text on the current frame */
+    pub fn type_text(&self, text: &str) {
+        self.state.borrow_mut().text.push_str(text);
+    }
+
+    /** Connect a gamepad */
+    pub fn connect_gamepad(&self, gamepad: usize) {
+        self.state.borrow_mut().gamepads.insert(gamepad);
+    }
+
+    /** Disconnect a gamepad, its buttons are released and its axes reset */
+    pub fn disconnect_gamepad(&self, gamepad: usize) {
+        let mut state = self.state.borrow_mut();
+        state.gamepads.remove(&gamepad);
+        let held = state
+            .gamepad_buttons
+            .down
+            .iter()
+            .filter(|(held, _)| *held == gamepad)
+            .copied()
+            .collect::<Vec<_>>();
+        for button in held {
+            state.gamepad_buttons.release(button);
+        }
+        state.gamepad_axes.retain(|(moved, _), _| *moved != gamepad);
+    }
+
+    /** Press and hold a button of a connected gamepad */
+    pub fn press_gamepad_button(&self, gamepad: usize, button: GamepadButton) {
+        let mut state = self.state.borrow_mut();
+        if state.gamepads.contains(&gamepad) {
+            state.gamepad_buttons.press((gamepad, button));
+        }
+    }
+
+    /** Release a held button of a gamepad */
+    pub fn release_gamepad_button(&self, gamepad: usize, button: GamepadButton) {
+        self.state.borrow_mut().gamepad_buttons.release((gamepad, button));
+    }
+
+    /** Move an axis of a connected gamepad, it keeps its value until moved again */
+    pub fn set_gamepad_axis(&self, gamepad: usize, axis: GamepadAxis, value: f32) {
+        let mut state = self.state.borrow_mut();
+        if state.gamepads.contains(&gamepad) {
+            state.gamepad_axes.insert((gamepad, axis), value);
+        }
+    }
+
+    /** Clear the presses, releases, scrolling and text of the frame that just ran */
+    pub fn end_frame(&self) {
+        let mut state = self.state.borrow_mut();
+        state.keys.end_frame();
+        state.mouse_buttons.end_frame();
+        state.gamepad_buttons.end_frame();
+        state.mouse_wheel = Vec2::ZERO;
+        state.text.clear();
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn is_key_pressed(&self, key: KeyCode) -> bool {
+        return self.state.borrow().keys.pressed.contains(&key);
+    }
+
+    fn is_key_released(&self, key: KeyCode) -> bool {
+        return self.state.borrow().keys.released.contains(&key);
+    }
+
+    fn is_key_down(&self, key: KeyCode) -> bool {
+        return self.state.borrow().keys.down.contains(&key);
+    }
+
+    fn screen_size(&self) -> Vec2 {
+        return self.state.borrow().screen_size;
+    }
+
+    fn mouse_position(&self) -> Vec2 {
+        return self.state.borrow().mouse_position;
+    }
+
+    fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        return self.state.borrow().mouse_buttons.pressed.contains(&button);
+    }
+
+    fn is_mouse_button_released(&self, button: MouseButton) -> bool {
+        return self.state.borrow().mouse_buttons.released.contains(&button);
+    }
+
+    fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        return self.state.borrow().mouse_buttons.down.contains(&button);
+    }
+
+    fn mouse_wheel(&self) -> Vec2 {
+        return self.state.borrow().mouse_wheel;
+    }
+
+    fn text_input(&self) -> String {
+        return self.state.borrow().text.clone();
+    }
+
+    fn is_gamepad_connected(&self, gamepad: usize) -> bool {
+        return self.state.borrow().gamepads.contains(&gamepad);
+    }
+
+    fn is_gamepad_button_pressed(&self, gamepad: usize, button: GamepadButton) -> bool {
+        return self.state.borrow().gamepad_buttons.pressed.contains(&(gamepad, button));
+    }
+
+    fn is_gamepad_button_released(&self, gamepad: usize, button: GamepadButton) -> bool {
+        return self.state.borrow().gamepad_buttons.released.contains(&(gamepad, button));
+    }
+
+    fn is_gamepad_button_down(&self, gamepad: usize, button: GamepadButton) -> bool {
+        return self.state.borrow().gamepad_buttons.down.contains(&(gamepad, button));
+    }
+
+    fn gamepad_axis(&self, gamepad: usize, axis: GamepadAxis) -> f32 {
+        return self.state.borrow().gamepad_axes.get(&(gamepad, axis)).copied().unwrap_or(0.0);
+    }
+}
@@ -1,22 +1,59 @@
-#[allow(unused_must_use)]
 use crate::{
+    debug::watch::FileWatcher,
     error,
-    error::{IslandError, IslandResult},
-    info,
+    error::{ErrorContext, ErrorKind, IslandError, IslandResult},
+    info, warn,
+};
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
 };
-use std::path::PathBuf;
 use json::JsonValue;
-use pyo3::prelude::*;
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
 
-use self::systems::{
-    debug::reload_systems,
-    render::{handle_camera, render_sprites, render_transforms},
+use self::{
+    actions::ActionMap,
+    components::{Animation, Camera, Component, Position, Script},
+    convert::{json_to_py, py_to_json},
+    storage::{Entity, EntityMut, EntityRef, Registry},
+    systems::{
+        animation::advance_animations,
+        collision::Colliders,
+        debug::reload_systems,
+        sorted_json,
+        render::{handle_camera, render_sprites, render_transforms, view_rect},
+    },
+    input::{InputSource, MacroquadInput},
+    prefab::Prefabs,
+    python::PyEntity,
+    scene::{returned_scene_to_json, PyScene, PySceneEntity, StageScene},
+    script::{exec_module, module_name, set_python_path, EntityScripts, ScriptEvent, ScriptFailure, ENTITY_STAGES},
+    validate::validate_scene,
+    time::Time,
 };
+use macroquad::prelude::{get_frame_time, screen_height, screen_width, vec2, Vec2};
 pub use plugin::Plugin;
+pub use script::ScriptErrorPolicy;
+pub use time::FixedTimestep;
 
+pub mod actions;
+pub mod components;
+mod convert;
 pub mod ctx;
-pub mod systems;
+pub mod input;
 pub mod plugin;
+pub mod prefab;
+pub mod python;
+mod scene;
+pub mod script;
+pub mod storage;
+pub mod systems;
+pub mod time;
+pub mod validate;
 
 /** Handles teh current world state */
 #[derive(Debug, Clone, Copy)]
@@ -28,13 +65,52 @@ pub enum WorldState {
 
 /** Represents an ecs world */
 pub struct World<'a> {
-    // The scene as a json value
+    // The scene-level json values that are not entities or the camera
     pub scene: JsonValue,
+    // The entities and their components
+    pub registry: Registry,
+    // The scene camera
+    pub camera: Camera,
+    // The frame and fixed-timestep clock
+    pub time: Time,
+    // The source the input queries read from
+    pub input: Rc<dyn InputSource>,
+    // The input actions and axes scripts query by name
+    pub actions: Rc<RefCell<ActionMap>>,
+    // How far each entity moved during the last fixed update, for interpolating the rendering
+    fixed_motion: hashbrown::HashMap<Entity, Vec2>,
     // The state of the scene when it was loaded
     initial_scene: JsonValue,
+    // The file the scene was loaded from
+    pub scene_path: Option<PathBuf>,
+    // The scene to switch to at the end of the frame, with the script that asked for it
+    pending_scene: Option<(PathBuf, Option<String>)>,
+    // The values that are kept when switching scenes
+    pub globals: JsonValue,
+    // The prefab and sub-scene files referenced by scenes
+    pub prefabs: Prefabs,
+    // Notices changed scene, script and sprite files for hot reloading in debug mode
+    pub file_watcher: FileWatcher,
     // The scripts
     pub scripts: Option<Vec<&'a PyModule>>,
-    pub py_json: &'a PyModule,
+    // The modules and instance states of the scripts attached to entities
+    pub entity_scripts: EntityScripts<'a>,
+    // The directory script module names are relative to, it is on the python path
+    scripts_root: PathBuf,
+    // The modules loaded from files under the scripts root with their files, none until they are looked up again
+    script_modules: Option<Vec<(String, PathBuf)>>,
+    // What to do when a script raises an exception
+    pub script_error_policy: ScriptErrorPolicy,
+    // The scripts that were disabled after failing, they are retried when the scripts are reloaded
+    pub failed_scripts: Vec<ScriptFailure>,
+    // The entities whose animation clip ended this frame, with the name of the clip
+    pub finished_animations: Vec<(Entity, String)>,
+    // The pairs of entities whose colliders overlapped at the last check, the lower id first
+    pub contacts: Vec<(Entity, Entity)>,
+    // The colliders as they were when the running script stage started, for the queries of scripts
+    colliders: Rc<Colliders>,
+    // The scene the running script stage passes to python, the world's scene is moved into it for the stage
+    stage: Rc<RefCell<StageScene>>,
 
     // The python context
     py: Python<'a>,
@@ -55,73 +131,150 @@ impl<'a> World<'a> {
     pub fn new(py: Python<'a>, first_scene: Option<&str>, plugins: Vec<Box<dyn Plugin>>) -> IslandResult<Self> {
         // Create the world
         let mut result = Self {
-            scene: JsonValue::Null,
+            scene: JsonValue::new_object(),
+            registry: Registry::new(),
+            camera: Camera::default(),
+            time: Time::new(None)?,
+            input: Rc::new(MacroquadInput::new()),
+            actions: Rc::new(RefCell::new(ActionMap::new())),
+            fixed_motion: hashbrown::HashMap::new(),
             initial_scene: JsonValue::Null,
+            scene_path: None,
+            pending_scene: None,
+            globals: JsonValue::new_object(),
+            prefabs: Prefabs::new(),
+            file_watcher: FileWatcher::default(),
             scripts: Some(vec![]),
-            py: py,
+            entity_scripts: EntityScripts::new(),
+            scripts_root: PathBuf::from("."),
+            script_modules: None,
+            script_error_policy: ScriptErrorPolicy::Abort,
+            failed_scripts: vec![],
+            finished_animations: vec![],
+            contacts: vec![],
+            colliders: Rc::new(Colliders::default()),
+            stage: Rc::new(RefCell::new(StageScene::new())),
+            py,
             plugins: Some(plugins),
-            py_json: PyModule::import(py, "json").unwrap(),
             should_run: true,
             state: WorldState::Gameplay,
             debug_draw: false,
         };
+        python::register_module(py).map_err(|e| {
+            return IslandError::python(py, "Failed to register the ie module", e);
+        })?;
+        set_python_path(py, None, &result.scripts_root).map_err(|e| {
+            return IslandError::python(py, "Failed to set the python path", e);
+        })?;
+        error::set_scripts_root(&result.scripts_root);
         // Load the first scene if it is provided
         if let Some(first_scene) = first_scene {
             result.load_scene(first_scene)?;
         } else {
             // Create an empty scene
-            result.scene = json::object! {
-                camera: {
-                    position: {
-                        x: 0,
-                        y: 0,
-                    },
-                    zoom: {
-                        x: 1,
-                        y: 1,
-                    }
-                },
+            result.set_scene(json::object! {
+                camera: Camera::default().to_json(),
                 entities: []
-            }
+            })?;
         }
 
         return Ok(result);
     }
 
+    /** Get the directory script module names are relative to */
+    pub fn scripts_root(&self) -> &Path {
+        return &self.scripts_root;
+    }
+
+    /**
+    Set the directory script module names are relative to and put it on the python path,
+    so scripts can import the modules and packages in it.
+    Scripts that are already loaded keep their names.
+    */
+    pub fn set_scripts_root<T>(&mut self, root: T) -> IslandResult<()>
+    where
+        T: Into<PathBuf>,
+    {
+        let root = root.into();
+        set_python_path(self.py, Some(&self.scripts_root), &root).map_err(|e| {
+            return IslandError::python(self.py, format!("Failed to add {:?} to the python path", root), e);
+        })?;
+        error::set_scripts_root(&root);
+        self.scripts_root = root;
+        self.script_modules = None;
+        return Ok(());
+    }
+
     /** Load a scene into the world */
     pub fn load_scene<T>(&mut self, path: T) -> IslandResult<()>
     where
         T: Into<PathBuf> + Clone,
     {
+        let path = path.into();
+        let scene = self.read_scene(&path)?;
+        return self.enter_scene(path, scene);
+    }
+
+    /** Read a scene file, expanding its prefabs and checking it without loading it */
+    fn read_scene(&mut self, path: &Path) -> IslandResult<JsonValue> {
         // Open the file
-        let file = std::fs::read_to_string(path.clone().into()).map_err(|e| {
-            return IslandError::new(format!(
-                "Failed to load scene \"{:?}\": {}",
-                path.clone().into(),
-                e
-            ));
+        let file = std::fs::read_to_string(path).map_err(|e| {
+            return IslandError::with_source(ErrorKind::Io, format!("Failed to load scene \"{:?}\"", path), e);
         })?;
         // Parse the scene
-        self.scene = json::parse(&file).map_err(|e| {
-            return IslandError::new(format!(
-                "Failed to parse scene \"{:?}\": {}",
-                path.clone().into(),
-                e
-            ));
+        let scene = json::parse(&file).map_err(|e| {
+            return IslandError::with_source(ErrorKind::Json, format!("Failed to parse scene \"{:?}\"", path), e);
         })?;
-        // Set the initial scene state
-        self.initial_scene = self.scene.clone();
-        // Load the scripts
+        let scene = self.prefabs.resolve_scene(&scene)?;
+        validate_scene(&scene, &self.registry).into_result(ErrorKind::SceneValidation, format!("Scene \"{:?}\"", path))?;
+        return Ok(scene);
+    }
+
+    /** Replace the current scene with a scene read from a file, then start its scripts and plugins */
+    fn enter_scene(&mut self, path: PathBuf, scene: JsonValue) -> IslandResult<()> {
+        self.set_scene(scene)?;
+        self.scene_path = Some(path.clone());
+        // Set the initial scene state, including the assigned entity ids
+        self.initial_scene = self.scene_json();
+        // Load the scripts, starting the entity scripts of the new scene fresh
+        self.entity_scripts.clear();
         self.scripts = Some(self.load_scripts()?);
 
                 // Call the plugins
         let mut plugins = self.plugins.take().unwrap();
         for plugin in plugins.iter_mut() {
-            plugin.init(self)?;
+            plugin.init(self).map_err(|e| plugin_error("init", e))?;
         }
         self.plugins = Some(plugins);
 
-        info!("Loaded scene file: {:?}", path.clone().into());
+        info!("Loaded scene file: {:?}", path);
+
+        return Ok(());
+    }
+
+    /**
+    Save the current scene to a file.
+    Prefab entities are saved as their overrides and sub-scenes as their references, like they are written by hand.
+    The json is pretty-printed with sorted keys, so saved scenes diff cleanly.
+    */
+    pub fn save_scene<T>(&mut self, path: T) -> IslandResult<()>
+    where
+        T: Into<PathBuf> + Clone,
+    {
+        let scene = self.prefabs.collapse_scene(&self.scene_json())?;
+        let scene = sorted_json(&scene);
+        std::fs::write(path.clone().into(), format!("{}\n", scene.pretty(4))).map_err(|e| {
+            return IslandError::with_source(
+                ErrorKind::Io,
+                format!("Failed to save scene \"{:?}\"", path.clone().into()),
+                e,
+            );
+        })?;
+
+        // Don't hot reload the scene the world just wrote
+        self.file_watcher.refresh(&path.clone().into());
+
+        info!("Saved scene file: {:?}", path.into());
 
         return Ok(());
     }
@@ -132,16 +285,20 @@ impl<'a> World<'a> {
         T: Into<String>,
     {
         // Parse the scene
-        self.scene = json::parse(&scene.into()).map_err(|e| {
-            return IslandError::new(format!("Failed to parse scene: {}", e));
+        let scene = json::parse(&scene.into()).map_err(|e| {
+            return IslandError::with_source(ErrorKind::Json, "Failed to parse scene", e);
         })?;
-        // Load the scripts
+        let scene = self.prefabs.resolve_scene(&scene)?;
+        validate_scene(&scene, &self.registry).into_result(ErrorKind::SceneValidation, "Scene")?;
+        self.set_scene(scene)?;
+        // Load the scripts, starting the entity scripts of the new scene fresh
+        self.entity_scripts.clear();
         self.scripts = Some(self.load_scripts()?);
 
                 // Call the plugins
         let mut plugins = self.plugins.take().unwrap();
         for plugin in plugins.iter_mut() {
-            plugin.init(self)?;
+            plugin.init(self).map_err(|e| plugin_error("init", e))?;
         }
         self.plugins = Some(plugins);
         return Ok(());
@@ -152,30 +309,78 @@ impl<'a> World<'a> {
     where
         T: Into<JsonValue>,
     {
-        // Parse the scene
-        self.scene = scene.into();
-        // Load the scripts
+        // Set the scene
+        let scene = self.prefabs.resolve_scene(&scene.into())?;
+        validate_scene(&scene, &self.registry).into_result(ErrorKind::SceneValidation, "Scene")?;
+        self.set_scene(scene)?;
+        // Load the scripts, starting the entity scripts of the new scene fresh
+        self.entity_scripts.clear();
         self.scripts = Some(self.load_scripts()?);
 
                 // Call the plugins
         let mut plugins = self.plugins.take().unwrap();
         for plugin in plugins.iter_mut() {
-            plugin.init(self)?;
+            plugin.init(self).map_err(|e| plugin_error("init", e))?;
         }
         self.plugins = Some(plugins);
         return Ok(());
     }
 
+    /**
+    Load the entities and scripts of another scene file into the current scene.
+    The loaded entities get new ids, scripts that are already running are not loaded again.
+    */
+    pub fn load_scene_additive<T>(&mut self, path: T) -> IslandResult<Vec<Entity>>
+    where
+        T: Into<PathBuf> + Clone,
+    {
+        let path = path.into();
+        let scene = self.read_scene(&path)?;
+
+        // Spawn the entities, they are saved with the current scene
+        let mut entities = vec![];
+        for entity in scene["entities"].members() {
+            let mut entity = entity.clone();
+            entity.remove("id");
+            entity.remove("subscene");
+            entities.push(self.spawn_entity(entity)?);
+        }
+
+        // Load the scripts that are not running yet
+        if !scene["scripts"].is_empty() && !self.scene["scripts"].is_array() {
+            self.scene["scripts"] = JsonValue::new_array();
+        }
+        for script in scene["scripts"].members() {
+            if self.scene["scripts"].contains(script.clone()) {
+                continue;
+            }
+            let path = script
+                .as_str()
+                .ok_or(IslandError::with_kind(
+                    ErrorKind::SceneValidation,
+                    "Cannot parse script path as string",
+                ))?;
+            let module = self.load_script(path)?;
+            self.scene["scripts"].push(path).unwrap();
+            self.scripts.as_mut().unwrap().extend(module);
+        }
+
+        info!("Loaded scene file additively: {:?}", path);
+
+        return Ok(entities);
+    }
+
     /** Reload the scene */
     pub fn reload_scene(&mut self) -> IslandResult<()> {
-        self.scene = self.initial_scene.clone();
-        // Load the scripts
+        self.set_scene(self.initial_scene.clone())?;
+        // Load the scripts, starting the entity scripts of the new scene fresh
+        self.entity_scripts.clear();
         self.scripts = Some(self.load_scripts()?);
 
                 // Call the plugins
         let mut plugins = self.plugins.take().unwrap();
         for plugin in plugins.iter_mut() {
-            plugin.init(self)?;
+            plugin.init(self).map_err(|e| plugin_error("init", e))?;
         }
         self.plugins = Some(plugins);
         info!("Reloaded scene");
@@ -184,19 +389,100 @@ impl<'a> World<'a> {
 
     /** Reload the scripts */
     pub fn reload_scripts(&mut self) -> IslandResult<()> {
-        // Load the scripts
+        // Keep the state the scripts want to survive the reload
+        let scripts = self.scripts.clone().unwrap();
+        let states = self.save_script_states(&scripts);
+        // Load the scripts, entity scripts are loaded again when they next run
         self.scripts = Some(self.load_scripts()?);
+        self.entity_scripts.clear_modules();
+        self.load_script_states(states)?;
         info!("Reloaded scripts");
         return Ok(());
     }
 
-    /** Update the world */
+    /**
+    Reload a single script, keeping the scene and the other scripts as they are.
+    The script's init function runs again, the same as when all scripts are reloaded.
+    */
+    pub fn reload_script(&mut self, path: &str) -> IslandResult<()> {
+        self.failed_scripts.retain(|failure| failure.path != path);
+
+        // Entity scripts keep their instance states in the world, they only need to be compiled again
+        if self.entity_scripts.module(path).is_some() {
+            self.entity_scripts.remove_module(path);
+            if let Some(module) = self.compile_script(path)? {
+                self.entity_scripts.insert_module(path, module);
+                info!("Reloaded entity script \"{}\"", path);
+            }
+        }
+        if !self.scene["scripts"].members().any(|script| script.as_str() == Some(path)) {
+            return Ok(());
+        }
+
+        let old = self
+            .scripts
+            .clone()
+            .unwrap()
+            .into_iter()
+            .filter(|script| script.filename().ok() == Some(path))
+            .collect::<Vec<_>>();
+        let states = self.save_script_states(&old);
+        let module = self.load_script(path)?;
+        let loaded = module.is_some();
+
+        let scene_scripts = &self.scene["scripts"];
+        let scripts = self.scripts.as_mut().unwrap();
+        scripts.retain(|script| script.filename().ok() != Some(path));
+        scripts.extend(module);
+        // Keep running the scripts in the order of the scene
+        scripts.sort_by_key(|script| {
+            return scene_scripts
+                .members()
+                .position(|scene_script| scene_script.as_str() == script.filename().ok())
+                .unwrap_or(usize::MAX);
+        });
+        self.load_script_states(states)?;
+        if loaded {
+            info!("Reloaded script \"{}\"", path);
+        }
+        return Ok(());
+    }
+
+    /** Update the world by the time the last frame took */
     pub fn update(&mut self, debug_mode: bool) -> IslandResult<()> {
+        return self.step(get_frame_time(), debug_mode);
+    }
+
+    /** Update the world by the given frame time in seconds */
+    pub fn step(&mut self, frame_time: f32, debug_mode: bool) -> IslandResult<()> {
+        self.input.begin_frame();
+
+        // Run the fixed updates the frame time adds up to
+        let fixed_steps = self.time.advance(frame_time);
+        for _ in 0..fixed_steps {
+            self.fixed_update()?;
+        }
+
+        // Advance the animations, the scripts can react to the clips that ended
+        self.finished_animations = advance_animations(&mut self.registry, frame_time);
+        let animation_events = self
+            .finished_animations
+            .iter()
+            .map(|(entity, clip)| ScriptEvent {
+                args: vec![PyEntity::from(*entity).into_py(self.py), clip.to_object(self.py)],
+                entities: vec![(entity.id(), vec![clip.to_object(self.py)])],
+            })
+            .collect::<Vec<_>>();
+
+        // Tell the scripts about the ended clips and the colliders that started, kept or stopped overlapping
+        let colliders = Colliders::new(&self.registry);
+        let mut events = vec![("on_animation_end", animation_events)];
+        events.extend(self.contact_events(&colliders));
+        self.run_event_hooks(colliders, events)?;
+
         let scripts = self.scripts.take().unwrap();
         // Update the scripts
-        for script in scripts.iter() {
-            self.run_script_stage(script, "update")?;
-        }
+        self.run_script_stage(&scripts, "update")?;
         self.scripts = Some(scripts);
 
         // Call the debug systems
@@ -209,36 +495,258 @@ impl<'a> World<'a> {
         // Call the plugins
         let mut plugins = self.plugins.take().unwrap();
         for plugin in plugins.iter_mut() {
-            plugin.update(self)?;
+            plugin.update(self).map_err(|e| plugin_error("update", e))?;
         }
         self.plugins = Some(plugins);
 
+        // Switch the scene now that nothing is running on the old one
+        if let Some((path, script)) = self.pending_scene.take() {
+            // Check the scene before unloading the current one, so a scene that fails to load keeps it
+            let loaded = match self.read_scene(&path) {
+                Ok(scene) => {
+                    self.unload_scene()?;
+                    self.enter_scene(path, scene)
+                }
+                Err(e) => Err(e),
+            };
+            // The script that asked for the scene is to blame for it
+            match (loaded, script) {
+                (Err(e), Some(script)) => self.handle_script_error(&script, "change_scene", e)?,
+                (Err(e), None) => return Err(e),
+                (Ok(()), _) => {}
+            }
+        }
+
+        return Ok(());
+    }
+
+    /** Switch to another scene at the end of the frame, the step returns the error if it fails to load */
+    pub fn change_scene<T>(&mut self, path: T)
+    where
+        T: Into<PathBuf>,
+    {
+        self.pending_scene = Some((path.into(), None));
+    }
+
+    /** Let the scripts and plugins of the current scene clean up before it is replaced */
+    fn unload_scene(&mut self) -> IslandResult<()> {
+        self.run_optional_script_stage("exit")?;
+
+        // Call the plugins
+        let mut plugins = self.plugins.take().unwrap();
+        for plugin in plugins.iter_mut() {
+            plugin.on_scene_unload(self).map_err(|e| plugin_error("on_scene_unload", e))?;
+        }
+        self.plugins = Some(plugins);
+        return Ok(());
+    }
+
+    /** Run a single fixed update */
+    fn fixed_update(&mut self) -> IslandResult<()> {
+        self.time.set_in_fixed_update(true);
+        let previous_positions = self
+            .registry
+            .query::<Position>()
+            .map(|(entity, position)| (entity, position.0))
+            .collect::<hashbrown::HashMap<_, _>>();
+
+        // Update the scripts that have a fixed update
+        self.run_optional_script_stage("fixed_update")?;
+
+        // Call the plugins
+        let mut plugins = self.plugins.take().unwrap();
+        for plugin in plugins.iter_mut() {
+            plugin.fixed_update(self).map_err(|e| plugin_error("fixed_update", e))?;
+        }
+        self.plugins = Some(plugins);
+
+        // Remember the movement of this update for interpolating
+        self.fixed_motion = self
+            .registry
+            .query::<Position>()
+            .filter_map(|(entity, position)| {
+                let previous = previous_positions.get(&entity)?;
+                return Some((entity, position.0 - *previous));
+            })
+            .collect();
+        self.time.set_in_fixed_update(false);
         return Ok(());
     }
 
     /** Render the world */
     pub fn render(&mut self) -> IslandResult<()> {
         // Call rendering systems
-        handle_camera(&self.scene)?;
-        render_sprites(&self.scene)?;
+        handle_camera(&self.camera)?;
+        let y_sort = self.scene["y_sort"].as_bool().unwrap_or(false);
+        let view = view_rect(&self.camera, vec2(screen_width(), screen_height()));
+        render_sprites(&self.registry, &self.fixed_motion, self.time.alpha(), y_sort, view)?;
         // Call the plugins
         let mut plugins = self.plugins.take().unwrap();
         for plugin in plugins.iter_mut() {
-            plugin.render(self)?;
+            plugin.render(self).map_err(|e| plugin_error("render", e))?;
         }
         self.plugins = Some(plugins);
         // Call the debug render systems
         if self.debug_draw {
-            render_transforms(&self.scene)?;
+            render_transforms(&self.registry)?;
         }
         return Ok(());
     }
 
-    /** Run a python script */
-    fn run_script_stage(&mut self, script: &PyModule, stage: &str) -> IslandResult<()> {
+    /** Run a stage of the python scripts, skipping the scripts that don't define it */
+    fn run_optional_script_stage(&mut self, stage: &str) -> IslandResult<()> {
+        let scripts = self.scripts.take().unwrap();
+        let stage_scripts = scripts
+            .iter()
+            .copied()
+            .filter(|script| script.hasattr(stage).unwrap_or(false))
+            .collect::<Vec<_>>();
+        let result = self.run_script_stage(&stage_scripts, stage);
+        self.scripts = Some(scripts);
+        return result;
+    }
+
+    /**
+    Run a stage of the python scripts.
+    The scripts read and write the world through the scene passed to them,
+    what a script changes is written back to the world when it returns.
+    */
+    fn run_script_stage(&mut self, scripts: &[&PyModule], stage: &str) -> IslandResult<()> {
+        if scripts.is_empty() && !self.has_entity_scripts(stage) {
+            return Ok(());
+        }
+
+        return self.run_stage(Colliders::new(&self.registry), |world, stage_py| {
+            for script in scripts {
+                // Skip the scripts that failed before
+                let path = script.filename().unwrap_or_default();
+                if world.failed_scripts.iter().any(|failure| failure.path == path) {
+                    continue;
+                }
+
+                if let Err(e) = world.run_script(script, stage, stage_py) {
+                    world.handle_script_error(path, stage, e)?;
+                }
+            }
+
+            // Run the scripts attached to entities after the scene scripts
+            return world.run_entity_scripts(stage, stage_py);
+        });
+    }
+
+    /** Check if any entity has a script that runs in the stage */
+    fn has_entity_scripts(&self, stage: &str) -> bool {
+        if !ENTITY_STAGES.contains(&stage) {
+            return false;
+        }
+        return self.registry.query::<Script>().any(|(_, script)| {
+            // Scripts that are not loaded yet might have the stage
+            return match self.entity_scripts.module(&script.0) {
+                Some(module) => module.hasattr(stage).unwrap_or(false),
+                None => true,
+            };
+        });
+    }
+
+    /**
+    Call the stage function of each entity's script as `stage(ctx, entity)`, in scene order.
+    The first time an instance runs, its optional `init(ctx, entity)` is called before it.
+    */
+    fn run_entity_scripts(&mut self, stage: &str, stage_py: &mut StagePy) -> IslandResult<()> {
+        if !ENTITY_STAGES.contains(&stage) {
+            return Ok(());
+        }
+        // Forget the state of despawned instances
+        let scene = self.stage.borrow();
+        self.entity_scripts.retain_states(|entity| scene.registry.contains(entity));
+
+        // Take the scripted entities up front, the scripts can spawn and despawn entities
+        let entities = scene
+            .registry
+            .query::<Script>()
+            .map(|(entity, script)| (entity, script.0.clone()))
+            .collect::<Vec<_>>();
+        drop(scene);
+
+        for (entity, path) in entities {
+            if !self.stage.borrow().registry.contains(entity)
+                || self.failed_scripts.iter().any(|failure| failure.path == path)
+            {
+                continue;
+            }
+            let module = match self.entity_script_module(&path)? {
+                Some(module) => module,
+                None => continue,
+            };
+            if !module.hasattr(stage).unwrap_or(false) {
+                continue;
+            }
+            if let Err(e) = self.run_entity_script(module, &path, entity, (stage, vec![]), stage_py) {
+                self.handle_script_error(&path, stage, e)?;
+            }
+        }
+        return Ok(());
+    }
+
+    /** Get the module of an entity script, compiling it the first time it runs */
+    fn entity_script_module(&mut self, path: &str) -> IslandResult<Option<&'a PyModule>> {
+        if let Some(module) = self.entity_scripts.module(path) {
+            return Ok(Some(module));
+        }
+        let module = self.compile_script(path)?;
+        if let Some(module) = module {
+            self.entity_scripts.insert_module(path, module);
+        }
+        return Ok(module);
+    }
+
+    /**
+    Call a function of the script instance of an entity as `function(ctx, entity, *args)`.
+    The first time the instance runs, its optional `init(ctx, entity)` is called before it.
+    */
+    fn run_entity_script(
+        &mut self,
+        module: &PyModule,
+        path: &str,
+        entity: Entity,
+        (function, args): (&str, Vec<PyObject>),
+        stage_py: &mut StagePy,
+    ) -> IslandResult<()> {
+        // Create the context for the instance, an event can come before its first stage
+        let (state, created) = self.entity_scripts.state(self.py, entity);
+        let ctx = self.create_ctx(&stage_py.globals)?;
+        ctx.borrow_mut().entity = Some(entity.id());
+        ctx.borrow_mut().state = Some(state);
+        let entity_py = PySceneEntity::new(&self.stage, entity).into_py(self.py);
+
+        // Call the functions of the instance
+        let mut functions = vec![(function, args)];
+        if created && module.hasattr("init").unwrap_or(false) {
+            functions.insert(0, ("init", vec![]));
+        }
+        let result = functions.into_iter().try_for_each(|(function, args)| {
+            let mut function_args = vec![ctx.to_object(self.py), entity_py.clone_ref(self.py)];
+            function_args.extend(args);
+            module
+                .getattr(function)
+                .and_then(|func| func.call1(PyTuple::new(self.py, function_args)))
+                .map_err(|e| {
+                    return IslandError::python(
+                        self.py,
+                        format!("Script error: {}, {} raised an exception for entity {}", path, function, entity),
+                        e,
+                    );
+                })?;
+            return Ok(());
+        });
+        return self.finish_script_call(path, result, ctx, stage_py);
+    }
+
+    /** Run a single script of a stage, a script that returns another scene replaces the scene with it */
+    fn run_script(&mut self, script: &PyModule, stage: &str, stage_py: &mut StagePy) -> IslandResult<()> {
         // Get the update functions
         let func = script.getattr(stage).map_err(|_| {
-            return IslandError::new(format!(
+            return IslandError::with_kind(ErrorKind::Python, format!(
                 "Failed to find {} functions in \"{}\"",
                 stage,
                 script.name().unwrap()
@@ -246,48 +754,470 @@ impl<'a> World<'a> {
         })?;
 
         // Create the context
-        let ctx = PyCell::new(self.py, ctx::HighgroundCtx {
+        let ctx = self.create_ctx(&stage_py.globals)?;
+
+        // Call the update function and take the scene it returned
+        let result = func
+            .call1((ctx.borrow_mut(), stage_py.scene.as_ref(self.py)))
+            .map_err(|e| {
+                return IslandError::python(
+                    self.py,
+                    format!("Script error: {}, {} raised an exception", script.name().unwrap(), stage),
+                    e,
+                );
+            })
+            .and_then(|returned_scene| {
+                if returned_scene.extract::<PyRef<PyScene>>().is_ok() {
+                    return Ok(());
+                }
+                let returned_scene = returned_scene.downcast::<PyDict>().map_err(|_| {
+                    return IslandError::with_kind(ErrorKind::Python, format!(
+                        "Script error: {}, {} has to return the scene",
+                        script.name().unwrap(),
+                        stage
+                    ));
+                })?;
+                let returned_scene = returned_scene_to_json(&self.stage, returned_scene).map_err(|e| {
+                    return IslandError::python(
+                        self.py,
+                        "Failed to convert the scene returned by the script to json",
+                        e,
+                    );
+                })?;
+                return self.stage.borrow_mut().replace(returned_scene).context(format!(
+                    "Script error: {}, {} returned an invalid scene",
+                    script.name().unwrap(),
+                    stage
+                ));
+            });
+        return self.finish_script_call(script.filename().unwrap_or_default(), result, ctx, stage_py);
+    }
+
+    /**
+    Write what a script changed through the scene back to the world and apply what it asked for through the context.
+    If the script failed or set an invalid value, the error is returned and the scene is left as the script found it,
+    apart from the entities it spawned or despawned through the scene.
+    */
+    fn finish_script_call<T>(
+        &mut self,
+        path: &str,
+        result: IslandResult<T>,
+        ctx: &PyCell<ctx::HighgroundCtx>,
+        stage_py: &mut StagePy,
+    ) -> IslandResult<T> {
+        let value = match result {
+            Ok(value) => self.stage.borrow_mut().sync(self.py).map(|_| value)?,
+            Err(e) => {
+                self.stage.borrow_mut().discard();
+                return Err(e);
+            }
+        };
+
+        // Modify the scene by passing the context
+        self.handle_context(ctx.borrow());
+        stage_py.saved_scenes.append(&mut ctx.borrow_mut().saved_scenes);
+        if let Some(scene_path) = ctx.borrow_mut().changed_scene.take() {
+            self.pending_scene = Some((scene_path.into(), Some(path.into())));
+        }
+        return Ok(value);
+    }
+
+    /**
+    Find the colliders that started, kept or stopped overlapping since the last check,
+    as the events of the `on_collision_enter`, `on_collision_stay` and `on_collision_exit` hooks.
+    */
+    fn contact_events(&mut self, colliders: &Colliders) -> Vec<(&'static str, Vec<ScriptEvent>)> {
+        let contacts = colliders.contacts();
+        let previous = self.contacts.iter().copied().collect::<hashbrown::HashSet<_>>();
+        let current = contacts.iter().copied().collect::<hashbrown::HashSet<_>>();
+        let (stayed, entered): (Vec<_>, Vec<_>) = contacts.iter().partition(|contact| previous.contains(*contact));
+        let exited = self
+            .contacts
+            .iter()
+            .filter(|contact| !current.contains(*contact))
+            .collect::<Vec<_>>();
+        let py = self.py;
+        let to_events = |contacts: Vec<&(Entity, Entity)>| {
+            return contacts
+                .into_iter()
+                .map(|(a, b)| ScriptEvent {
+                    args: vec![PyEntity::from(*a).into_py(py), PyEntity::from(*b).into_py(py)],
+                    entities: vec![
+                        (a.id(), vec![PyEntity::from(*b).into_py(py)]),
+                        (b.id(), vec![PyEntity::from(*a).into_py(py)]),
+                    ],
+                })
+                .collect::<Vec<_>>();
+        };
+        let events = vec![
+            ("on_collision_enter", to_events(entered)),
+            ("on_collision_stay", to_events(stayed)),
+            ("on_collision_exit", to_events(exited)),
+        ];
+        self.contacts = contacts;
+        return events;
+    }
+
+    /**
+    Call the event hooks of the scene scripts as `hook(ctx, *args)` for each event, then the hooks of the scripts
+    of the entities in the events as `hook(ctx, entity, *args)`, all in one stage in the order of the hooks.
+    An entity instance that hasn't run yet gets its `init` called before the hook.
+    */
+    fn run_event_hooks(&mut self, colliders: Colliders, hooks: Vec<(&str, Vec<ScriptEvent>)>) -> IslandResult<()> {
+        let scripts = self.scripts.clone().unwrap();
+        let hooks = hooks
+            .into_iter()
+            .filter(|(_, events)| !events.is_empty())
+            .filter_map(|(hook, events)| {
+                let scripts = scripts
+                    .iter()
+                    .copied()
+                    .filter(|script| script.hasattr(hook).unwrap_or(false))
+                    .collect::<Vec<_>>();
+                let entity_hooks = self.registry.query::<Script>().any(|(_, script)| {
+                    // Scripts that are not loaded yet might have the hook
+                    return match self.entity_scripts.module(&script.0) {
+                        Some(module) => module.hasattr(hook).unwrap_or(false),
+                        None => true,
+                    };
+                });
+                if scripts.is_empty() && !entity_hooks {
+                    return None;
+                }
+                return Some((hook, events, scripts, entity_hooks));
+            })
+            .collect::<Vec<_>>();
+        if hooks.is_empty() {
+            return Ok(());
+        }
+
+        return self.run_stage(colliders, |world, stage_py| {
+            for (hook, events, scripts, entity_hooks) in hooks {
+                world.run_scene_event_hook(hook, &events, &scripts, stage_py)?;
+                if entity_hooks {
+                    world.run_entity_event_hook(hook, &events, stage_py)?;
+                }
+            }
+            return Ok(());
+        });
+    }
+
+    /** Call the event hook of the scene scripts that define it, in the stage of `run_event_hooks` */
+    fn run_scene_event_hook(
+        &mut self,
+        hook: &str,
+        events: &[ScriptEvent],
+        scripts: &[&PyModule],
+        stage_py: &mut StagePy,
+    ) -> IslandResult<()> {
+        'scripts: for script in scripts {
+            let path = script.filename().unwrap_or_default();
+            for event in events {
+                if self.failed_scripts.iter().any(|failure| failure.path == path) {
+                    continue 'scripts;
+                }
+                let ctx = self.create_ctx(&stage_py.globals)?;
+                let mut args = vec![ctx.to_object(self.py)];
+                args.extend(event.args.iter().map(|arg| arg.clone_ref(self.py)));
+                let result = script
+                    .getattr(hook)
+                    .and_then(|func| func.call1(PyTuple::new(self.py, args)))
+                    .map_err(|e| {
+                        return IslandError::python(
+                            self.py,
+                            format!("Script error: {}, {} raised an exception", script.name().unwrap(), hook),
+                            e,
+                        );
+                    });
+                if let Err(e) = self.finish_script_call(path, result, ctx, stage_py) {
+                    self.handle_script_error(path, hook, e)?;
+                    continue 'scripts;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    /** Call the event hook of the scripts of the entities in the events, in the stage of `run_event_hooks` */
+    fn run_entity_event_hook(&mut self, hook: &str, events: &[ScriptEvent], stage_py: &mut StagePy) -> IslandResult<()> {
+        for (id, args) in events.iter().flat_map(|event| event.entities.iter()) {
+            // The scripts before can despawn the entity or change its script
+            let entity = Entity::from_id(*id);
+            let path = match self.stage.borrow().registry.get::<Script>(entity) {
+                Some(script) => script.0.clone(),
+                None => continue,
+            };
+            if self.failed_scripts.iter().any(|failure| failure.path == path) {
+                continue;
+            }
+            let module = match self.entity_script_module(&path)? {
+                Some(module) => module,
+                None => continue,
+            };
+            if !module.hasattr(hook).unwrap_or(false) {
+                continue;
+            }
+            let args = args.iter().map(|arg| arg.clone_ref(self.py)).collect();
+            if let Err(e) = self.run_entity_script(module, &path, entity, (hook, args), stage_py) {
+                self.handle_script_error(&path, hook, e)?;
+            }
+        }
+        return Ok(());
+    }
+
+    /**
+    Run scripts on the scene of a stage.
+    The scene is moved out of the world for the scripts and back in once they have run, even when a script aborts
+    the stage. The colliders are the ones the queries of the scripts see, placed before the stage.
+    */
+    fn run_stage<T, F>(&mut self, colliders: Colliders, run: F) -> IslandResult<T>
+    where
+        F: FnOnce(&mut Self, &mut StagePy) -> IslandResult<T>,
+    {
+        let globals = json_to_py(self.py, &self.globals).map_err(|e| {
+            return IslandError::python(self.py, "Failed to convert the globals to a python object", e);
+        })?;
+        let scene = Py::new(self.py, PyScene {
+            stage: self.stage.clone(),
+        })
+        .map_err(|e| {
+            return IslandError::python(self.py, "Failed to create the scene for the scripts", e);
+        })?;
+        let mut stage_py = StagePy {
+            scene: scene.into_py(self.py),
+            globals,
+            saved_scenes: vec![],
+        };
+
+        self.colliders = Rc::new(colliders);
+        self.stage
+            .borrow_mut()
+            .begin(&mut self.registry, &mut self.camera, &mut self.scene, &mut self.prefabs);
+        let result = run(self, &mut stage_py);
+        self.stage
+            .borrow_mut()
+            .end(&mut self.registry, &mut self.camera, &mut self.scene, &mut self.prefabs);
+        let value = result?;
+
+        self.globals = py_to_json(stage_py.globals.as_ref(self.py)).map_err(|e| {
+            return IslandError::python(
+                self.py,
+                "Failed to convert the globals set by the scripts to json",
+                e,
+            );
+        })?;
+
+        // Save the scene once the changes of all scripts are in
+        for path in stage_py.saved_scenes {
+            if let Err(e) = self.save_scene(path) {
+                error!("{:#}", e);
+            }
+        }
+
+        return Ok(value);
+    }
+
+    /** Create the highground context for a script call */
+    fn create_ctx(&self, globals_py: &PyObject) -> IslandResult<&'a PyCell<ctx::HighgroundCtx>> {
+        return PyCell::new(self.py, ctx::HighgroundCtx {
             world_state: self.state,
+            delta_time: self.time.delta(),
+            frame_time: self.time.frame_time(),
+            interpolation_alpha: self.time.alpha(),
+            input: self.input.clone(),
+            actions: self.actions.clone(),
+            camera: self.stage.borrow().camera,
+            stage: self.stage.clone(),
+            globals: globals_py.clone_ref(self.py),
             spawned_entities: vec![],
+            despawned_entities: vec![],
+            saved_scenes: vec![],
+            changed_scene: None,
+            entity: None,
+            state: None,
+            finished_animations: self
+                .finished_animations
+                .iter()
+                .map(|(entity, clip)| (entity.id(), clip.clone()))
+                .collect(),
+            played_animations: vec![],
+            colliders: self.colliders.clone(),
         }).map_err(|e| {
-            return IslandError::new(format!("Failed to create highground ctx: {}", e));
-        })?;
+            return IslandError::python(self.py, "Failed to create highground ctx", e);
+        });
+    }
 
-        // Convert the scene to a python json
-        let scene_py = json_to_py(&self.scene, self.py_json)?;
-        // Call the update function and get the scene
-        let returned_scene = func.call1((ctx.borrow_mut(), scene_py)).map_err(|e| {
-            return IslandError::new(format!("Script error: {}, {}", script.name().unwrap(), e));
-        })?;
+    /**
+    Call an optional hook of a script with the context followed by the arguments.
+    Returns none if the script does not define the hook.
+    Changes the hook makes through the context are applied to the world like a stage's.
+    */
+    fn call_script_hook(
+        &mut self,
+        script: &PyModule,
+        hook: &str,
+        args: Vec<PyObject>,
+    ) -> IslandResult<Option<PyObject>> {
+        if !script.hasattr(hook).unwrap_or(false) {
+            return Ok(None);
+        }
 
-        // Set the new scene
-        self.scene = py_to_json(returned_scene, self.py_json)?;
+        return self.run_stage(Colliders::new(&self.registry), |world, stage_py| {
+            // Call the hook with the context first
+            let ctx = world.create_ctx(&stage_py.globals)?;
+            let mut hook_args = vec![ctx.to_object(world.py)];
+            hook_args.extend(args);
+            let result = script
+                .getattr(hook)
+                .and_then(|func| func.call1(PyTuple::new(world.py, hook_args)))
+                .map(|result| Some(result.into()))
+                .map_err(|e| {
+                    return IslandError::python(
+                        world.py,
+                        format!("Script error: {}, {} raised an exception", script.name().unwrap(), hook),
+                        e,
+                    );
+                });
+            return world.finish_script_call(script.filename().unwrap_or_default(), result, ctx, stage_py);
+        });
+    }
 
-        // Modify the world by passing the context
-        self.handle_context(ctx.borrow())?;
+    /**
+    Collect the state of the scripts that define `save_state(ctx) -> dict`, by script path.
+    A script that fails to save its state is reloaded without it.
+    */
+    fn save_script_states(&mut self, scripts: &[&PyModule]) -> Vec<(String, PyObject)> {
+        let mut states = vec![];
+        for script in scripts {
+            let path = script.filename().unwrap_or_default();
+            let state = match self.call_script_hook(script, "save_state", vec![]) {
+                Ok(Some(state)) => state,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("Failed to save the state of \"{}\", reloading it without: {:?}", path, e);
+                    continue;
+                }
+            };
+            if state.as_ref(self.py).downcast::<PyDict>().is_err() {
+                error!("Failed to save the state of \"{}\", save_state has to return a dict", path);
+                continue;
+            }
+            states.push((path.to_string(), state));
+        }
+        return states;
+    }
 
+    /** Hand the saved states back to the reloaded scripts that define `load_state(ctx, state)` */
+    fn load_script_states(&mut self, states: Vec<(String, PyObject)>) -> IslandResult<()> {
+        let scripts = self.scripts.clone().unwrap();
+        for (path, state) in states {
+            let script = scripts.iter().find(|script| script.filename().ok() == Some(path.as_str()));
+            if let Some(script) = script {
+                if let Err(e) = self.call_script_hook(script, "load_state", vec![state]) {
+                    self.handle_script_error(&path, "load_state", e)?;
+                }
+            }
+        }
         return Ok(());
     }
 
-    /** Handle the highground context after its been returned */
-    pub(crate) fn handle_context(&mut self, ctx: PyRef<ctx::HighgroundCtx>) -> IslandResult<()> {
-        // Spawn the entities
+    /** Log the error of a failed script, then return it or disable the script depending on the policy */
+    fn handle_script_error(&mut self, path: &str, stage: &str, e: IslandError) -> IslandResult<()> {
+        error!("{:?}", e);
+        if self.script_error_policy == ScriptErrorPolicy::Abort {
+            return Err(e);
+        }
+        warn!("Disabled script \"{}\" until the scripts are reloaded", path);
+        self.failed_scripts.push(ScriptFailure {
+            path: path.into(),
+            stage: stage.into(),
+            error: e,
+        });
+        return Ok(());
+    }
+
+    /**
+    Apply the highground context to the scene after a script returned.
+    Spawns are applied before despawns, both in the order the script queued them,
+    so the next script sees the result.
+    */
+    pub(crate) fn handle_context(&mut self, ctx: PyRef<ctx::HighgroundCtx>) {
+        let mut stage = self.stage.borrow_mut();
+
+        // Spawn the entities with the ids the script reserved
         for entity in &ctx.spawned_entities {
-            // Strange if-block to prevent the compiler from complaining because i am not using the error
-            if let Ok(_) = self.spawn_entity(entity.clone()).map_err(|e| {
-                error!("Failed to spawn entity: {}", e);
-            }) {}
+            if let Err(e) = stage.spawn(entity) {
+                error!("{}", e);
+            }
         }
 
-        return Ok(());
+        // Play the animations
+        for (id, clip, restart) in &ctx.played_animations {
+            let entity = Entity::from_id(*id);
+            if !stage.registry.contains(entity) {
+                warn!("Failed to play animation \"{}\" on entity {}, it does not exist", clip, id);
+                continue;
+            }
+            let current = stage.registry.get::<Animation>(entity);
+            if !restart && matches!(current, Some(current) if current.clip == *clip && current.playing) {
+                continue;
+            }
+            stage.registry.insert(entity, Animation::new(clip.as_str()));
+        }
+
+        // Despawn the entities
+        for id in &ctx.despawned_entities {
+            if !stage.despawn(Entity::from_id(*id)) {
+                warn!("Failed to despawn entity {}, it does not exist", id);
+            }
+        }
     }
 
-    /** Spawn an entity */
-    pub fn spawn_entity(&mut self, entity: JsonValue) -> IslandResult<()> {
-        // Add the entity
-        self.scene["entities"].push(entity).map_err(|e| {
-            return IslandError::new(format!("Failed to push an entity to the world: {}", e));
-        })?;
+    /** Spawn an entity from its json object, expanding its prefab */
+    pub fn spawn_entity(&mut self, entity: JsonValue) -> IslandResult<Entity> {
+        let entity = self.prefabs.resolve_entity(&entity)?;
+        return self.registry.load_entity(&entity).context("Failed to spawn an entity in the world");
+    }
+
+    /** Get a handle to an entity */
+    pub fn get_entity(&self, entity: Entity) -> Option<EntityRef<'_>> {
+        return self.registry.entity(entity);
+    }
+
+    /** Get a mutable handle to an entity */
+    pub fn get_entity_mut(&mut self, entity: Entity) -> Option<EntityMut<'_>> {
+        return self.registry.entity_mut(entity);
+    }
+
+    /** Despawn an entity, returns false if it did not exist */
+    pub fn despawn_entity(&mut self, entity: Entity) -> bool {
+        return self.registry.despawn(entity);
+    }
+
+    /** Find the first entity with the name */
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        return self.registry.find_by_name(name);
+    }
+
+    /** Find all entities with the tag */
+    pub fn find_by_tag(&self, tag: &str) -> Vec<Entity> {
+        return self.registry.find_by_tag(tag);
+    }
+
+    /** Get the scene as a json value, the same shape it is loaded from */
+    pub fn scene_json(&self) -> JsonValue {
+        let mut scene = self.scene.clone();
+        scene[Camera::NAME] = self.camera.to_json();
+        scene["entities"] = self.registry.save_entities();
+        return scene;
+    }
+
+    /** Replace the current scene with a json scene, the current scene stays if the new one fails to load */
+    fn set_scene(&mut self, scene: JsonValue) -> IslandResult<()> {
+        let (scene, camera, registry) = parse_scene(scene, &self.registry)?;
+        self.scene = scene;
+        self.camera = camera;
+        self.registry = registry;
         return Ok(());
     }
 
@@ -295,6 +1225,8 @@ impl<'a> World<'a> {
     fn load_scripts(&mut self) -> IslandResult<Vec<&'a PyModule>> {
         // The scripts
         let mut scripts = vec![];
+        // Give the failed scripts another chance
+        self.failed_scripts.clear();
 
         let scene = self.scene.clone();
 
@@ -304,85 +1236,647 @@ impl<'a> World<'a> {
                 // Get the script path
                 let path = script
                     .as_str()
-                    .ok_or(IslandError::new("Cannot parse script path as string"))?;
-                // Get the name
-                let name = path.split("/").collect::<Vec<&str>>();
-                let name = name.last().unwrap().replace(".py", "");
-                // Read the script
-                let code = std::fs::read_to_string(path).map_err(|e| {
-                    return IslandError::new(format!(
-                        "Failed to read script \"{:?}\": {}",
-                        path.clone(),
-                        e
-                    ));
-                })?;
-                // Create the script module
-                let module = PyModule::from_code(self.py, &code, &format!("{}.py", name), &name)
-                    .map_err(|e| {
-                        return IslandError::new(format!(
-                            "Failed to read script \"{:?}\": {}",
-                            path.clone(),
-                            e
-                        ));
-                    })?;
-
-                // Call the init function
-                self.run_script_stage(module, "init")?;
+                    .ok_or(IslandError::with_kind(
+                    ErrorKind::SceneValidation,
+                    "Cannot parse script path as string",
+                ))?;
                 // Push the script
-                scripts.push(module);
+                scripts.extend(self.load_script(path)?);
             }
         }
 
         return Ok(scripts);
     }
+
+    /**
+    Load a script and call its init function.
+    Returns none if the script does not compile and the policy disabled it.
+    */
+    fn load_script(&mut self, path: &str) -> IslandResult<Option<&'a PyModule>> {
+        let module = match self.compile_script(path)? {
+            Some(module) => module,
+            None => return Ok(None),
+        };
+
+        // Call the init function
+        self.run_script_stage(&[module], "init")?;
+        return Ok(Some(module));
+    }
+
+    /**
+    Read a script and run its code as a module.
+    Returns none if the script does not compile and the policy disabled it.
+    */
+    fn compile_script(&mut self, path: &str) -> IslandResult<Option<&'a PyModule>> {
+        // Running a script can import modules, look them up again
+        self.script_modules = None;
+        // Get the module name
+        let name = module_name(&self.scripts_root, Path::new(path));
+        // Read the script
+        let code = std::fs::read_to_string(path).map_err(|e| {
+            return IslandError::with_source(ErrorKind::Io, format!("Failed to read script \"{:?}\"", path), e);
+        })?;
+        // Create the script module
+        let module = match exec_module(self.py, &name, path, &code) {
+            Ok(module) => module,
+            Err(e) => {
+                let e = IslandError::python(self.py, format!("Failed to load script \"{:?}\"", path), e);
+                self.handle_script_error(path, "load", e)?;
+                return Ok(None);
+            }
+        };
+        return Ok(Some(module));
+    }
 }
 
-/** Convert a json object to a python-compatible json object  */
-fn json_to_py<'a>(json: &JsonValue, py_json: &'a PyModule) -> IslandResult<&'a PyAny> {
-    let convert_fn = py_json.getattr("loads").map_err(|e| {
-        return IslandError::new(format!(
-            "Failed to fetch `loads` function in json module: {}",
-            e
-        ));
-    })?;
-    // Convert the json to a string
-    let json_str = json::stringify(json.clone());
-    let result = convert_fn.call1((json_str,)).map_err(|e| {
-        return IslandError::new(format!(
-            "Failed to convert the json object to a python-json object: {}",
-            e
-        ));
-    })?;
-
-    return Ok(result);
+/** The python objects the scripts of a stage share */
+struct StagePy {
+    // The scene passed to the scripts
+    scene: PyObject,
+    // The globals passed to the scripts through the context
+    globals: PyObject,
+    // The files the scripts asked to save the scene to
+    saved_scenes: Vec<String>,
 }
 
-/** Convert a python-json object to a rust-json object */
-fn py_to_json<'a>(obj: &'a PyAny, py_json: &'a PyModule) -> IslandResult<JsonValue> {
-    let convert_fn = py_json.getattr("dumps").map_err(|e| {
-        return IslandError::new(format!(
-            "Failed to fetch `dumps` function in json module: {}",
-            e
-        ));
-    })?;
-    // Call the dump functio
-    let result = convert_fn.call1((obj,)).map_err(|e| {
-        return IslandError::new(format!(
-            "Failed to convert python-json object into json-object: {}",
-            e
-        ));
-    })?;
-    // Cast to string
-    let result_str: String = result.extract().map_err(|e| {
-        return IslandError::new(format!(
-            "Failed to convert python-json result to string: {}",
-            e
-        ));
-    })?;
-    // Parse the resulted strung
-    let result_parsed = json::parse(&result_str).map_err(|e| {
-        return IslandError::new(format!("Failed to parse python-json string: {}", e));
-    })?;
-
-    return Ok(result_parsed);
+/**
+Load a json scene into its scene-level values, its camera and a new registry with the component types of `like`.
+The whole scene is loaded before anything is returned, so a scene that fails to load leaves nothing behind.
+*/
+fn parse_scene(mut scene: JsonValue, like: &Registry) -> IslandResult<(JsonValue, Camera, Registry)> {
+    if !scene.is_object() {
+        return Err(IslandError::with_kind(ErrorKind::SceneValidation, format!(
+            "Scene has to be a json object: {}",
+            scene
+        )));
+    }
+    // Load the camera
+    let camera = if scene.has_key(Camera::NAME) {
+        Camera::from_json(&scene[Camera::NAME]).map_err(|e| {
+            return IslandError::with_source(ErrorKind::SceneValidation, "Failed to load the scene camera", e);
+        })?
+    } else {
+        Camera::default()
+    };
+    // Load the entities
+    let mut registry = like.empty_like();
+    registry.load_entities(&scene["entities"])?;
+    // Keep the remaining scene-level values
+    scene.remove(Camera::NAME);
+    scene.remove("entities");
+    return Ok((scene, camera, registry));
+}
+
+/** Turn an error returned by a plugin into a plugin error, keeping it as the source */
+fn plugin_error(stage: &str, e: IslandError) -> IslandError {
+    return IslandError::with_source(ErrorKind::Plugin, format!("Plugin failed in {}", stage), e);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::components::Tag;
+    use crate::ecs::input::ScriptedInput;
+    use macroquad::prelude::MouseButton;
+
+    /** Create a world without a window that runs a script written to a temporary file */
+    fn world_with_script<'a>(py: Python<'a>, name: &str, script: &str, entities: JsonValue) -> World<'a> {
+        let path = std::env::temp_dir().join(format!("ie_test_{}.py", name));
+        std::fs::write(&path, script).unwrap();
+        let mut world = World::new(py, None, vec![]).unwrap();
+        world.input = Rc::new(ScriptedInput::new());
+        world
+            .load_scene_json(json::object! { scripts: [path.to_str().unwrap()], entities: entities })
+            .unwrap();
+        return world;
+    }
+
+    #[test]
+    fn scenes_that_fail_to_load_leave_the_current_scene() {
+        Python::with_gil(|py| {
+            let mut world = World::new(py, None, vec![]).unwrap();
+            world
+                .set_scene(json::object! { y_sort: true, entities: [{ name: "kept" }] })
+                .unwrap();
+            let invalid = json::object! {
+                camera: { position: { x: 5, y: 5 }, zoom: { x: 1, y: 1 } },
+                entities: [{ name: "loaded" }, { position: "nowhere" }],
+            };
+            assert!(world.set_scene(invalid).is_err());
+            assert!(world.find_by_name("kept").is_some());
+            assert!(world.find_by_name("loaded").is_none());
+            assert_eq!(world.camera.position, Vec2::ZERO);
+            assert_eq!(world.scene["y_sort"], true);
+        });
+    }
+
+    #[test]
+    fn saved_scenes_keep_their_prefab_and_subscene_references() {
+        Python::with_gil(|py| {
+            let directory = std::env::temp_dir().join("ie_test_save_references");
+            std::fs::create_dir_all(&directory).unwrap();
+            let (crate_path, room_path, level_path) =
+                (directory.join("crate.json"), directory.join("room.json"), directory.join("level.json"));
+            std::fs::write(&crate_path, json::object! { name: "crate", position: { x: 0, y: 0 } }.dump()).unwrap();
+            std::fs::write(&room_path, json::object! { entities: [{ name: "door" }] }.dump()).unwrap();
+            std::fs::write(&level_path, json::object! {
+                subscenes: [room_path.to_str().unwrap()],
+                entities: [{ prefab: crate_path.to_str().unwrap(), scale: { x: 2, y: 2 } }],
+            }.dump()).unwrap();
+
+            let mut world = World::new(py, None, vec![]).unwrap();
+            world.input = Rc::new(ScriptedInput::new());
+            world.load_scene(&level_path).unwrap();
+            assert!(world.find_by_name("door").is_some());
+            let entity = world.find_by_name("crate").unwrap();
+            world.registry.get_mut::<Position>(entity).unwrap().0.x = 4.0;
+            world.save_scene(&level_path).unwrap();
+
+            let saved = json::parse(&std::fs::read_to_string(&level_path).unwrap()).unwrap();
+            assert_eq!(saved["subscenes"], json::array![room_path.to_str().unwrap()]);
+            assert_eq!(saved["entities"], json::array![{
+                id: entity.id(),
+                position: { x: 4.0 },
+                prefab: crate_path.to_str().unwrap(),
+                scale: { x: 2.0, y: 2.0 },
+            }]);
+
+            // Edits to the referenced files reach the saved scene
+            std::fs::write(&crate_path, json::object! { name: "box", position: { x: 0, y: 3 } }.dump()).unwrap();
+            world.prefabs.clear();
+            world.load_scene(&level_path).unwrap();
+            let entity = world.find_by_name("box").unwrap();
+            assert_eq!(world.registry.get::<Position>(entity).unwrap().0, vec2(4.0, 3.0));
+            assert!(world.find_by_name("door").is_some());
+        });
+    }
+
+    #[test]
+    fn scripts_read_the_mouse_through_the_scene_camera() {
+        Python::with_gil(|py| {
+            let script = "
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    ctx.globals()['screen'] = ctx.mouse_position()
+    ctx.globals()['world'] = ctx.mouse_world_position()
+    ctx.globals()['down'] = ctx.is_mouse_button_down('Left')
+    return scene
+";
+            let path = std::env::temp_dir().join("ie_test_mouse.py");
+            std::fs::write(&path, script).unwrap();
+            let input = Rc::new(ScriptedInput::new());
+            let mut world = World::new(py, None, vec![]).unwrap();
+            world.input = input.clone();
+            world
+                .load_scene_json(json::object! {
+                    scripts: [path.to_str().unwrap()],
+                    camera: { position: { x: 100, y: 50 }, zoom: { x: 2, y: 2 } },
+                    entities: [],
+                })
+                .unwrap();
+            input.move_mouse(500.0, 400.0);
+            input.press_mouse_button(MouseButton::Left);
+            world.step(1.0 / 60.0, false).unwrap();
+
+            // The screen is 800 by 600, so the mouse is 100 by 100 pixels from the center
+            assert_eq!(world.globals["screen"], json::object! { x: 500.0, y: 400.0 });
+            assert_eq!(world.globals["world"], json::object! { x: 150.0, y: 100.0 });
+            assert_eq!(world.globals["down"], true);
+        });
+    }
+
+    #[test]
+    fn unknown_input_names_raise_value_errors() {
+        Python::with_gil(|py| {
+            let script = "
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    errors = []
+    queries = [
+        lambda: ctx.is_key_down('Spacebar'),
+        lambda: ctx.is_mouse_button_pressed('Back'),
+        lambda: ctx.is_gamepad_button_down('Triangle'),
+        lambda: ctx.gamepad_axis('LeftZ'),
+    ]
+    for query in queries:
+        try:
+            query()
+        except ValueError as e:
+            errors.append(str(e))
+    ctx.globals()['errors'] = errors
+    ctx.globals()['space'] = ctx.is_key_down('Space')
+    return scene
+";
+            let mut world = world_with_script(py, "unknown_names", script, json::array![]);
+            world.step(1.0 / 60.0, false).unwrap();
+
+            let errors = &world.globals["errors"];
+            assert_eq!(errors.len(), 4, "{}", errors);
+            for (error, name) in errors.members().zip(["Spacebar", "Back", "Triangle", "LeftZ"].iter()) {
+                assert!(error.as_str().unwrap().contains(name), "{}", error);
+            }
+            assert_eq!(world.globals["space"], false);
+        });
+    }
+
+    #[test]
+    fn hot_reload_looks_up_the_script_modules_after_scripts_run() {
+        Python::with_gil(|py| {
+            let root = std::env::temp_dir().join("ie_test_hot_reload_modules");
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root).unwrap();
+            let script = root.join("ie_hot_script.py");
+            std::fs::write(root.join("ie_hot_helper.py"), "SPEED = 1\n").unwrap();
+            std::fs::write(root.join("ie_hot_later.py"), "SPEED = 2\n").unwrap();
+            std::fs::write(&script, "import ie_hot_helper\n\ndef init(ctx, scene):\n    return scene\n\ndef update(ctx, scene):\n    return scene\n").unwrap();
+
+            let mut world = World::new(py, None, vec![]).unwrap();
+            world.input = Rc::new(ScriptedInput::new());
+            world.set_scripts_root(&root).unwrap();
+            world
+                .load_scene_json(json::object! { scripts: [script.to_str().unwrap()], entities: [] })
+                .unwrap();
+            assert!(world.script_modules.is_none());
+            let names = |world: &World| {
+                let modules = world.script_modules.as_ref().unwrap();
+                let mut names = modules.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+                names.sort();
+                return names;
+            };
+
+            // The first poll looks them up, later polls use the same list
+            world.step(1.0, true).unwrap();
+            assert_eq!(names(&world), vec!["ie_hot_helper", "ie_hot_script"]);
+            py.import("ie_hot_later").unwrap();
+            world.step(1.0, true).unwrap();
+            assert_eq!(names(&world), vec!["ie_hot_helper", "ie_hot_script"]);
+
+            // Running a script again looks them up at the next poll
+            world.reload_script(script.to_str().unwrap()).unwrap();
+            assert!(world.script_modules.is_none());
+            world.step(1.0, true).unwrap();
+            assert_eq!(names(&world), vec!["ie_hot_helper", "ie_hot_later", "ie_hot_script"]);
+        });
+    }
+
+    #[test]
+    fn entities_appended_to_the_scene_expand_their_prefab() {
+        Python::with_gil(|py| {
+            let prefab = std::env::temp_dir().join("ie_test_append_prefab.json");
+            std::fs::write(&prefab, json::object! { tag: "enemy", position: { x: 0, y: 3 } }.dump()).unwrap();
+            let script = format!("
+import json
+
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    if not ctx.find_by_tag('enemy'):
+        appended = scene['entities'].append({{'prefab': {path:?}, 'name': 'appended', 'position': {{'x': 5}}}})
+        appended['name'] = 'renamed'
+        ctx.spawn_entity(json.dumps({{'prefab': {path:?}, 'name': 'spawned', 'position': {{'x': 5}}}}))
+    return scene
+", path = prefab.to_str().unwrap());
+            let mut world = world_with_script(py, "append_prefab", &script, json::array![]);
+            world.step(1.0 / 60.0, false).unwrap();
+
+            let appended = world.find_by_name("renamed").unwrap();
+            let spawned = world.find_by_name("spawned").unwrap();
+            for entity in [appended, spawned].iter() {
+                assert_eq!(world.registry.get::<Position>(*entity).unwrap().0, vec2(5.0, 3.0));
+                assert_eq!(world.registry.get::<Tag>(*entity).unwrap().0, "enemy");
+            }
+        });
+    }
+
+    #[test]
+    fn scripts_change_the_registry_through_the_scene() {
+        Python::with_gil(|py| {
+            let script = "
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    for entity in scene['entities']:
+        entity['position']['x'] += 1
+    scene['score'] = scene.get('score', 0) + 1
+    if ctx.find_by_name('spawned') is None:
+        spawned = scene['entities'].append({'name': 'spawned', 'position': {'x': 10, 'y': 0}})
+        spawned['position']['y'] = 5
+    return scene
+";
+            let mut world = world_with_script(py, "write_through", script, json::array![{ position: { x: 0, y: 0 } }]);
+            world.step(1.0 / 60.0, false).unwrap();
+            world.step(1.0 / 60.0, false).unwrap();
+
+            let spawned = world.find_by_name("spawned").unwrap();
+            assert_eq!(world.registry.get::<Position>(spawned).unwrap().0, vec2(11.0, 5.0));
+            assert_eq!(world.registry.get::<Position>(world.registry.entities()[0]).unwrap().0, vec2(2.0, 0.0));
+            assert_eq!(world.registry.entities().len(), 2);
+            assert_eq!(world.scene["score"], 2);
+        });
+    }
+
+    #[test]
+    fn invalid_component_writes_disable_the_script() {
+        Python::with_gil(|py| {
+            let script = "
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    scene['entities'][0]['position']['x'] += 1
+    scene['entities'][0]['scale'] = 'huge'
+    return scene
+";
+            let mut world = world_with_script(py, "invalid_write", script, json::array![{ position: { x: 0, y: 0 } }]);
+            world.script_error_policy = ScriptErrorPolicy::Disable;
+            world.step(1.0 / 60.0, false).unwrap();
+
+            // None of the writes of the failed script are applied
+            let entity = world.registry.entities()[0];
+            assert_eq!(world.registry.get::<Position>(entity).unwrap().0, Vec2::ZERO);
+            assert_eq!(world.failed_scripts.len(), 1);
+            assert_eq!(world.failed_scripts[0].stage, "update");
+        });
+    }
+
+    #[test]
+    fn invalid_scenes_returned_by_scripts_disable_the_script() {
+        Python::with_gil(|py| {
+            let script = "
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    return {'entities': [{'position': 'nowhere'}]}
+";
+            let mut world = world_with_script(py, "invalid_scene", script, json::array![{ name: "kept" }]);
+            world.script_error_policy = ScriptErrorPolicy::Disable;
+            world.step(1.0 / 60.0, false).unwrap();
+            world.step(1.0 / 60.0, false).unwrap();
+
+            assert!(world.find_by_name("kept").is_some());
+            assert_eq!(world.failed_scripts.len(), 1);
+            assert_eq!(world.failed_scripts[0].stage, "update");
+        });
+    }
+
+    #[test]
+    fn scenes_that_fail_to_load_on_a_scene_change_keep_the_current_scene() {
+        Python::with_gil(|py| {
+            let script = "
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    ctx.change_scene('does/not/exist.json')
+    return scene
+
+def exit(ctx, scene):
+    scene['exited'] = True
+    return scene
+";
+            let mut world = world_with_script(py, "missing_scene", script, json::array![{ name: "kept" }]);
+            world.script_error_policy = ScriptErrorPolicy::Disable;
+            world.step(1.0 / 60.0, false).unwrap();
+
+            // The current scene is not unloaded for a scene that can't be read
+            assert!(world.find_by_name("kept").is_some());
+            assert!(!world.scene.has_key("exited"));
+            assert_eq!(world.failed_scripts.len(), 1);
+            assert_eq!(world.failed_scripts[0].stage, "change_scene");
+
+            // Without a script to blame, the step returns the error
+            world.change_scene("does/not/exist.json");
+            assert!(world.step(1.0 / 60.0, false).is_err());
+            assert!(world.find_by_name("kept").is_some());
+        });
+    }
+
+    #[test]
+    fn scene_changes_exit_the_old_scene_and_keep_the_globals() {
+        Python::with_gil(|py| {
+            let directory = std::env::temp_dir().join("ie_test_change_scene");
+            std::fs::create_dir_all(&directory).unwrap();
+            let (first, second) = (directory.join("first.py"), directory.join("second.py"));
+            let second_scene = directory.join("second.json");
+            let first_script = format!("
+def init(ctx, scene):
+    ctx.globals()['log'] = ['first init']
+    return scene
+
+def update(ctx, scene):
+    log = ctx.globals()['log']
+    log.append('first update')
+    if len(log) == 2:
+        ctx.globals()['coins'] = 3
+        ctx.change_scene({:?})
+        log.append('first asked')
+    return scene
+
+def exit(ctx, scene):
+    ctx.globals()['log'].append('first exit')
+    return scene
+", second_scene.to_str().unwrap());
+            let second_script = "
+def init(ctx, scene):
+    ctx.globals()['log'].append('second init')
+    ctx.globals()['coins_seen'] = ctx.globals()['coins']
+    return scene
+
+def update(ctx, scene):
+    ctx.globals()['log'].append('second update')
+    return scene
+";
+            std::fs::write(&first, first_script).unwrap();
+            std::fs::write(&second, second_script).unwrap();
+            std::fs::write(&second_scene, json::object! {
+                scripts: [second.to_str().unwrap()],
+                entities: [{ name: "second" }],
+            }.dump()).unwrap();
+
+            let mut world = World::new(py, None, vec![]).unwrap();
+            world.input = Rc::new(ScriptedInput::new());
+            world
+                .load_scene_json(json::object! { scripts: [first.to_str().unwrap()], entities: [{ name: "first" }] })
+                .unwrap();
+
+            // The scene changes at the end of the frame, after the old scene's exit stage
+            world.step(1.0 / 60.0, false).unwrap();
+            assert_eq!(
+                world.globals["log"],
+                json::array!["first init", "first update", "first asked", "first exit", "second init"]
+            );
+            assert_eq!(world.scene_path, Some(second_scene));
+            assert!(world.find_by_name("first").is_none());
+            assert!(world.find_by_name("second").is_some());
+
+            // Only the new scene's scripts run from then on, with the globals of the old one
+            world.step(1.0 / 60.0, false).unwrap();
+            assert_eq!(world.globals["log"][5], "second update");
+            assert_eq!(world.globals["log"].len(), 6);
+            assert_eq!(world.globals["coins"], 3);
+            assert_eq!(world.globals["coins_seen"], 3);
+        });
+    }
+
+    #[test]
+    fn reloaded_scripts_get_back_the_state_they_saved() {
+        Python::with_gil(|py| {
+            let script = "
+count = 0
+
+def init(ctx, scene):
+    ctx.globals()['inits'] = ctx.globals().get('inits', 0) + 1
+    return scene
+
+def update(ctx, scene):
+    global count
+    count += 1
+    ctx.globals()['count'] = count
+    return scene
+
+def save_state(ctx):
+    return {'count': count}
+
+def load_state(ctx, state):
+    global count
+    count = state['count']
+    ctx.globals()['loaded'] = count
+";
+            let mut world = world_with_script(py, "reload_state", script, json::array![]);
+            let path = world.scene["scripts"][0].as_str().unwrap().to_string();
+            for _ in 0..3 {
+                world.step(1.0 / 60.0, false).unwrap();
+            }
+            assert_eq!(world.globals["count"], 3);
+
+            // The module starts over and its init runs, then it gets its state back
+            world.reload_scripts().unwrap();
+            assert_eq!(world.globals["inits"], 2);
+            assert_eq!(world.globals["loaded"], 3);
+            world.step(1.0 / 60.0, false).unwrap();
+            assert_eq!(world.globals["count"], 4);
+
+            // Reloading just the script keeps its state too
+            world.reload_script(&path).unwrap();
+            assert_eq!(world.globals["inits"], 3);
+            assert_eq!(world.globals["loaded"], 4);
+            world.step(1.0 / 60.0, false).unwrap();
+            assert_eq!(world.globals["count"], 5);
+        });
+    }
+
+    #[test]
+    fn entity_scripts_keep_a_state_per_entity() {
+        Python::with_gil(|py| {
+            let entity_script = "
+def init(ctx, entity):
+    ctx.state()['count'] = 0
+    ctx.globals().setdefault('inits', []).append(entity['name'])
+
+def update(ctx, entity):
+    state = ctx.state()
+    state['count'] += 1 if entity['name'] == 'slow' else 10
+    ctx.globals().setdefault('counts', {})[entity['name']] = state['count']
+";
+            let path = std::env::temp_dir().join("ie_test_entity_state.py");
+            std::fs::write(&path, entity_script).unwrap();
+            let path = path.to_str().unwrap();
+            let scene_script = "
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    return scene
+";
+            let entities = json::array![{ name: "slow", script: path }, { name: "fast", script: path }];
+            let mut world = world_with_script(py, "entity_state_scene", scene_script, entities);
+            world.step(1.0 / 60.0, false).unwrap();
+            world.step(1.0 / 60.0, false).unwrap();
+            assert_eq!(world.globals["inits"], json::array!["slow", "fast"]);
+            assert_eq!(world.globals["counts"], json::object! { slow: 2, fast: 20 });
+
+            // The states are kept when the module is reloaded, the instances don't run init again
+            world.reload_scripts().unwrap();
+            world.step(1.0 / 60.0, false).unwrap();
+            assert_eq!(world.globals["inits"], json::array!["slow", "fast"]);
+            assert_eq!(world.globals["counts"], json::object! { slow: 3, fast: 30 });
+        });
+    }
+
+    #[test]
+    fn raycasts_reject_values_that_are_not_finite() {
+        Python::with_gil(|py| {
+            let script = "
+import ie
+
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    errors = 0
+    rays = [
+        (ie.Vec2(float('nan'), 0), ie.Vec2(1, 0), None),
+        (ie.Vec2(0, float('inf')), ie.Vec2(1, 0), None),
+        (ie.Vec2(0, 0), ie.Vec2(float('nan'), 1), None),
+        (ie.Vec2(0, 0), ie.Vec2(1, 0), float('nan')),
+        (ie.Vec2(0, 0), ie.Vec2(1, 0), float('inf')),
+    ]
+    for origin, direction, max_distance in rays:
+        try:
+            ctx.raycast(origin, direction, max_distance)
+        except ValueError:
+            errors += 1
+    ctx.globals()['errors'] = errors
+    ctx.globals()['hit'] = ctx.raycast(ie.Vec2(-10, 1), ie.Vec2(1, 0)) is not None
+    return scene
+";
+            let entities = json::array![{ position: { x: 0, y: 0 }, collider: { shape: "rect", size: { x: 2, y: 2 } } }];
+            let mut world = world_with_script(py, "raycast_values", script, entities);
+            world.step(1.0 / 60.0, false).unwrap();
+            assert_eq!(world.globals["errors"], 5);
+            assert_eq!(world.globals["hit"], true);
+        });
+    }
+
+    #[test]
+    fn scripts_get_the_collision_events_of_each_frame() {
+        Python::with_gil(|py| {
+            let script = "
+def init(ctx, scene):
+    return scene
+
+def on_collision_enter(ctx, a, b):
+    ctx.globals().setdefault('events', []).append(['enter', int(a), int(b)])
+
+def on_collision_stay(ctx, a, b):
+    ctx.globals()['events'].append(['stay', int(a), int(b)])
+
+def on_collision_exit(ctx, a, b):
+    ctx.globals()['events'].append(['exit', int(a), int(b)])
+
+def update(ctx, scene):
+    if len(ctx.globals()['events']) == 2:
+        scene['entities'][1]['position']['x'] = 10
+    return scene
+";
+            let collider = json::object! { shape: "rect", size: { x: 2, y: 2 } };
+            let entities = json::array![
+                { position: { x: 0, y: 0 }, collider: collider.clone() },
+                { position: { x: 1, y: 0 }, collider: collider.clone() },
+            ];
+            let mut world = world_with_script(py, "collision_events", script, entities);
+            for _ in 0..3 {
+                world.step(1.0 / 60.0, false).unwrap();
+            }
+            let (a, b) = (world.registry.entities()[0].id(), world.registry.entities()[1].id());
+            assert_eq!(world.globals["events"], json::array![["enter", a, b], ["stay", a, b], ["exit", a, b]]);
+        });
+    }
 }
@@ -8,13 +8,23 @@ To be implemented by each plugin for the island engine.
 For if you need rust's speed or need to render something in a way ie doesn't provide on its own.
 
 ```no_run
+use ie::prelude::*;
+
 pub struct ExamplePlugin;
 
-impl ie::Plugin for ExamplePlugin {
-    fn update(&mut self, world: &mut World) -> IslandResult<()> {
+impl Plugin for ExamplePlugin {
+    fn init(&mut self, _world: &mut World) -> IslandResult<()> {
+        return Ok(());
+    }
+
+    fn update(&mut self, _world: &mut World) -> IslandResult<()> {
         println!("Hello, world!");
         return Ok(());
     }
+
+    fn render(&self, _world: &World) -> IslandResult<()> {
+        return Ok(());
+    }
 }
 
 #[macroquad::main("Plugin example")]
@@ -29,6 +39,14 @@ pub trait Plugin {
     fn init(&mut self, _world: &mut World) -> IslandResult<()>;
     /** To be called each update loop */
     fn update(&mut self, _world: &mut World) -> IslandResult<()>;
+    /** To be called each fixed update, when the app runs with a fixed timestep */
+    fn fixed_update(&mut self, _world: &mut World) -> IslandResult<()> {
+        return Ok(());
+    }
+    /** To be called before the scene is replaced by another one */
+    fn on_scene_unload(&mut self, _world: &mut World) -> IslandResult<()> {
+        return Ok(());
+    }
     /** To be called each render loop */
     fn render(&self, _world: &World) -> IslandResult<()>;
 }
\ No newline at end of file
@@ -0,0 +1,378 @@
+//! Prefab and sub-scene references in scene files
+use json::JsonValue;
+
+use crate::error::{ErrorKind, IslandError, IslandResult};
+
+/** The deepest prefabs and sub-scenes can be nested, to catch reference cycles */
+const MAX_DEPTH: usize = 16;
+
+/**
+Resolves `"prefab": "path.json"` entities and `"subscenes": [...]` scene references.
+Prefab files hold a single entity object, the entity's own values override the prefab's.
+Resolved scenes can be collapsed back to their references for saving, so edits to the referenced files keep reaching
+the scenes that use them.
+*/
+pub struct Prefabs {
+    // The parsed files by path
+    cache: hashbrown::HashMap<String, JsonValue>,
+}
+
+impl Prefabs {
+    /** Construct an empty prefab cache */
+    pub fn new() -> Self {
+        return Self {
+            cache: hashbrown::HashMap::new(),
+        };
+    }
+
+    /** Forget the cached files, so they are read again on the next load */
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /**
+    Expand the prefab of an entity, if it has one.
+    The "prefab" key is kept so the entity still references it when saved.
+    */
+    pub fn resolve_entity(&mut self, entity: &JsonValue) -> IslandResult<JsonValue> {
+        return self.resolve_entity_at(entity, 0);
+    }
+
+    /**
+    Expand the sub-scenes and prefabs of a scene.
+    The entities and scripts of sub-scenes are added to the scene, the entities get new ids and a "subscene" key
+    naming the sub-scene they come from. The "subscenes" references are kept.
+    */
+    pub fn resolve_scene(&mut self, scene: &JsonValue) -> IslandResult<JsonValue> {
+        return self.resolve_scene_at(scene, 0);
+    }
+
+    /**
+    Reduce an expanded entity to the values that differ from its prefab, if it has one.
+    Values the entity doesn't have but its prefab does can't be written as overrides, they come back when loading.
+    */
+    pub fn collapse_entity(&mut self, entity: &JsonValue) -> IslandResult<JsonValue> {
+        if !entity.has_key("prefab") {
+            return Ok(entity.clone());
+        }
+        let prefab = self.resolve_entity(&json::object! { prefab: entity["prefab"].clone() })?;
+        let mut result = diff_json(&prefab, entity);
+        result["prefab"] = entity["prefab"].clone();
+        return Ok(result);
+    }
+
+    /**
+    Turn an expanded scene back into the references it was loaded from, the inverse of `resolve_scene`.
+    The entities and scripts of sub-scenes are left out and prefab entities keep only their overrides.
+    */
+    pub fn collapse_scene(&mut self, scene: &JsonValue) -> IslandResult<JsonValue> {
+        let mut result = scene.clone();
+
+        let mut entities = JsonValue::new_array();
+        for entity in scene["entities"].members() {
+            // Sub-scene entities are loaded from their sub-scene
+            if !entity.has_key("subscene") {
+                entities.push(self.collapse_entity(entity)?).unwrap();
+            }
+        }
+        result["entities"] = entities;
+
+        if scene.has_key("subscenes") {
+            let subscenes = self.resolve_scene(&json::object! { subscenes: scene["subscenes"].clone() })?;
+            let scripts = result["scripts"]
+                .members()
+                .filter(|script| !subscenes["scripts"].contains((*script).clone()))
+                .cloned()
+                .collect::<Vec<_>>();
+            if result.has_key("scripts") {
+                result["scripts"] = scripts.into();
+            }
+        }
+        return Ok(result);
+    }
+
+    /** Get the paths of the files that have been loaded */
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        return self.cache.keys().map(|path| path.as_str());
+    }
+
+    /** Read and parse a referenced file */
+    fn load(&mut self, path: &str) -> IslandResult<&JsonValue> {
+        if !self.cache.contains_key(path) {
+            let file = std::fs::read_to_string(path).map_err(|e| {
+                return IslandError::with_source(ErrorKind::Io, format!("Failed to load \"{}\"", path), e);
+            })?;
+            let json = json::parse(&file).map_err(|e| {
+                return IslandError::with_source(ErrorKind::Json, format!("Failed to parse \"{}\"", path), e);
+            })?;
+            self.cache.insert(path.into(), json);
+        }
+        return Ok(&self.cache[path]);
+    }
+
+    fn resolve_entity_at(&mut self, entity: &JsonValue, depth: usize) -> IslandResult<JsonValue> {
+        if !entity.has_key("prefab") {
+            return Ok(entity.clone());
+        }
+        if depth >= MAX_DEPTH {
+            return Err(IslandError::with_kind(ErrorKind::SceneValidation, format!(
+                "Prefabs are nested too deep, is there a reference cycle? {}",
+                entity
+            )));
+        }
+        let path = entity["prefab"].as_str().ok_or_else(|| {
+            return IslandError::with_kind(ErrorKind::SceneValidation, format!("Prefab path has to be a string: {}", entity["prefab"]));
+        })?;
+
+        // Resolve the prefab itself first, prefabs can be based on other prefabs
+        let prefab = self.load(path)?.clone();
+        let mut result = self.resolve_entity_at(&prefab, depth + 1)?;
+        result.remove("id");
+        merge_json(&mut result, entity);
+        return Ok(result);
+    }
+
+    fn resolve_scene_at(&mut self, scene: &JsonValue, depth: usize) -> IslandResult<JsonValue> {
+        if depth >= MAX_DEPTH {
+            return Err(IslandError::with_kind(
+                ErrorKind::SceneValidation,
+                "Sub-scenes are nested too deep, is there a reference cycle?",
+            ));
+        }
+        let mut result = scene.clone();
+
+        // Expand the prefabs of the scene's own entities
+        let mut entities = JsonValue::new_array();
+        for entity in scene["entities"].members() {
+            entities.push(self.resolve_entity(entity)?).unwrap();
+        }
+
+        // Add the entities and scripts of the sub-scenes
+        for path in scene["subscenes"].members() {
+            let path = path.as_str().ok_or_else(|| {
+                return IslandError::with_kind(ErrorKind::SceneValidation, format!("Sub-scene path has to be a string: {}", path));
+            })?;
+            let subscene = self.load(path)?.clone();
+            let subscene = self.resolve_scene_at(&subscene, depth + 1)?;
+            for entity in subscene["entities"].members() {
+                let mut entity = entity.clone();
+                entity.remove("id");
+                entity["subscene"] = path.into();
+                entities.push(entity).unwrap();
+            }
+            if !subscene["scripts"].is_empty() && !result["scripts"].is_array() {
+                result["scripts"] = JsonValue::new_array();
+            }
+            for script in subscene["scripts"].members() {
+                if !result["scripts"].contains(script.clone()) {
+                    result["scripts"].push(script.clone()).unwrap();
+                }
+            }
+        }
+
+        result["entities"] = entities;
+        return Ok(result);
+    }
+}
+
+impl Default for Prefabs {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/** Merge the overrides into the base json, objects are merged recursively and other values replaced */
+pub fn merge_json(base: &mut JsonValue, overrides: &JsonValue) {
+    if !(base.is_object() && overrides.is_object()) {
+        *base = overrides.clone();
+        return;
+    }
+    for (key, value) in overrides.entries() {
+        if base[key].is_object() && value.is_object() {
+            merge_json(&mut base[key], value);
+        } else {
+            base[key] = value.clone();
+        }
+    }
+}
+
+/**
+Get the values that differ from the base json, the inverse of `merge_json`.
+Objects are compared recursively and numbers as the floats the components keep.
+*/
+pub fn diff_json(base: &JsonValue, value: &JsonValue) -> JsonValue {
+    if !(base.is_object() && value.is_object()) {
+        return value.clone();
+    }
+    let mut result = JsonValue::new_object();
+    for (key, value) in value.entries() {
+        if !base.has_key(key) {
+            result[key] = value.clone();
+        } else if !same_json(&base[key], value) {
+            result[key] = diff_json(&base[key], value);
+        }
+    }
+    return result;
+}
+
+/** Check if two json values are the same, numbers are the same if they give the same float */
+fn same_json(a: &JsonValue, b: &JsonValue) -> bool {
+    return match (a, b) {
+        (JsonValue::Number(_), JsonValue::Number(_)) => a.as_f32() == b.as_f32(),
+        (JsonValue::Object(_), JsonValue::Object(_)) => {
+            a.len() == b.len() && a.entries().all(|(key, value)| b.has_key(key) && same_json(value, &b[key]))
+        }
+        (JsonValue::Array(_), JsonValue::Array(_)) => {
+            a.len() == b.len() && a.members().zip(b.members()).all(|(a, b)| same_json(a, b))
+        }
+        _ => a == b,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** Write the files to a temporary directory named after the test, returns their paths by name */
+    fn write_files(test: &str, files: &[(&str, JsonValue)]) -> hashbrown::HashMap<String, String> {
+        let directory = std::env::temp_dir().join(format!("ie_test_{}", test));
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut paths = hashbrown::HashMap::new();
+        for (name, json) in files.iter() {
+            let path = directory.join(name);
+            std::fs::write(&path, json.dump()).unwrap();
+            paths.insert(name.to_string(), path.to_string_lossy().into_owned());
+        }
+        return paths;
+    }
+
+    #[test]
+    fn objects_merge_recursively_and_other_values_are_replaced() {
+        let mut base = json::object! {
+            position: { x: 1, y: 2 },
+            tags: ["a", "b"],
+            name: "base",
+            sprite: { texture: "base", flip_x: true },
+        };
+        merge_json(&mut base, &json::object! {
+            position: { y: 5 },
+            tags: ["c"],
+            sprite: "replaced",
+            extra: null,
+        });
+        assert_eq!(base, json::object! {
+            position: { x: 1, y: 5 },
+            tags: ["c"],
+            name: "base",
+            sprite: "replaced",
+            extra: null,
+        });
+    }
+
+    #[test]
+    fn entities_override_their_nested_prefabs() {
+        let paths = write_files("nested_prefabs", &[(
+            "base.json",
+            json::object! { id: 9, name: "base", position: { x: 1, y: 1 }, scale: { x: 2, y: 2 } },
+        )]);
+        let enemy = json::object! { prefab: paths["base.json"].as_str(), name: "enemy", position: { x: 3 } };
+        let paths = write_files("nested_prefabs", &[("enemy.json", enemy)]);
+
+        let mut prefabs = Prefabs::new();
+        let entity = json::object! { id: 4, prefab: paths["enemy.json"].as_str(), position: { y: 7 } };
+        let resolved = prefabs.resolve_entity(&entity).unwrap();
+        assert_eq!(resolved, json::object! {
+            // The entity keeps its own id, not the one of the prefab
+            name: "enemy",
+            position: { x: 3, y: 7 },
+            scale: { x: 2, y: 2 },
+            prefab: paths["enemy.json"].as_str(),
+            id: 4,
+        });
+        assert_eq!(prefabs.paths().count(), 2);
+    }
+
+    #[test]
+    fn prefab_cycles_are_errors() {
+        let paths = write_files("prefab_cycle", &[("a.json", json::object! {}), ("b.json", json::object! {})]);
+        std::fs::write(&paths["a.json"], json::object! { prefab: paths["b.json"].as_str() }.dump()).unwrap();
+        std::fs::write(&paths["b.json"], json::object! { prefab: paths["a.json"].as_str() }.dump()).unwrap();
+
+        let entity = json::object! { prefab: paths["a.json"].as_str() };
+        assert!(Prefabs::new().resolve_entity(&entity).is_err());
+        assert!(Prefabs::new().resolve_entity(&json::object! { prefab: 5 }).is_err());
+    }
+
+    #[test]
+    fn subscenes_add_their_entities_and_scripts() {
+        let paths = write_files("subscenes", &[(
+            "room.json",
+            json::object! {
+                scripts: ["shared.py", "room.py"],
+                entities: [{ id: 0, name: "door" }, { id: 1, name: "key" }],
+            },
+        )]);
+
+        let scene = json::object! {
+            scripts: ["shared.py"],
+            subscenes: [paths["room.json"].as_str()],
+            entities: [{ id: 0, name: "player" }],
+        };
+        let resolved = Prefabs::new().resolve_scene(&scene).unwrap();
+        let room = paths["room.json"].as_str();
+        assert_eq!(resolved, json::object! {
+            scripts: ["shared.py", "room.py"],
+            subscenes: [room],
+            // The entities of the sub-scene get new ids when the scene loads
+            entities: [{ id: 0, name: "player" }, { name: "door", subscene: room }, { name: "key", subscene: room }],
+        });
+    }
+
+    #[test]
+    fn collapsed_entities_keep_only_their_overrides() {
+        let enemy = json::object! {
+            name: "enemy",
+            position: { x: 0.1, y: 2 },
+            sprite: { texture: "enemy", dest_size: { x: 16, y: 16 } },
+        };
+        let paths = write_files("collapse_entity", &[("enemy.json", enemy)]);
+        let mut prefabs = Prefabs::new();
+
+        let entity = json::object! { id: 3, prefab: paths["enemy.json"].as_str(), position: { x: 5 } };
+        let mut resolved = prefabs.resolve_entity(&entity).unwrap();
+        // Saved components hold floats, they still match the prefab's numbers
+        resolved["position"]["x"] = 5.0f32.into();
+        resolved["position"]["y"] = 2.0f32.into();
+        assert_eq!(prefabs.collapse_entity(&resolved).unwrap(), entity);
+
+        resolved["sprite"]["dest_size"]["y"] = 32.into();
+        resolved["tag"] = "boss".into();
+        assert_eq!(prefabs.collapse_entity(&resolved).unwrap(), json::object! {
+            id: 3,
+            prefab: paths["enemy.json"].as_str(),
+            position: { x: 5 },
+            sprite: { dest_size: { y: 32 } },
+            tag: "boss",
+        });
+
+        let plain = json::object! { id: 1, name: "plain" };
+        assert_eq!(prefabs.collapse_entity(&plain).unwrap(), plain);
+    }
+
+    #[test]
+    fn collapsed_scenes_keep_their_references() {
+        let paths = write_files("collapse_scene", &[
+            ("crate.json", json::object! { name: "crate", scale: { x: 2, y: 2 } }),
+            ("room.json", json::object! { scripts: ["room.py"], entities: [{ name: "door" }] }),
+        ]);
+        let scene = json::object! {
+            scripts: ["level.py"],
+            subscenes: [paths["room.json"].as_str()],
+            entities: [{ id: 0, prefab: paths["crate.json"].as_str() }, { id: 1, name: "player" }],
+        };
+        let mut prefabs = Prefabs::new();
+        let resolved = prefabs.resolve_scene(&scene).unwrap();
+        assert_eq!(resolved["entities"].len(), 3);
+        assert_eq!(prefabs.collapse_scene(&resolved).unwrap(), scene);
+    }
+}
@@ -0,0 +1,625 @@
+//! The `ie` python module, so scripts can `import ie` for the engine types
+use json::JsonValue;
+use macroquad::prelude::{Color, Rect, Vec2};
+use pyo3::{
+    basic::CompareOp,
+    prelude::*,
+    types::PyDict,
+    wrap_pymodule, PyNativeType, PyNumberProtocol, PyObjectProtocol,
+};
+
+use super::{
+    ctx::HighgroundCtx,
+    scene::{PyEntityList, PyScene, PySceneEntity},
+    storage::Entity,
+    systems::collision::RayHit,
+    WorldState,
+};
+
+/** A 2d vector */
+#[pyclass(name = "Vec2")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PyVec2 {
+    #[pyo3(get, set)]
+    pub x: f32,
+    #[pyo3(get, set)]
+    pub y: f32,
+}
+
+#[pymethods]
+impl PyVec2 {
+    /** Construct a new vector */
+    #[new]
+    #[args(x = "0.0", y = "0.0")]
+    pub fn new(x: f32, y: f32) -> Self {
+        return Self { x, y };
+    }
+
+    /** Read a vector from a `{"x": ..., "y": ...}` dict, like the position of an entity */
+    #[staticmethod]
+    pub fn from_dict(dict: &PyAny) -> PyResult<Self> {
+        return Ok(Self {
+            x: dict.get_item("x")?.extract()?,
+            y: dict.get_item("y")?.extract()?,
+        });
+    }
+
+    /** Get the vector as a `{"x": ..., "y": ...}` dict */
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        dict.set_item("x", self.x)?;
+        dict.set_item("y", self.y)?;
+        return Ok(dict.into());
+    }
+
+    /** Get the length of the vector */
+    pub fn length(&self) -> f32 {
+        return Vec2::from(*self).length();
+    }
+
+    /** Get the vector scaled to a length of 1, or zero if it has no length */
+    pub fn normalized(&self) -> Self {
+        return Vec2::from(*self).normalize_or_zero().into();
+    }
+
+    /** Get the dot product with another vector */
+    pub fn dot(&self, other: PyVec2) -> f32 {
+        return Vec2::from(*self).dot(other.into());
+    }
+
+    /** Get the distance to another vector */
+    pub fn distance(&self, other: PyVec2) -> f32 {
+        return Vec2::from(*self).distance(other.into());
+    }
+
+    /** Get the vector `t` of the way to another vector */
+    pub fn lerp(&self, other: PyVec2, t: f32) -> Self {
+        return Vec2::from(*self).lerp(other.into(), t).into();
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyVec2 {
+    fn __repr__(&self) -> String {
+        return format!("Vec2({}, {})", self.x, self.y);
+    }
+
+    fn __richcmp__(&self, other: PyVec2, op: CompareOp) -> PyResult<bool> {
+        return compare(self == &other, op);
+    }
+}
+
+#[pyproto]
+impl PyNumberProtocol for PyVec2 {
+    fn __add__(lhs: PyVec2, rhs: PyVec2) -> PyVec2 {
+        return (Vec2::from(lhs) + Vec2::from(rhs)).into();
+    }
+
+    fn __sub__(lhs: PyVec2, rhs: PyVec2) -> PyVec2 {
+        return (Vec2::from(lhs) - Vec2::from(rhs)).into();
+    }
+
+    fn __mul__(lhs: PyVec2, rhs: f32) -> PyVec2 {
+        return (Vec2::from(lhs) * rhs).into();
+    }
+
+    fn __rmul__(&self, other: f32) -> PyVec2 {
+        return (Vec2::from(*self) * other).into();
+    }
+
+    fn __truediv__(lhs: PyVec2, rhs: f32) -> PyVec2 {
+        return (Vec2::from(lhs) / rhs).into();
+    }
+
+    fn __neg__(&self) -> PyVec2 {
+        return (-Vec2::from(*self)).into();
+    }
+}
+
+impl From<Vec2> for PyVec2 {
+    fn from(vec: Vec2) -> Self {
+        return Self { x: vec.x, y: vec.y };
+    }
+}
+
+impl From<PyVec2> for Vec2 {
+    fn from(vec: PyVec2) -> Self {
+        return Vec2::new(vec.x, vec.y);
+    }
+}
+
+/** A rectangle from its top left corner and size */
+#[pyclass(name = "Rect")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PyRect {
+    #[pyo3(get, set)]
+    pub x: f32,
+    #[pyo3(get, set)]
+    pub y: f32,
+    #[pyo3(get, set)]
+    pub w: f32,
+    #[pyo3(get, set)]
+    pub h: f32,
+}
+
+#[pymethods]
+impl PyRect {
+    /** Construct a new rectangle */
+    #[new]
+    #[args(x = "0.0", y = "0.0", w = "0.0", h = "0.0")]
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        return Self { x, y, w, h };
+    }
+
+    /** Read a rectangle from a `{"x": ..., "y": ..., "w": ..., "h": ...}` dict, like the source of a sprite */
+    #[staticmethod]
+    pub fn from_dict(dict: &PyAny) -> PyResult<Self> {
+        return Ok(Self {
+            x: dict.get_item("x")?.extract()?,
+            y: dict.get_item("y")?.extract()?,
+            w: dict.get_item("w")?.extract()?,
+            h: dict.get_item("h")?.extract()?,
+        });
+    }
+
+    /** Get the rectangle as a `{"x": ..., "y": ..., "w": ..., "h": ...}` dict */
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        dict.set_item("x", self.x)?;
+        dict.set_item("y", self.y)?;
+        dict.set_item("w", self.w)?;
+        dict.set_item("h", self.h)?;
+        return Ok(dict.into());
+    }
+
+    /** Get the center of the rectangle */
+    pub fn center(&self) -> PyVec2 {
+        return PyVec2::new(self.x + self.w / 2.0, self.y + self.h / 2.0);
+    }
+
+    /** Check if a point is inside the rectangle */
+    pub fn contains(&self, point: PyVec2) -> bool {
+        return Rect::from(*self).contains(point.into());
+    }
+
+    /** Check if the rectangle overlaps another one */
+    pub fn overlaps(&self, other: PyRect) -> bool {
+        return Rect::from(*self).overlaps(&other.into());
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyRect {
+    fn __repr__(&self) -> String {
+        return format!("Rect({}, {}, {}, {})", self.x, self.y, self.w, self.h);
+    }
+
+    fn __richcmp__(&self, other: PyRect, op: CompareOp) -> PyResult<bool> {
+        return compare(self == &other, op);
+    }
+}
+
+impl From<Rect> for PyRect {
+    fn from(rect: Rect) -> Self {
+        return Self {
+            x: rect.x,
+            y: rect.y,
+            w: rect.w,
+            h: rect.h,
+        };
+    }
+}
+
+impl From<PyRect> for Rect {
+    fn from(rect: PyRect) -> Self {
+        return Rect::new(rect.x, rect.y, rect.w, rect.h);
+    }
+}
+
+/** A color with channels from 0 to 1 */
+#[pyclass(name = "Color")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PyColor {
+    #[pyo3(get, set)]
+    pub r: f32,
+    #[pyo3(get, set)]
+    pub g: f32,
+    #[pyo3(get, set)]
+    pub b: f32,
+    #[pyo3(get, set)]
+    pub a: f32,
+}
+
+#[pymethods]
+impl PyColor {
+    /** Construct a new color */
+    #[new]
+    #[args(a = "1.0")]
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        return Self { r, g, b, a };
+    }
+
+    /** Construct a color from channels from 0 to 255 */
+    #[staticmethod]
+    #[args(a = "255")]
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        return Color::from_rgba(r, g, b, a).into();
+    }
+
+    /** Read a color from a `{"r": ..., "g": ..., "b": ..., "a": ...}` dict, alpha defaults to 1 */
+    #[staticmethod]
+    pub fn from_dict(dict: &PyAny) -> PyResult<Self> {
+        let a = match dict.get_item("a") {
+            Ok(a) => a.extract()?,
+            Err(_) => 1.0,
+        };
+        return Ok(Self {
+            r: dict.get_item("r")?.extract()?,
+            g: dict.get_item("g")?.extract()?,
+            b: dict.get_item("b")?.extract()?,
+            a,
+        });
+    }
+
+    /** Get the color as a `{"r": ..., "g": ..., "b": ..., "a": ...}` dict */
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        dict.set_item("r", self.r)?;
+        dict.set_item("g", self.g)?;
+        dict.set_item("b", self.b)?;
+        dict.set_item("a", self.a)?;
+        return Ok(dict.into());
+    }
+
+    #[classattr]
+    const WHITE: PyColor = PyColor {
+        r: 1.0,
+        g: 1.0,
+        b: 1.0,
+        a: 1.0,
+    };
+    #[classattr]
+    const BLACK: PyColor = PyColor {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    };
+    #[classattr]
+    const RED: PyColor = PyColor {
+        r: 1.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    };
+    #[classattr]
+    const GREEN: PyColor = PyColor {
+        r: 0.0,
+        g: 1.0,
+        b: 0.0,
+        a: 1.0,
+    };
+    #[classattr]
+    const BLUE: PyColor = PyColor {
+        r: 0.0,
+        g: 0.0,
+        b: 1.0,
+        a: 1.0,
+    };
+    #[classattr]
+    const YELLOW: PyColor = PyColor {
+        r: 1.0,
+        g: 1.0,
+        b: 0.0,
+        a: 1.0,
+    };
+    #[classattr]
+    const TRANSPARENT: PyColor = PyColor {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 0.0,
+    };
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyColor {
+    fn __repr__(&self) -> String {
+        return format!("Color({}, {}, {}, {})", self.r, self.g, self.b, self.a);
+    }
+
+    fn __richcmp__(&self, other: PyColor, op: CompareOp) -> PyResult<bool> {
+        return compare(self == &other, op);
+    }
+}
+
+impl From<Color> for PyColor {
+    fn from(color: Color) -> Self {
+        return Self {
+            r: color.r,
+            g: color.g,
+            b: color.b,
+            a: color.a,
+        };
+    }
+}
+
+impl From<PyColor> for Color {
+    fn from(color: PyColor) -> Self {
+        return Color::new(color.r, color.g, color.b, color.a);
+    }
+}
+
+/** An entity id taken from python, either an int or an `Entity` handle */
+pub type EntityId = u64;
+
+/**
+A handle to an entity.
+It can be used anywhere an entity id is expected and compares equal to its id.
+*/
+#[pyclass(name = "Entity")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PyEntity {
+    #[pyo3(get)]
+    pub id: u64,
+}
+
+#[pymethods]
+impl PyEntity {
+    /** Construct a handle to the entity with the id */
+    #[new]
+    pub fn new(id: EntityId) -> Self {
+        return Self { id };
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyEntity {
+    fn __repr__(&self) -> String {
+        return format!("Entity({})", self.id);
+    }
+
+    fn __hash__(&self) -> u64 {
+        // The same hash as the id, so handles and ids find the same dict entries
+        return self.id;
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyResult<PyObject> {
+        let py = other.py();
+        let other = match other.extract::<PyEntity>() {
+            Ok(other) => other.id,
+            Err(_) => match other.extract::<u64>() {
+                Ok(other) => other,
+                Err(_) => return Ok(py.NotImplemented()),
+            },
+        };
+        return Ok(compare(self.id == other, op)?.into_py(py));
+    }
+}
+
+#[pyproto]
+impl PyNumberProtocol for PyEntity {
+    fn __index__(&self) -> u64 {
+        return self.id;
+    }
+}
+
+impl From<Entity> for PyEntity {
+    fn from(entity: Entity) -> Self {
+        return Self { id: entity.id() };
+    }
+}
+
+/** Where a ray cast by the context hit a collider */
+#[pyclass(name = "RayHit")]
+#[derive(Debug, Clone, Copy)]
+pub struct PyRayHit {
+    // The entity of the collider that was hit
+    #[pyo3(get)]
+    pub entity: PyEntity,
+    // The point on the edge of the collider, the origin if the ray started inside it
+    #[pyo3(get)]
+    pub point: PyVec2,
+    // The normal of the edge that was hit, zero if the ray started inside the collider
+    #[pyo3(get)]
+    pub normal: PyVec2,
+    // The distance from the origin to the point
+    #[pyo3(get)]
+    pub distance: f32,
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyRayHit {
+    fn __repr__(&self) -> String {
+        return format!(
+            "RayHit(entity={}, point=({}, {}), normal=({}, {}), distance={})",
+            self.entity.id, self.point.x, self.point.y, self.normal.x, self.normal.y, self.distance
+        );
+    }
+}
+
+impl From<RayHit> for PyRayHit {
+    fn from(hit: RayHit) -> Self {
+        return Self {
+            entity: hit.entity.into(),
+            point: hit.point.into(),
+            normal: hit.normal.into(),
+            distance: hit.distance,
+        };
+    }
+}
+
+/** The state of the world, it compares equal to its name */
+#[pyclass(name = "WorldState")]
+#[derive(Debug, Clone, Copy)]
+pub struct PyWorldState {
+    pub state: WorldState,
+}
+
+#[pymethods]
+#[allow(non_upper_case_globals)]
+impl PyWorldState {
+    #[classattr]
+    const Editor: PyWorldState = PyWorldState {
+        state: WorldState::Editor,
+    };
+    #[classattr]
+    const Menu: PyWorldState = PyWorldState {
+        state: WorldState::Menu,
+    };
+    #[classattr]
+    const Gameplay: PyWorldState = PyWorldState {
+        state: WorldState::Gameplay,
+    };
+}
+
+impl PyWorldState {
+    /** Get the name of the state */
+    pub fn name(&self) -> &'static str {
+        return match self.state {
+            WorldState::Editor => "Editor",
+            WorldState::Menu => "Menu",
+            WorldState::Gameplay => "Gameplay",
+        };
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyWorldState {
+    fn __repr__(&self) -> String {
+        return format!("WorldState.{}", self.name());
+    }
+
+    fn __str__(&self) -> &'static str {
+        return self.name();
+    }
+
+    fn __hash__(&self) -> u64 {
+        return self.state as u64;
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyResult<PyObject> {
+        let py = other.py();
+        let other = match other.extract::<PyWorldState>() {
+            Ok(other) => other.name(),
+            Err(_) => match other.extract::<&str>() {
+                Ok(other) => other,
+                Err(_) => return Ok(py.NotImplemented()),
+            },
+        };
+        return Ok(compare(self.name() == other, op)?.into_py(py));
+    }
+}
+
+/** The names of the keys, for the key functions of the context */
+#[pyclass(name = "Key")]
+pub struct PyKey;
+
+/** The names of the mouse buttons, for the mouse functions of the context */
+#[pyclass(name = "MouseButton")]
+pub struct PyMouseButton;
+
+/** The names of the gamepad buttons, for the gamepad functions of the context, `South` to `North` are the face buttons by position */
+#[pyclass(name = "GamepadButton")]
+pub struct PyGamepadButton;
+
+/** The names of the gamepad axes, for `gamepad_axis` of the context */
+#[pyclass(name = "GamepadAxis")]
+pub struct PyGamepadAxis;
+
+/** Defines name constants on a class, each holding its own name */
+macro_rules! name_constants {
+    ($class:ident, $($name:ident),* $(,)?) => {
+        #[pymethods]
+        #[allow(non_upper_case_globals)]
+        impl $class {
+            $(
+                #[classattr]
+                const $name: &'static str = stringify!($name);
+            )*
+        }
+    };
+}
+
+// The keys are named like the keys in macroquad
+name_constants!(
+    PyKey, Space, Apostrophe, Comma, Minus, Period, Slash, Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7,
+    Key8, Key9, Semicolon, Equal, A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V,
+    W, X, Y, Z, LeftBracket, Backslash, RightBracket, GraveAccent, World1, World2, Escape, Enter,
+    Tab, Backspace, Insert, Delete, Right, Left, Down, Up, PageUp, PageDown, Home, End, CapsLock,
+    ScrollLock, NumLock, PrintScreen, Pause, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13,
+    F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25, Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6,
+    Kp7, Kp8, Kp9, KpDecimal, KpDivide, KpMultiply, KpSubtract, KpAdd, KpEnter, KpEqual, LeftShift,
+    LeftControl, LeftAlt, LeftSuper, RightShift, RightControl, RightAlt, RightSuper, Menu,
+);
+name_constants!(PyMouseButton, Left, Right, Middle);
+name_constants!(
+    PyGamepadButton, A, B, X, Y, South, East, West, North, LeftBumper, RightBumper, Back, Start, Guide, LeftStick,
+    RightStick, DPadUp, DPadDown, DPadLeft, DPadRight,
+);
+name_constants!(PyGamepadAxis, LeftX, LeftY, RightX, RightY, LeftTrigger, RightTrigger);
+
+/** Apply an equality comparison, other comparisons are not supported */
+pub(crate) fn compare(equal: bool, op: CompareOp) -> PyResult<bool> {
+    return match op {
+        CompareOp::Eq => Ok(equal),
+        CompareOp::Ne => Ok(!equal),
+        _ => Err(pyo3::exceptions::PyTypeError::new_err("Only == and != are supported")),
+    };
+}
+
+/** The engine types for the python scripts */
+#[pymodule]
+fn ie(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    module.add_class::<PyVec2>()?;
+    module.add_class::<PyRect>()?;
+    module.add_class::<PyColor>()?;
+    module.add_class::<PyEntity>()?;
+    module.add_class::<PyRayHit>()?;
+    module.add_class::<PyWorldState>()?;
+    module.add_class::<PyScene>()?;
+    module.add_class::<PyEntityList>()?;
+    module.add_class::<PySceneEntity>()?;
+    module.add_class::<PyKey>()?;
+    module.add_class::<PyMouseButton>()?;
+    module.add_class::<PyGamepadButton>()?;
+    module.add_class::<PyGamepadAxis>()?;
+    module.add_class::<HighgroundCtx>()?;
+    return Ok(());
+}
+
+/** The python type stubs of the `ie` module, generated from its bindings by the build script */
+pub const STUBS: &str = include_str!(concat!(env!("OUT_DIR"), "/ie.pyi"));
+
+/** Make `import ie` find the engine module, if it is not importable yet */
+pub(crate) fn register_module(py: Python) -> PyResult<()> {
+    let modules = py.import("sys")?.getattr("modules")?.downcast::<PyDict>()?;
+    if !modules.contains("ie")? {
+        modules.set_item("ie", wrap_pymodule!(ie)(py))?;
+    }
+    return Ok(());
+}
+
+/** Convert one of the engine types to its json value, none if it is not one */
+pub(crate) fn engine_type_to_json(object: &PyAny) -> Option<JsonValue> {
+    if let Ok(vec) = object.extract::<PyVec2>() {
+        return Some(json::object! { x: vec.x, y: vec.y });
+    }
+    if let Ok(rect) = object.extract::<PyRect>() {
+        return Some(json::object! { x: rect.x, y: rect.y, w: rect.w, h: rect.h });
+    }
+    if let Ok(color) = object.extract::<PyColor>() {
+        return Some(json::object! { r: color.r, g: color.g, b: color.b, a: color.a });
+    }
+    if let Ok(entity) = object.extract::<PyEntity>() {
+        return Some(entity.id.into());
+    }
+    if let Ok(entity) = object.extract::<PyRef<PySceneEntity>>() {
+        return Some(entity.id.into());
+    }
+    if let Ok(state) = object.extract::<PyWorldState>() {
+        return Some(state.name().into());
+    }
+    return None;
+}
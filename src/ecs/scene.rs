@@ -0,0 +1,875 @@
+//! The scene that script stages pass to python, read and written through to the registry of the world
+use std::{
+    borrow::Cow,
+    cell::{RefCell, RefMut},
+    rc::Rc,
+};
+
+use json::JsonValue;
+use pyo3::{
+    basic::CompareOp,
+    exceptions::{PyIndexError, PyKeyError, PyRuntimeError, PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyList, PySlice},
+    PyIterProtocol, PyNativeType, PyMappingProtocol, PyNumberProtocol, PyObjectProtocol, PySequenceProtocol,
+};
+
+use crate::error::{ErrorKind, IslandError, IslandResult};
+
+use super::{
+    components::{Camera, Component},
+    convert::{json_to_py, py_to_json},
+    parse_scene,
+    prefab::Prefabs,
+    python::compare,
+    storage::{Entity, Registry},
+};
+
+/**
+The scene of the world while a script stage runs, shared by the python objects the scripts use it through.
+The world moves its registry, camera, scene values and prefabs in for the stage and takes them back when it is over.
+Components are converted to python the first time a script gets them, and written back after the script returns.
+*/
+pub(crate) struct StageScene {
+    // If a stage is running, the scene objects can't be used outside of one
+    running: bool,
+    // The entities and their components
+    pub(crate) registry: Registry,
+    // The scene camera
+    pub(crate) camera: Camera,
+    // The scene-level values that are not entities or the camera
+    pub(crate) values: JsonValue,
+    // The prefabs entities spawned by the scripts are expanded with
+    pub(crate) prefabs: Prefabs,
+    // The python objects of the components the running script got or set, by entity and name
+    components: hashbrown::HashMap<Entity, hashbrown::HashMap<String, PyObject>>,
+    // The python objects of the camera and the scene-level values the running script got or set
+    touched_values: hashbrown::HashMap<String, PyObject>,
+}
+
+impl StageScene {
+    /** Construct a scene that waits for the first stage */
+    pub(crate) fn new() -> Self {
+        return Self {
+            running: false,
+            registry: Registry::new(),
+            camera: Camera::default(),
+            values: JsonValue::new_object(),
+            prefabs: Prefabs::new(),
+            components: hashbrown::HashMap::new(),
+            touched_values: hashbrown::HashMap::new(),
+        };
+    }
+
+    /** Move the scene of the world in for a stage */
+    pub(crate) fn begin(
+        &mut self,
+        registry: &mut Registry,
+        camera: &mut Camera,
+        values: &mut JsonValue,
+        prefabs: &mut Prefabs,
+    ) {
+        std::mem::swap(&mut self.registry, registry);
+        std::mem::swap(&mut self.camera, camera);
+        std::mem::swap(&mut self.values, values);
+        std::mem::swap(&mut self.prefabs, prefabs);
+        self.running = true;
+    }
+
+    /** Move the scene back into the world once the stage is over, what scripts set and did not sync is dropped */
+    pub(crate) fn end(
+        &mut self,
+        registry: &mut Registry,
+        camera: &mut Camera,
+        values: &mut JsonValue,
+        prefabs: &mut Prefabs,
+    ) {
+        self.components.clear();
+        self.touched_values.clear();
+        std::mem::swap(&mut self.registry, registry);
+        std::mem::swap(&mut self.camera, camera);
+        std::mem::swap(&mut self.values, values);
+        std::mem::swap(&mut self.prefabs, prefabs);
+        self.running = false;
+    }
+
+    /**
+    Write the python objects the last script got or set back into the scene.
+    Everything is converted and checked before anything is written, so an invalid value leaves the scene as it was.
+    */
+    pub(crate) fn sync(&mut self, py: Python) -> IslandResult<()> {
+        let components = std::mem::take(&mut self.components);
+        let touched_values = std::mem::take(&mut self.touched_values);
+
+        let mut loaded = vec![];
+        for (entity, components) in components.iter() {
+            if !self.registry.contains(*entity) {
+                continue;
+            }
+            for (name, object) in components.iter() {
+                let json = py_to_json(object.as_ref(py)).map_err(|e| {
+                    return IslandError::python(
+                        py,
+                        format!("Failed to convert component \"{}\" of entity {} to json", name, entity),
+                        e,
+                    );
+                })?;
+                if let Some(Err(e)) = self.registry.validate_component(name, &json) {
+                    return Err(e.context(format!("Scripts set an invalid component on entity {}", entity)));
+                }
+                loaded.push((*entity, name, json));
+            }
+        }
+        let mut camera = None;
+        let mut values = vec![];
+        for (key, object) in touched_values.iter() {
+            let json = py_to_json(object.as_ref(py)).map_err(|e| {
+                return IslandError::python(py, format!("Failed to convert scene value \"{}\" to json", key), e);
+            })?;
+            if key == Camera::NAME {
+                camera = Some(Camera::from_json(&json).map_err(|e| {
+                    return IslandError::with_source(ErrorKind::SceneValidation, "Scripts set an invalid camera", e);
+                })?);
+            } else {
+                values.push((key, json));
+            }
+        }
+
+        for (entity, name, json) in loaded {
+            self.registry.load_component(entity, name, &json)?;
+        }
+        if let Some(camera) = camera {
+            self.camera = camera;
+        }
+        for (key, json) in values {
+            self.values[key.as_str()] = json;
+        }
+        return Ok(());
+    }
+
+    /** Drop what the last script got or set without writing it, for a script that failed */
+    pub(crate) fn discard(&mut self) {
+        self.components.clear();
+        self.touched_values.clear();
+    }
+
+    /**
+    Replace the whole scene with a json scene a script returned.
+    Nothing changes if the scene is invalid, the entities keep the ids they have in it.
+    */
+    pub(crate) fn replace(&mut self, scene: JsonValue) -> IslandResult<()> {
+        let (values, camera, registry) = parse_scene(scene, &self.registry)?;
+        self.values = values;
+        self.camera = camera;
+        self.registry = registry;
+        self.components.clear();
+        self.touched_values.clear();
+        return Ok(());
+    }
+
+    /**
+    Get a component the way the running script sees it.
+    The python object the script got or set takes precedence over the registry, none if it is not a valid component.
+    */
+    pub(crate) fn component<T: Component + Clone>(&self, py: Python, entity: Entity) -> Option<Cow<'_, T>> {
+        return match self.components.get(&entity).and_then(|components| components.get(T::NAME)) {
+            Some(object) => {
+                let json = py_to_json(object.as_ref(py)).ok()?;
+                T::from_json(&json).ok().map(Cow::Owned)
+            }
+            None => self.registry.get::<T>(entity).map(Cow::Borrowed),
+        };
+    }
+
+    /** Despawn an entity right away, returns false if it did not exist */
+    pub(crate) fn despawn(&mut self, entity: Entity) -> bool {
+        self.components.remove(&entity);
+        return self.registry.despawn(entity);
+    }
+
+    /** Get the python object of a component, converting it the first time the running script gets it */
+    fn get_component(&mut self, py: Python, entity: Entity, name: &str) -> PyResult<Option<PyObject>> {
+        if let Some(object) = self.components.get(&entity).and_then(|components| components.get(name)) {
+            return Ok(Some(object.clone_ref(py)));
+        }
+        let json = match self.registry.save_component(entity, name) {
+            Some(json) => json,
+            None => return Ok(None),
+        };
+        let object = json_to_py(py, &json)?;
+        self.components
+            .entry(entity)
+            .or_default()
+            .insert(name.into(), object.clone_ref(py));
+        return Ok(Some(object));
+    }
+
+    /** Check if an entity has a component the way the running script sees it */
+    fn has_component(&self, entity: Entity, name: &str) -> bool {
+        let cached = self.components.get(&entity).map(|components| components.contains_key(name));
+        return cached.unwrap_or(false) || self.registry.has_component(entity, name);
+    }
+
+    /** Get the names of the components of an entity the way the running script sees them */
+    fn component_names(&self, entity: Entity) -> Vec<String> {
+        let mut names = self
+            .registry
+            .component_names(entity)
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if let Some(components) = self.components.get(&entity) {
+            let mut added = components.keys().filter(|name| !names.contains(name)).cloned().collect::<Vec<_>>();
+            added.sort();
+            names.extend(added);
+        }
+        return names;
+    }
+
+    /** Get an entity as json, with the components the running script set on it */
+    fn entity_json(&self, py: Python, entity: Entity) -> PyResult<JsonValue> {
+        let mut json = self.registry.save_entity(entity);
+        if let Some(components) = self.components.get(&entity) {
+            for (name, object) in components.iter() {
+                json[name.as_str()] = py_to_json(object.as_ref(py))?;
+            }
+        }
+        return Ok(json);
+    }
+
+    /** Spawn an entity from json right away, expanding its prefab, raising an error if it is not a valid entity */
+    pub(crate) fn spawn(&mut self, json: &JsonValue) -> PyResult<Entity> {
+        let json = self.resolve_entity(json)?;
+        return self.spawn_resolved(&json);
+    }
+
+    /** Expand the prefab of an entity, raising an error if it can't be loaded */
+    fn resolve_entity(&mut self, json: &JsonValue) -> PyResult<JsonValue> {
+        return self
+            .prefabs
+            .resolve_entity(json)
+            .map_err(|e| PyValueError::new_err(format!("Failed to spawn entity: {:#}", e)));
+    }
+
+    /** Spawn an entity whose prefab is expanded right away, raising an error if it is not a valid entity */
+    fn spawn_resolved(&mut self, json: &JsonValue) -> PyResult<Entity> {
+        return self
+            .registry
+            .load_entity(json)
+            .map_err(|e| PyValueError::new_err(format!("Failed to spawn entity: {:#}", e)));
+    }
+
+    /** Replace all entities with the ones in a json array, nothing changes if one of them is invalid */
+    fn replace_entities(&mut self, entities: &JsonValue) -> PyResult<()> {
+        if !entities.is_array() {
+            return Err(PyTypeError::new_err("The entities of the scene have to be a list"));
+        }
+        let mut registry = self.registry.empty_like();
+        let mut resolved = JsonValue::new_array();
+        for entity in entities.members() {
+            let entity = self
+                .prefabs
+                .resolve_entity(entity)
+                .map_err(|e| PyValueError::new_err(format!("Failed to set the entities: {:#}", e)))?;
+            resolved.push(entity).unwrap();
+        }
+        registry
+            .load_entities(&resolved)
+            .map_err(|e| PyValueError::new_err(format!("Failed to set the entities: {:#}", e)))?;
+        self.registry = registry;
+        self.components.clear();
+        return Ok(());
+    }
+
+    /** Get the python object of the camera or a scene-level value, converting it the first time */
+    fn get_value(&mut self, py: Python, key: &str) -> PyResult<Option<PyObject>> {
+        if let Some(object) = self.touched_values.get(key) {
+            return Ok(Some(object.clone_ref(py)));
+        }
+        let json = if key == Camera::NAME {
+            self.camera.to_json()
+        } else if self.values.has_key(key) {
+            self.values[key].clone()
+        } else {
+            return Ok(None);
+        };
+        let object = json_to_py(py, &json)?;
+        self.touched_values.insert(key.into(), object.clone_ref(py));
+        return Ok(Some(object));
+    }
+
+    /** Remove a scene-level value, removing the camera resets it */
+    fn remove_value(&mut self, key: &str) -> bool {
+        let touched = self.touched_values.remove(key).is_some();
+        if key == Camera::NAME {
+            self.camera = Camera::default();
+            return true;
+        }
+        let present = self.values.has_key(key);
+        self.values.remove(key);
+        return present || touched;
+    }
+
+    /** Get the keys of the scene the way the running script sees them */
+    fn keys(&self) -> Vec<String> {
+        let mut keys = vec![Camera::NAME.to_string(), "entities".to_string()];
+        keys.extend(self.values.entries().map(|(key, _)| key.to_string()));
+        let mut added = self
+            .touched_values
+            .keys()
+            .filter(|key| !keys.contains(key))
+            .cloned()
+            .collect::<Vec<_>>();
+        added.sort();
+        keys.extend(added);
+        return keys;
+    }
+}
+
+/** Borrow the scene for a python object, raising an error outside of a stage or while the scene is in use */
+fn borrow_stage(stage: &Rc<RefCell<StageScene>>) -> PyResult<RefMut<'_, StageScene>> {
+    let stage = stage
+        .try_borrow_mut()
+        .map_err(|_| PyRuntimeError::new_err("The scene is already in use"))?;
+    if !stage.running {
+        return Err(PyRuntimeError::new_err("The scene can only be used while a script stage runs"));
+    }
+    return Ok(stage);
+}
+
+/** Borrow the scene for an entity, raising an error if the entity was despawned */
+fn borrow_entity(stage: &Rc<RefCell<StageScene>>, entity: Entity) -> PyResult<RefMut<'_, StageScene>> {
+    let stage = borrow_stage(stage)?;
+    if !stage.registry.contains(entity) {
+        return Err(PyRuntimeError::new_err(format!("Entity {} does not exist", entity)));
+    }
+    return Ok(stage);
+}
+
+/** Get the key of a scene or entity as a string */
+fn key_str(key: &PyAny) -> PyResult<&str> {
+    return key
+        .extract::<&str>()
+        .map_err(|_| PyKeyError::new_err(format!("Keys of the scene are strings, got {}", key)));
+}
+
+/** Convert entities to json, the entities of the scene with what the running script set on them */
+fn entities_to_json(stage: &Rc<RefCell<StageScene>>, entities: &PyAny) -> PyResult<JsonValue> {
+    let py = entities.py();
+    // Collect the entities first, iterating the scene borrows it
+    let entities = entities.iter()?.collect::<PyResult<Vec<_>>>()?;
+    let stage = borrow_stage(stage)?;
+    let mut result = JsonValue::new_array();
+    for entity in entities {
+        let json = match entity.extract::<PyRef<PySceneEntity>>() {
+            Ok(entity) if stage.registry.contains(entity.entity()) => stage.entity_json(py, entity.entity())?,
+            Ok(entity) => return Err(PyRuntimeError::new_err(format!("Entity {} does not exist", entity.id))),
+            Err(_) => py_to_json(entity)?,
+        };
+        result.push(json).unwrap();
+    }
+    return Ok(result);
+}
+
+/**
+Convert a scene a script returned to json.
+The entities can be the entities of the scene, the other values have to convert to json.
+*/
+pub(crate) fn returned_scene_to_json(stage: &Rc<RefCell<StageScene>>, scene: &PyDict) -> PyResult<JsonValue> {
+    let mut result = JsonValue::new_object();
+    for (key, value) in scene.iter() {
+        let key = key_str(key)?;
+        result[key] = match key {
+            "entities" => entities_to_json(stage, value)?,
+            _ => py_to_json(value)?,
+        };
+    }
+    return Ok(result);
+}
+
+/**
+The scene passed to the scripts, a dict-like view of the world with the `camera`, the `entities` and the
+other scene-level values.
+Changes to the values and components are written to the world when the script returns,
+spawning and despawning entities through the entity list happens right away.
+*/
+#[pyclass(name = "Scene", unsendable)]
+pub(crate) struct PyScene {
+    pub(crate) stage: Rc<RefCell<StageScene>>,
+}
+
+#[pymethods]
+impl PyScene {
+    /** Get the keys of the scene */
+    pub fn keys(&self) -> PyResult<Vec<String>> {
+        return Ok(borrow_stage(&self.stage)?.keys());
+    }
+
+    /** Get the value of a key, the default if the scene does not have it */
+    #[args(default = "None")]
+    pub fn get(&self, py: Python, key: &str, default: Option<PyObject>) -> PyResult<PyObject> {
+        if key == "entities" {
+            return Ok(self.entities(py)?.into_py(py));
+        }
+        let value = borrow_stage(&self.stage)?.get_value(py, key)?;
+        return Ok(value.or(default).unwrap_or_else(|| py.None()));
+    }
+
+    /** Copy the whole scene into a dict, the way it is saved */
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let stage = borrow_stage(&self.stage)?;
+        let mut scene = stage.values.clone();
+        for (key, object) in stage.touched_values.iter() {
+            scene[key.as_str()] = py_to_json(object.as_ref(py))?;
+        }
+        if !stage.touched_values.contains_key(Camera::NAME) {
+            scene[Camera::NAME] = stage.camera.to_json();
+        }
+        let mut entities = JsonValue::new_array();
+        for entity in stage.registry.entities() {
+            entities.push(stage.entity_json(py, *entity)?).unwrap();
+        }
+        scene["entities"] = entities;
+        return json_to_py(py, &scene);
+    }
+}
+
+impl PyScene {
+    /** Get the list of the entities of the scene */
+    fn entities(&self, py: Python) -> PyResult<Py<PyEntityList>> {
+        borrow_stage(&self.stage)?;
+        return Py::new(py, PyEntityList {
+            stage: self.stage.clone(),
+        });
+    }
+}
+
+#[pyproto]
+impl PyMappingProtocol for PyScene {
+    fn __len__(&self) -> PyResult<usize> {
+        return Ok(borrow_stage(&self.stage)?.keys().len());
+    }
+
+    fn __getitem__(&self, key: &PyAny) -> PyResult<PyObject> {
+        let py = key.py();
+        let key = key_str(key)?;
+        if key == "entities" {
+            return Ok(self.entities(py)?.into_py(py));
+        }
+        return borrow_stage(&self.stage)?
+            .get_value(py, key)?
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()));
+    }
+
+    fn __setitem__(&mut self, key: &PyAny, value: &PyAny) -> PyResult<()> {
+        let key = key_str(key)?;
+        if key == "entities" {
+            let entities = entities_to_json(&self.stage, value)?;
+            return borrow_stage(&self.stage)?.replace_entities(&entities);
+        }
+        borrow_stage(&self.stage)?.touched_values.insert(key.into(), value.into());
+        return Ok(());
+    }
+
+    fn __delitem__(&mut self, key: &PyAny) -> PyResult<()> {
+        let key = key_str(key)?;
+        if key == "entities" {
+            return Err(PyTypeError::new_err("The entities of the scene can't be removed, clear them instead"));
+        }
+        if !borrow_stage(&self.stage)?.remove_value(key) {
+            return Err(PyKeyError::new_err(key.to_string()));
+        }
+        return Ok(());
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for PyScene {
+    fn __contains__(&self, key: &PyAny) -> PyResult<bool> {
+        let key = match key.extract::<&str>() {
+            Ok(key) => key,
+            Err(_) => return Ok(false),
+        };
+        return Ok(borrow_stage(&self.stage)?.keys().iter().any(|k| k == key));
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for PyScene {
+    fn __iter__(slf: PyRef<Self>) -> PyResult<PyObject> {
+        let py = slf.py();
+        let keys = PyList::new(py, borrow_stage(&slf.stage)?.keys());
+        return Ok(keys.call_method0("__iter__")?.into());
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyScene {
+    fn __repr__(&self) -> PyResult<String> {
+        return Ok(format!("Scene({} entities)", borrow_stage(&self.stage)?.registry.entities().len()));
+    }
+}
+
+/**
+The entities of the scene, a list-like view of the world in scene order.
+Appending an entity spawns it right away, removing one despawns it and the last entity takes its place.
+*/
+#[pyclass(name = "EntityList", unsendable)]
+pub(crate) struct PyEntityList {
+    stage: Rc<RefCell<StageScene>>,
+}
+
+#[pymethods]
+impl PyEntityList {
+    /** Spawn an entity from a dict right away, expanding its prefab, returns the spawned entity */
+    pub fn append(&mut self, py: Python, entity: &PyAny) -> PyResult<PySceneEntity> {
+        let mut json = entities_to_json(&self.stage, PyList::new(py, [entity]))?[0].take();
+        let dict = entity.downcast::<PyDict>().ok();
+        if dict.is_none() {
+            // A copy of an entity of the scene
+            json.remove("id");
+        }
+
+        let mut stage = borrow_stage(&self.stage)?;
+        let resolved = stage.resolve_entity(&json)?;
+        let spawned = stage.spawn_resolved(&resolved)?;
+        // Keep the objects of the dict, so the script can keep changing them, unless the prefab added to them
+        if let Some(dict) = dict {
+            let components = stage.components.entry(spawned).or_default();
+            for (key, value) in dict.iter() {
+                if let Ok(key) = key.extract::<&str>() {
+                    if key != "id" && resolved[key] == json[key] {
+                        components.insert(key.into(), value.into());
+                    }
+                }
+            }
+        }
+        return Ok(PySceneEntity::new(&self.stage, spawned));
+    }
+
+    /** Spawn each entity of an iterable right away */
+    pub fn extend(&mut self, py: Python, entities: &PyAny) -> PyResult<()> {
+        let entities = entities.iter()?.collect::<PyResult<Vec<_>>>()?;
+        for entity in entities {
+            self.append(py, entity)?;
+        }
+        return Ok(());
+    }
+
+    /** Despawn an entity right away, raising an error if it is not in the scene */
+    pub fn remove(&mut self, entity: &PyAny) -> PyResult<()> {
+        let id = entity_id(entity).ok_or_else(|| PyValueError::new_err(format!("{} is not an entity", entity)))?;
+        if !borrow_stage(&self.stage)?.despawn(Entity::from_id(id)) {
+            return Err(PyValueError::new_err(format!("Entity {} is not in the scene", id)));
+        }
+        return Ok(());
+    }
+
+    /** Despawn the entity at the index right away, returns it as a dict */
+    #[args(index = "-1")]
+    pub fn pop(&mut self, py: Python, index: isize) -> PyResult<PyObject> {
+        let mut stage = borrow_stage(&self.stage)?;
+        let entity = entity_at(&stage, index)?;
+        let json = stage.entity_json(py, entity)?;
+        stage.despawn(entity);
+        return json_to_py(py, &json);
+    }
+
+    /** Despawn all entities right away */
+    pub fn clear(&mut self) -> PyResult<()> {
+        let mut stage = borrow_stage(&self.stage)?;
+        for entity in stage.registry.entities().to_vec() {
+            stage.despawn(entity);
+        }
+        return Ok(());
+    }
+}
+
+#[pyproto]
+impl PyMappingProtocol for PyEntityList {
+    fn __len__(&self) -> PyResult<usize> {
+        return Ok(borrow_stage(&self.stage)?.registry.entities().len());
+    }
+
+    fn __getitem__(&self, index: &PyAny) -> PyResult<PyObject> {
+        let py = index.py();
+        let stage = borrow_stage(&self.stage)?;
+        if let Ok(slice) = index.downcast::<PySlice>() {
+            let entities = slice_entities(&stage, slice)?
+                .into_iter()
+                .map(|entity| PySceneEntity::new(&self.stage, entity).into_py(py))
+                .collect::<Vec<_>>();
+            return Ok(PyList::new(py, entities).into());
+        }
+        let entity = entity_at(&stage, index.extract()?)?;
+        return Ok(PySceneEntity::new(&self.stage, entity).into_py(py));
+    }
+
+    fn __setitem__(&mut self, index: &PyAny, value: &PyAny) -> PyResult<()> {
+        let py = index.py();
+        let index = index
+            .extract::<isize>()
+            .map_err(|_| PyTypeError::new_err("Entities can only be replaced one at a time"))?;
+        let mut json = entities_to_json(&self.stage, PyList::new(py, [value]))?[0].take();
+
+        // Replace the entity in place, keeping its id
+        let mut stage = borrow_stage(&self.stage)?;
+        let entity = entity_at(&stage, index)?;
+        json["id"] = entity.id().into();
+        let mut entities = JsonValue::new_array();
+        for other in stage.registry.entities() {
+            let other = if *other == entity {
+                json.clone()
+            } else {
+                stage.entity_json(py, *other)?
+            };
+            entities.push(other).unwrap();
+        }
+        return stage.replace_entities(&entities);
+    }
+
+    fn __delitem__(&mut self, index: &PyAny) -> PyResult<()> {
+        let mut stage = borrow_stage(&self.stage)?;
+        let entities = match index.downcast::<PySlice>() {
+            Ok(slice) => slice_entities(&stage, slice)?,
+            Err(_) => vec![entity_at(&stage, index.extract()?)?],
+        };
+        for entity in entities {
+            stage.despawn(entity);
+        }
+        return Ok(());
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for PyEntityList {
+    fn __contains__(&self, entity: &PyAny) -> PyResult<bool> {
+        let stage = borrow_stage(&self.stage)?;
+        return Ok(entity_id(entity)
+            .map(|id| stage.registry.contains(Entity::from_id(id)))
+            .unwrap_or(false));
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for PyEntityList {
+    fn __iter__(slf: PyRef<Self>) -> PyResult<PyObject> {
+        let py = slf.py();
+        // Iterate over the entities as they are now, the loop can spawn and despawn entities
+        let entities = borrow_stage(&slf.stage)?
+            .registry
+            .entities()
+            .iter()
+            .map(|entity| PySceneEntity::new(&slf.stage, *entity).into_py(py))
+            .collect::<Vec<_>>();
+        return Ok(PyList::new(py, entities).call_method0("__iter__")?.into());
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyEntityList {
+    fn __repr__(&self) -> PyResult<String> {
+        return Ok(format!("EntityList({})", borrow_stage(&self.stage)?.registry.entities().len()));
+    }
+}
+
+/** Get the entity at an index of the scene, negative indices count from the end */
+fn entity_at(stage: &StageScene, index: isize) -> PyResult<Entity> {
+    let entities = stage.registry.entities();
+    let position = if index < 0 {
+        entities.len() as isize + index
+    } else {
+        index
+    };
+    if position < 0 || position as usize >= entities.len() {
+        return Err(PyIndexError::new_err("Entity index out of range"));
+    }
+    return Ok(entities[position as usize]);
+}
+
+/** Get the entities in a slice of the scene */
+fn slice_entities(stage: &StageScene, slice: &PySlice) -> PyResult<Vec<Entity>> {
+    let entities = stage.registry.entities();
+    let indices = slice.indices(entities.len() as _)?;
+    let mut result = vec![];
+    let mut index = indices.start;
+    for _ in 0..indices.slicelength {
+        result.push(entities[index as usize]);
+        index += indices.step;
+    }
+    return Ok(result);
+}
+
+/** Get the id of an entity of the scene, an `Entity` handle or an int */
+fn entity_id(entity: &PyAny) -> Option<u64> {
+    if let Ok(entity) = entity.extract::<PyRef<PySceneEntity>>() {
+        return Some(entity.id);
+    }
+    return entity.extract::<u64>().ok();
+}
+
+/**
+An entity of the scene, a dict-like view of its components with its `id`.
+Changes to the components are written to the world when the script returns, removing a component happens right away.
+It can be used anywhere an entity id is expected and compares equal to its id.
+*/
+#[pyclass(name = "SceneEntity", unsendable)]
+pub(crate) struct PySceneEntity {
+    stage: Rc<RefCell<StageScene>>,
+    // The id of the entity
+    #[pyo3(get)]
+    pub(crate) id: u64,
+}
+
+impl PySceneEntity {
+    /** Construct the python object of an entity of the scene */
+    pub(crate) fn new(stage: &Rc<RefCell<StageScene>>, entity: Entity) -> Self {
+        return Self {
+            stage: stage.clone(),
+            id: entity.id(),
+        };
+    }
+
+    /** Get the entity */
+    fn entity(&self) -> Entity {
+        return Entity::from_id(self.id);
+    }
+}
+
+#[pymethods]
+impl PySceneEntity {
+    /** Get the keys of the entity, the id followed by the names of its components */
+    pub fn keys(&self) -> PyResult<Vec<String>> {
+        let mut keys = vec!["id".to_string()];
+        keys.extend(borrow_entity(&self.stage, self.entity())?.component_names(self.entity()));
+        return Ok(keys);
+    }
+
+    /** Get the values of the keys of the entity */
+    pub fn values(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        return Ok(self.items(py)?.into_iter().map(|(_, value)| value).collect());
+    }
+
+    /** Get the keys of the entity with their values */
+    pub fn items(&self, py: Python) -> PyResult<Vec<(String, PyObject)>> {
+        let mut stage = borrow_entity(&self.stage, self.entity())?;
+        let mut items = vec![("id".to_string(), self.id.into_py(py))];
+        for name in stage.component_names(self.entity()) {
+            if let Some(component) = stage.get_component(py, self.entity(), &name)? {
+                items.push((name, component));
+            }
+        }
+        return Ok(items);
+    }
+
+    /** Get a component, the default if the entity does not have it */
+    #[args(default = "None")]
+    pub fn get(&self, py: Python, key: &str, default: Option<PyObject>) -> PyResult<PyObject> {
+        if key == "id" {
+            return Ok(self.id.into_py(py));
+        }
+        let component = borrow_entity(&self.stage, self.entity())?.get_component(py, self.entity(), key)?;
+        return Ok(component.or(default).unwrap_or_else(|| py.None()));
+    }
+
+    /** Copy the entity into a dict, the way it is saved */
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let json = borrow_entity(&self.stage, self.entity())?.entity_json(py, self.entity())?;
+        return json_to_py(py, &json);
+    }
+}
+
+#[pyproto]
+impl PyMappingProtocol for PySceneEntity {
+    fn __len__(&self) -> PyResult<usize> {
+        return Ok(self.keys()?.len());
+    }
+
+    fn __getitem__(&self, key: &PyAny) -> PyResult<PyObject> {
+        let py = key.py();
+        let key = key_str(key)?;
+        if key == "id" {
+            return Ok(self.id.into_py(py));
+        }
+        return borrow_entity(&self.stage, self.entity())?
+            .get_component(py, self.entity(), key)?
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()));
+    }
+
+    fn __setitem__(&mut self, key: &PyAny, value: &PyAny) -> PyResult<()> {
+        let key = key_str(key)?;
+        if key == "id" {
+            return Err(PyKeyError::new_err("The id of an entity can't be changed"));
+        }
+        let mut stage = borrow_entity(&self.stage, self.entity())?;
+        stage
+            .components
+            .entry(self.entity())
+            .or_default()
+            .insert(key.into(), value.into());
+        return Ok(());
+    }
+
+    fn __delitem__(&mut self, key: &PyAny) -> PyResult<()> {
+        let key = key_str(key)?;
+        if key == "id" {
+            return Err(PyKeyError::new_err("The id of an entity can't be removed"));
+        }
+        let mut stage = borrow_entity(&self.stage, self.entity())?;
+        let cached = stage
+            .components
+            .get_mut(&self.entity())
+            .and_then(|components| components.remove(key))
+            .is_some();
+        if !stage.registry.remove_component(self.entity(), key) && !cached {
+            return Err(PyKeyError::new_err(key.to_string()));
+        }
+        return Ok(());
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for PySceneEntity {
+    fn __contains__(&self, key: &PyAny) -> PyResult<bool> {
+        let key = match key.extract::<&str>() {
+            Ok(key) => key,
+            Err(_) => return Ok(false),
+        };
+        return Ok(key == "id" || borrow_entity(&self.stage, self.entity())?.has_component(self.entity(), key));
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for PySceneEntity {
+    fn __iter__(slf: PyRef<Self>) -> PyResult<PyObject> {
+        let keys = PyList::new(slf.py(), slf.keys()?);
+        return Ok(keys.call_method0("__iter__")?.into());
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PySceneEntity {
+    fn __repr__(&self) -> String {
+        return format!("SceneEntity({})", self.id);
+    }
+
+    fn __hash__(&self) -> u64 {
+        // The same hash as the id, like entity handles
+        return self.id;
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyResult<PyObject> {
+        let py = other.py();
+        return match entity_id(other) {
+            Some(other) => Ok(compare(self.id == other, op)?.into_py(py)),
+            None => Ok(py.NotImplemented()),
+        };
+    }
+}
+
+#[pyproto]
+impl PyNumberProtocol for PySceneEntity {
+    fn __index__(&self) -> u64 {
+        return self.id;
+    }
+}
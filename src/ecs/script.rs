@@ -0,0 +1,324 @@
+//! How the world loads python scripts, handles failing ones and runs the ones attached to entities
+use std::path::{Path, PathBuf};
+
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyList},
+};
+
+use crate::error::IslandError;
+
+use super::storage::Entity;
+
+/** What the world does when a script raises an exception */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptErrorPolicy {
+    /** Return the error, which stops the game loop */
+    Abort,
+    /** Log the error and disable the script, the rest of the game keeps running */
+    Disable,
+}
+
+/** A script that was disabled after it failed */
+#[derive(Debug, Clone)]
+pub struct ScriptFailure {
+    // The path of the script
+    pub path: String,
+    // The stage the script failed in
+    pub stage: String,
+    // The error the script failed with, including the python traceback
+    pub error: IslandError,
+}
+
+/** The stages that run the scripts attached to entities */
+pub(crate) const ENTITY_STAGES: [&str; 2] = ["update", "fixed_update"];
+
+/** The arguments of an event for the scene scripts and for the scripts of the entities it involves */
+pub(crate) struct ScriptEvent {
+    // The arguments after the context for the scene scripts
+    pub args: Vec<PyObject>,
+    // The entities whose scripts get the event, with the arguments after the context and the entity
+    pub entities: Vec<(u64, Vec<PyObject>)>,
+}
+
+/**
+The modules and per-instance state of the scripts attached to entities with a `"script"` component.
+Each module is loaded once and shared by its instances, the state of an instance lives here
+so it survives reloading the module.
+*/
+pub struct EntityScripts<'a> {
+    // The loaded modules by path
+    modules: hashbrown::HashMap<String, &'a PyModule>,
+    // The state dict of each script instance
+    states: hashbrown::HashMap<Entity, PyObject>,
+}
+
+impl<'a> EntityScripts<'a> {
+    /** Construct an empty set of entity scripts */
+    pub fn new() -> Self {
+        return Self {
+            modules: hashbrown::HashMap::new(),
+            states: hashbrown::HashMap::new(),
+        };
+    }
+
+    /** Get a loaded module by path */
+    pub fn module(&self, path: &str) -> Option<&'a PyModule> {
+        return self.modules.get(path).copied();
+    }
+
+    /** Add or replace a loaded module */
+    pub fn insert_module(&mut self, path: &str, module: &'a PyModule) {
+        self.modules.insert(path.into(), module);
+    }
+
+    /** Forget a loaded module, it is loaded again the next time it runs */
+    pub fn remove_module(&mut self, path: &str) {
+        self.modules.remove(path);
+    }
+
+    /** Forget all loaded modules, keeping the instance states */
+    pub fn clear_modules(&mut self) {
+        self.modules.clear();
+    }
+
+    /** Get the state dict of an instance, the flag is set if it was just created */
+    pub fn state(&mut self, py: Python, entity: Entity) -> (PyObject, bool) {
+        if let Some(state) = self.states.get(&entity) {
+            return (state.clone_ref(py), false);
+        }
+        let state: PyObject = PyDict::new(py).into();
+        self.states.insert(entity, state.clone_ref(py));
+        return (state, true);
+    }
+
+    /** Keep only the states of the instances the predicate holds for */
+    pub fn retain_states<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(Entity) -> bool,
+    {
+        self.states.retain(|entity, _| keep(*entity));
+    }
+
+    /** Forget all modules and instance states */
+    pub fn clear(&mut self) {
+        self.modules.clear();
+        self.states.clear();
+    }
+}
+
+impl Default for EntityScripts<'_> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/**
+Derive the module name of a script from its path relative to the scripts root,
+`enemies/boss.py` becomes `enemies.boss` and `enemies/__init__.py` becomes `enemies`.
+Scripts outside of the root are named after their file.
+*/
+pub fn module_name(root: &Path, path: &Path) -> String {
+    let relative = match (root.canonicalize(), path.canonicalize()) {
+        (Ok(root), Ok(path)) => path.strip_prefix(root).map(|path| path.to_path_buf()).ok(),
+        _ => None,
+    };
+    let relative = relative.unwrap_or_else(|| path.file_name().unwrap_or_default().into());
+
+    let mut parts = relative
+        .with_extension("")
+        .iter()
+        .map(|part| part.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    if parts.len() > 1 && parts.last().map(String::as_str) == Some("__init__") {
+        parts.pop();
+    }
+    return parts.join(".");
+}
+
+/**
+Run the code of a script as a module registered in `sys.modules` under its name.
+The package of the module is set from the name, so relative imports work.
+*/
+pub(crate) fn exec_module<'py>(py: Python<'py>, name: &str, path: &str, code: &str) -> PyResult<&'py PyModule> {
+    let module = PyModule::new(py, name)?;
+    module.setattr("__file__", path)?;
+    if Path::new(path).file_stem().map(|stem| stem == "__init__").unwrap_or(false) {
+        // A package's own module, its submodules are next to it
+        let directory = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+        module.setattr("__path__", vec![directory.to_string_lossy()])?;
+        module.setattr("__package__", name)?;
+    } else {
+        module.setattr("__package__", name.rsplit_once('.').map(|(package, _)| package).unwrap_or(""))?;
+    }
+
+    let modules = py.import("sys")?.getattr("modules")?;
+    modules.set_item(name, module)?;
+    let builtins = py.import("builtins")?;
+    let result = builtins
+        .getattr("compile")?
+        .call1((code, path, "exec"))
+        .and_then(|code| builtins.getattr("exec")?.call1((code, module.dict())));
+    if let Err(e) = result {
+        // Like a failed import, don't leave a half-run module behind
+        modules.call_method1("pop", (name, py.None()))?;
+        return Err(e);
+    }
+    return Ok(module);
+}
+
+/**
+Get the modules in `sys.modules` that were loaded from files under the scripts root, with their files.
+These are the scripts and the helper modules they import.
+*/
+pub(crate) fn root_modules(py: Python, root: &Path) -> PyResult<Vec<(String, PathBuf)>> {
+    let root = match root.canonicalize() {
+        Ok(root) => root,
+        Err(_) => return Ok(vec![]),
+    };
+    let modules = py.import("sys")?.getattr("modules")?.downcast::<PyDict>()?;
+    let mut result = vec![];
+    for (name, module) in modules.iter() {
+        // Built in modules have no file
+        let file = match module.getattr("__file__").and_then(|file| file.extract::<String>()) {
+            Ok(file) => file,
+            Err(_) => continue,
+        };
+        if Path::new(&file).canonicalize().map(|path| path.starts_with(&root)).unwrap_or(false) {
+            result.push((name.extract()?, PathBuf::from(file)));
+        }
+    }
+    return Ok(result);
+}
+
+/**
+Remove changed modules from `sys.modules`, with the modules that import them directly or through others,
+so they are imported again from their files.
+A module imports another if it holds that module, or a function or class defined in it.
+Returns the names of the removed modules, the scripts among them have to be run again.
+*/
+pub(crate) fn evict_modules(py: Python, modules: &[(String, PathBuf)], changed: &[String]) -> PyResult<Vec<String>> {
+    let sys_modules = py.import("sys")?.getattr("modules")?.downcast::<PyDict>()?;
+    let mut stale = changed.iter().cloned().collect::<hashbrown::HashSet<_>>();
+    loop {
+        let mut added = false;
+        for (name, _) in modules.iter() {
+            if stale.contains(name) {
+                continue;
+            }
+            let module = match sys_modules.get_item(name.as_str()) {
+                Some(module) => module,
+                None => continue,
+            };
+            let imports_stale = module.getattr("__dict__")?.downcast::<PyDict>()?.values().iter().any(|value| {
+                let source = match value.downcast::<PyModule>() {
+                    Ok(module) => module.name().map(String::from).ok(),
+                    Err(_) => value.getattr("__module__").and_then(|source| source.extract()).ok(),
+                };
+                return source.map(|source| stale.contains(&source)).unwrap_or(false);
+            });
+            if imports_stale {
+                stale.insert(name.clone());
+                added = true;
+            }
+        }
+        if !added {
+            break;
+        }
+    }
+
+    for name in stale.iter() {
+        sys_modules.call_method1("pop", (name, py.None()))?;
+    }
+    return Ok(stale.into_iter().collect());
+}
+
+/** Put the scripts root first on `sys.path`, replacing the previous root */
+pub(crate) fn set_python_path(py: Python, previous: Option<&Path>, root: &Path) -> PyResult<()> {
+    let sys_path = py.import("sys")?.getattr("path")?.downcast::<PyList>()?;
+    // Remove the previous root, and the new one so it is not on the path twice
+    for path in previous.into_iter().chain(Some(root)) {
+        let path = path.to_string_lossy();
+        let index = sys_path
+            .iter()
+            .position(|entry| entry.extract::<&str>().map(|entry| entry == path).unwrap_or(false));
+        if let Some(index) = index {
+            sys_path.del_item(index as isize)?;
+        }
+    }
+    sys_path.insert(0, root.to_string_lossy().as_ref())?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_names_follow_the_packages_under_the_root() {
+        let directory = std::env::temp_dir().join("ie_test_module_names");
+        let root = directory.join("scripts");
+        let files = [
+            "player.py",
+            "enemies/__init__.py",
+            "enemies/boss.py",
+            "enemies/bosses/__init__.py",
+            "enemies/bosses/dragon.py",
+            "__init__.py",
+        ];
+        for file in files.iter() {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        std::fs::write(directory.join("outside.py"), "").unwrap();
+
+        let name = |path: PathBuf| module_name(&root, &path);
+        assert_eq!(name(root.join("player.py")), "player");
+        assert_eq!(name(root.join("enemies/boss.py")), "enemies.boss");
+        assert_eq!(name(root.join("enemies/bosses/dragon.py")), "enemies.bosses.dragon");
+        assert_eq!(name(root.join("enemies/bosses/__init__.py")), "enemies.bosses");
+        assert_eq!(name(root.join("enemies/__init__.py")), "enemies");
+        // The root itself is not a package
+        assert_eq!(name(root.join("__init__.py")), "__init__");
+        // Paths are compared after resolving them
+        assert_eq!(name(root.join("enemies/../enemies/boss.py")), "enemies.boss");
+
+        // Scripts outside of the root or that don't exist are named after their file
+        assert_eq!(name(directory.join("outside.py")), "outside");
+        assert_eq!(name(root.join("enemies/missing.py")), "missing");
+        assert_eq!(module_name(&directory.join("missing_root"), &root.join("enemies/boss.py")), "boss");
+    }
+
+    #[test]
+    fn changed_modules_are_evicted_with_their_importers() {
+        let root = std::env::temp_dir().join("ie_test_evict_modules");
+        std::fs::create_dir_all(&root).unwrap();
+        let files = [
+            ("ie_evict_helper", "def speed():\n    return 1\n"),
+            ("ie_evict_user", "from ie_evict_helper import speed\n"),
+            ("ie_evict_indirect", "import ie_evict_user\n"),
+            ("ie_evict_other", "SPEED = 2\n"),
+        ];
+        for (name, code) in files.iter() {
+            std::fs::write(root.join(format!("{}.py", name)), code).unwrap();
+        }
+
+        Python::with_gil(|py| {
+            set_python_path(py, None, &root).unwrap();
+            py.import("ie_evict_indirect").unwrap();
+            py.import("ie_evict_other").unwrap();
+            let modules = root_modules(py, &root).unwrap();
+            let mut names = modules.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+            names.sort_unstable();
+            assert_eq!(names, ["ie_evict_helper", "ie_evict_indirect", "ie_evict_other", "ie_evict_user"]);
+
+            let mut stale = evict_modules(py, &modules, &["ie_evict_helper".to_string()]).unwrap();
+            stale.sort();
+            assert_eq!(stale, ["ie_evict_helper", "ie_evict_indirect", "ie_evict_user"]);
+            let sys_modules = py.import("sys").unwrap().getattr("modules").unwrap().downcast::<PyDict>().unwrap();
+            assert!(sys_modules.get_item("ie_evict_helper").is_none());
+            assert!(sys_modules.get_item("ie_evict_other").is_some());
+        });
+    }
+}
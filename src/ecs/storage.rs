@@ -0,0 +1,923 @@
+//! Sparse-set component storage for the world's entities
+use std::any::{Any, TypeId};
+
+use json::JsonValue;
+
+use crate::{
+    error::{ErrorKind, IslandError, IslandResult},
+    warn,
+};
+
+use super::components::{Animation, Collider, Component, Name, Position, Scale, Script, Sprite, Tag, Tilemap};
+
+/** The largest entity id, ids have to fit in an i64 to survive the conversions to python and back */
+pub const MAX_ENTITY_ID: u64 = i64::MAX as u64;
+
+/** A stable identifier for an entity, saved as the entity's "id" field */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity(u64);
+
+impl Entity {
+    /** Construct an entity handle from a raw id */
+    pub fn from_id(id: u64) -> Self {
+        return Self(id);
+    }
+
+    /** Get the raw id of the entity */
+    pub fn id(&self) -> u64 {
+        return self.0;
+    }
+}
+
+impl std::fmt::Display for Entity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/** Stores components of a single type packed in a dense array */
+pub struct SparseSet<T> {
+    sparse: hashbrown::HashMap<Entity, usize>,
+    dense: Vec<Entity>,
+    data: Vec<T>,
+}
+
+impl<T> SparseSet<T> {
+    /** Construct an empty sparse set */
+    pub fn new() -> Self {
+        return Self {
+            sparse: hashbrown::HashMap::new(),
+            dense: vec![],
+            data: vec![],
+        };
+    }
+
+    /** Insert a value for the entity, replacing the old one */
+    pub fn insert(&mut self, entity: Entity, value: T) -> Option<T> {
+        if let Some(index) = self.sparse.get(&entity) {
+            return Some(std::mem::replace(&mut self.data[*index], value));
+        }
+        self.sparse.insert(entity, self.dense.len());
+        self.dense.push(entity);
+        self.data.push(value);
+        return None;
+    }
+
+    /** Remove the value of the entity */
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = self.sparse.remove(&entity)?;
+        self.dense.swap_remove(index);
+        let value = self.data.swap_remove(index);
+        // Fix up the index of the entity that was moved into the hole
+        if let Some(moved) = self.dense.get(index) {
+            self.sparse.insert(*moved, index);
+        }
+        return Some(value);
+    }
+
+    /** Get the value of the entity */
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        return self.sparse.get(&entity).map(|index| &self.data[*index]);
+    }
+
+    /** Get the value of the entity mutably */
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        let index = *self.sparse.get(&entity)?;
+        return Some(&mut self.data[index]);
+    }
+
+    /** Check if the entity has a value */
+    pub fn contains(&self, entity: Entity) -> bool {
+        return self.sparse.contains_key(&entity);
+    }
+
+    /** Iterate over the values in storage order */
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        return self.dense.iter().copied().zip(self.data.iter());
+    }
+
+    /** Iterate mutably over the values in storage order */
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        return self.dense.iter().copied().zip(self.data.iter_mut());
+    }
+
+    /** Get the amount of stored values */
+    pub fn len(&self) -> usize {
+        return self.data.len();
+    }
+
+    /** Check if the set is empty */
+    pub fn is_empty(&self) -> bool {
+        return self.data.is_empty();
+    }
+
+    /** Remove all values */
+    pub fn clear(&mut self) {
+        self.sparse.clear();
+        self.dense.clear();
+        self.data.clear();
+    }
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/** Type-erased access to a component storage */
+trait ComponentStorage {
+    /** Check that the json is a valid component */
+    fn validate(&self, json: &JsonValue) -> IslandResult<()>;
+    /** Load the component of the entity from json */
+    fn load(&mut self, entity: Entity, json: &JsonValue) -> IslandResult<()>;
+    /** Save the component of the entity as json */
+    fn save(&self, entity: Entity) -> Option<JsonValue>;
+    /** Check if the entity has the component */
+    fn contains(&self, entity: Entity) -> bool;
+    /** Remove the component of the entity */
+    fn remove(&mut self, entity: Entity);
+    /** Remove all components */
+    fn clear(&mut self);
+    /** Construct an empty storage of the same component type */
+    fn new_empty(&self) -> Box<dyn ComponentStorage>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Component> ComponentStorage for SparseSet<T> {
+    fn validate(&self, json: &JsonValue) -> IslandResult<()> {
+        T::from_json(json).map_err(|e| {
+            return IslandError::with_source(
+                ErrorKind::SceneValidation,
+                format!("Invalid component \"{}\"", T::NAME),
+                e,
+            );
+        })?;
+        return Ok(());
+    }
+
+    fn load(&mut self, entity: Entity, json: &JsonValue) -> IslandResult<()> {
+        let component = T::from_json(json).map_err(|e| {
+            return IslandError::with_source(
+                ErrorKind::SceneValidation,
+                format!("Failed to load component \"{}\" of entity {}", T::NAME, entity),
+                e,
+            );
+        })?;
+        self.insert(entity, component);
+        return Ok(());
+    }
+
+    fn save(&self, entity: Entity) -> Option<JsonValue> {
+        return self.get(entity).map(|component| component.to_json());
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        return SparseSet::contains(self, entity);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        SparseSet::remove(self, entity);
+    }
+
+    fn clear(&mut self) {
+        SparseSet::clear(self);
+    }
+
+    fn new_empty(&self) -> Box<dyn ComponentStorage> {
+        return Box::new(SparseSet::<T>::new());
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
+}
+
+/** A set of components that can be fetched together from the registry */
+pub trait Query<'r> {
+    type Item;
+
+    /** Fetch the components of the entity, if it has all of them */
+    fn fetch(registry: &'r Registry, entity: Entity) -> Option<Self::Item>;
+}
+
+impl<'r, A: Component> Query<'r> for A {
+    type Item = &'r A;
+
+    fn fetch(registry: &'r Registry, entity: Entity) -> Option<Self::Item> {
+        return registry.get::<A>(entity);
+    }
+}
+
+macro_rules! impl_query {
+    ($($component:ident),+) => {
+        impl<'r, $($component: Component),+> Query<'r> for ($($component,)+) {
+            type Item = ($(&'r $component,)+);
+
+            fn fetch(registry: &'r Registry, entity: Entity) -> Option<Self::Item> {
+                return Some(($(registry.get::<$component>(entity)?,)+));
+            }
+        }
+    };
+}
+
+impl_query!(A);
+impl_query!(A, B);
+impl_query!(A, B, C);
+impl_query!(A, B, C, D);
+
+/** Holds the entities of the world and their components */
+pub struct Registry {
+    // The id the next spawned entity receives
+    next_id: u64,
+    // The alive entities in scene order, a despawned entity's place is taken by the last one
+    entities: Vec<Entity>,
+    // The index of each alive entity in the entities
+    indices: hashbrown::HashMap<Entity, usize>,
+    // The typed component storages
+    storages: Vec<Box<dyn ComponentStorage>>,
+    storage_types: hashbrown::HashMap<TypeId, usize>,
+    storage_names: Vec<&'static str>,
+    // The json components without a registered type
+    untyped: SparseSet<JsonValue>,
+}
+
+impl Registry {
+    /** Construct a registry with the builtin components registered */
+    pub fn new() -> Self {
+        let mut result = Self {
+            next_id: 0,
+            entities: vec![],
+            indices: hashbrown::HashMap::new(),
+            storages: vec![],
+            storage_types: hashbrown::HashMap::new(),
+            storage_names: vec![],
+            untyped: SparseSet::new(),
+        };
+        result.register::<Position>();
+        result.register::<Scale>();
+        result.register::<Sprite>();
+        result.register::<Name>();
+        result.register::<Tag>();
+        result.register::<Script>();
+        result.register::<Animation>();
+        result.register::<Tilemap>();
+        result.register::<Collider>();
+        return result;
+    }
+
+    /** Register a component type so it gets loaded from json into typed storage */
+    pub fn register<T: Component>(&mut self) {
+        if self.storage_types.contains_key(&TypeId::of::<T>()) {
+            return;
+        }
+        self.storage_types
+            .insert(TypeId::of::<T>(), self.storages.len());
+        self.storages.push(Box::new(SparseSet::<T>::new()));
+        self.storage_names.push(T::NAME);
+    }
+
+    /**
+    Construct a registry without entities that has the same component types registered.
+    Its ids continue after the ones handed out by this registry.
+    */
+    pub fn empty_like(&self) -> Self {
+        return Self {
+            next_id: self.next_id,
+            entities: vec![],
+            indices: hashbrown::HashMap::new(),
+            storages: self.storages.iter().map(|storage| storage.new_empty()).collect(),
+            storage_types: self.storage_types.clone(),
+            storage_names: self.storage_names.clone(),
+            untyped: SparseSet::new(),
+        };
+    }
+
+    /** Spawn an entity without any components */
+    pub fn spawn(&mut self) -> Entity {
+        let entity = Entity(self.next_id());
+        self.next_id = self.next_id.max(entity.0 + 1);
+        self.indices.insert(entity, self.entities.len());
+        self.entities.push(entity);
+        return entity;
+    }
+
+    /** Spawn an entity with the given id, falling back to a fresh id if it is taken or too large */
+    fn spawn_with_id(&mut self, id: u64) -> Entity {
+        let entity = Entity(id);
+        if id > MAX_ENTITY_ID {
+            warn!("Entity id {} is larger than {}, assigning a new one", id, MAX_ENTITY_ID);
+            return self.spawn();
+        }
+        if self.contains(entity) {
+            warn!("Entity id {} is already in use, assigning a new one", id);
+            return self.spawn();
+        }
+        self.next_id = self.next_id.max(id + 1);
+        self.indices.insert(entity, self.entities.len());
+        self.entities.push(entity);
+        return entity;
+    }
+
+    /**
+    Get the id the next spawned entity will receive.
+    Once an entity has the id `MAX_ENTITY_ID`, the lowest id that is not in use is handed out instead.
+    */
+    pub fn next_id(&self) -> u64 {
+        if self.next_id <= MAX_ENTITY_ID {
+            return self.next_id;
+        }
+        return (0..=MAX_ENTITY_ID).find(|id| !self.contains(Entity(*id))).unwrap_or(MAX_ENTITY_ID);
+    }
+
+    /** Make sure ids below `next_id` are never handed out, for ids reserved outside the registry */
+    pub(crate) fn reserve_ids(&mut self, next_id: u64) {
+        self.next_id = self.next_id.max(next_id.min(MAX_ENTITY_ID + 1));
+    }
+
+    /** Despawn an entity and remove its components, the last entity takes its place in the scene order */
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        let index = match self.indices.remove(&entity) {
+            Some(index) => index,
+            None => return false,
+        };
+        self.entities.swap_remove(index);
+        // Fix up the index of the entity that was moved into the hole
+        if let Some(moved) = self.entities.get(index) {
+            self.indices.insert(*moved, index);
+        }
+        for storage in self.storages.iter_mut() {
+            storage.remove(entity);
+        }
+        self.untyped.remove(entity);
+        return true;
+    }
+
+    /** Despawn every entity, ids are not reused afterwards */
+    pub fn clear(&mut self) {
+        self.entities.clear();
+        self.indices.clear();
+        for storage in self.storages.iter_mut() {
+            storage.clear();
+        }
+        self.untyped.clear();
+    }
+
+    /** Check if the entity is alive */
+    pub fn contains(&self, entity: Entity) -> bool {
+        return self.indices.contains_key(&entity);
+    }
+
+    /** Get the alive entities in scene order */
+    pub fn entities(&self) -> &[Entity] {
+        return &self.entities;
+    }
+
+    /** Get a handle to an alive entity */
+    pub fn entity(&self, entity: Entity) -> Option<EntityRef<'_>> {
+        if !self.contains(entity) {
+            return None;
+        }
+        return Some(EntityRef {
+            entity,
+            registry: self,
+        });
+    }
+
+    /** Get a mutable handle to an alive entity */
+    pub fn entity_mut(&mut self, entity: Entity) -> Option<EntityMut<'_>> {
+        if !self.contains(entity) {
+            return None;
+        }
+        return Some(EntityMut {
+            entity,
+            registry: self,
+        });
+    }
+
+    /** Find the first entity with the name */
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        return self
+            .query::<Name>()
+            .find(|(_, n)| n.0 == name)
+            .map(|(entity, _)| entity);
+    }
+
+    /** Find all entities with the tag, in scene order */
+    pub fn find_by_tag(&self, tag: &str) -> Vec<Entity> {
+        return self
+            .query::<Tag>()
+            .filter(|(_, t)| t.0 == tag)
+            .map(|(entity, _)| entity)
+            .collect();
+    }
+
+    /** Get the storage of a component type */
+    pub fn storage<T: Component>(&self) -> Option<&SparseSet<T>> {
+        let index = self.storage_types.get(&TypeId::of::<T>())?;
+        return self.storages[*index].as_any().downcast_ref();
+    }
+
+    /** Get the storage of a component type mutably, registering it if needed */
+    pub fn storage_mut<T: Component>(&mut self) -> &mut SparseSet<T> {
+        self.register::<T>();
+        let index = self.storage_types[&TypeId::of::<T>()];
+        return self.storages[index]
+            .as_any_mut()
+            .downcast_mut()
+            .expect("storage should match its type id");
+    }
+
+    /** Add a component to the entity */
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) {
+        self.storage_mut::<T>().insert(entity, component);
+    }
+
+    /** Remove a component from the entity */
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        return self.storage_mut::<T>().remove(entity);
+    }
+
+    /** Get a component of the entity */
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        return self.storage::<T>()?.get(entity);
+    }
+
+    /** Get a component of the entity mutably */
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        return self.storage_mut::<T>().get_mut(entity);
+    }
+
+    /** Get the json components of the entity that have no registered type */
+    pub fn untyped(&self, entity: Entity) -> Option<&JsonValue> {
+        return self.untyped.get(entity);
+    }
+
+    /** Iterate over the entities that have all the queried components, in scene order */
+    pub fn query<'r, Q: Query<'r>>(&'r self) -> impl Iterator<Item = (Entity, Q::Item)> + 'r {
+        return self
+            .entities
+            .iter()
+            .filter_map(move |entity| Q::fetch(self, *entity).map(|item| (*entity, item)));
+    }
+
+    /** Iterate mutably over a single component type */
+    pub fn query_mut<T: Component>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        return self.storage_mut::<T>().iter_mut();
+    }
+
+    /** Load an entity from its json object */
+    pub fn load_entity(&mut self, json: &JsonValue) -> IslandResult<Entity> {
+        if !json.is_object() {
+            return Err(IslandError::with_kind(ErrorKind::SceneValidation, format!(
+                "Entity has to be a json object: {}",
+                json
+            )));
+        }
+
+        // Keep the saved id so entities stay identifiable across loads
+        let entity = match json["id"].as_u64() {
+            Some(id) => self.spawn_with_id(id),
+            None => self.spawn(),
+        };
+
+        for (key, value) in json.entries() {
+            if key == "id" {
+                continue;
+            }
+            let result = match self.storage_index(key) {
+                Some(index) => self.storages[index].load(entity, value),
+                None => {
+                    self.untyped_mut(entity)[key] = value.clone();
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                self.despawn(entity);
+                return Err(e);
+            }
+        }
+
+        return Ok(entity);
+    }
+
+    /** Check a component by its json name, none if no component type is registered for it */
+    pub fn validate_component(&self, name: &str, json: &JsonValue) -> Option<IslandResult<()>> {
+        let index = self.storage_index(name)?;
+        return Some(self.storages[index].validate(json));
+    }
+
+    /** Get the json names of the components of the entity, the typed ones first */
+    pub fn component_names(&self, entity: Entity) -> Vec<&str> {
+        let mut names = self
+            .storage_names
+            .iter()
+            .zip(self.storages.iter())
+            .filter(|(_, storage)| storage.contains(entity))
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+        if let Some(untyped) = self.untyped.get(entity) {
+            names.extend(untyped.entries().map(|(name, _)| name));
+        }
+        return names;
+    }
+
+    /** Check if the entity has a component by its json name */
+    pub fn has_component(&self, entity: Entity, name: &str) -> bool {
+        return match self.storage_index(name) {
+            Some(index) => self.storages[index].contains(entity),
+            None => self.untyped.get(entity).map(|untyped| untyped.has_key(name)).unwrap_or(false),
+        };
+    }
+
+    /** Save a component of the entity by its json name */
+    pub fn save_component(&self, entity: Entity, name: &str) -> Option<JsonValue> {
+        return match self.storage_index(name) {
+            Some(index) => self.storages[index].save(entity),
+            None => self
+                .untyped
+                .get(entity)
+                .filter(|untyped| untyped.has_key(name))
+                .map(|untyped| untyped[name].clone()),
+        };
+    }
+
+    /** Load a component of an alive entity from json by its name, replacing the one it had */
+    pub fn load_component(&mut self, entity: Entity, name: &str, json: &JsonValue) -> IslandResult<()> {
+        if !self.contains(entity) {
+            return Err(IslandError::with_kind(ErrorKind::SceneValidation, format!(
+                "Failed to load component \"{}\", entity {} does not exist",
+                name, entity
+            )));
+        }
+        match self.storage_index(name) {
+            Some(index) => self.storages[index].load(entity, json)?,
+            None => self.untyped_mut(entity)[name] = json.clone(),
+        }
+        return Ok(());
+    }
+
+    /** Remove a component of the entity by its json name, returns false if the entity did not have it */
+    pub fn remove_component(&mut self, entity: Entity, name: &str) -> bool {
+        if !self.has_component(entity, name) {
+            return false;
+        }
+        match self.storage_index(name) {
+            Some(index) => self.storages[index].remove(entity),
+            None => {
+                self.untyped_mut(entity).remove(name);
+            }
+        }
+        return true;
+    }
+
+    /** Check that an entity json object would load without errors */
+    pub fn validate_entity(&self, json: &JsonValue) -> IslandResult<()> {
+        if !json.is_object() {
+            return Err(IslandError::with_kind(ErrorKind::SceneValidation, format!(
+                "Entity has to be a json object: {}",
+                json
+            )));
+        }
+        for (key, value) in json.entries() {
+            if let Some(index) = self.storage_index(key) {
+                self.storages[index].validate(value)?;
+            }
+        }
+        return Ok(());
+    }
+
+    /** Save an entity as a json object */
+    pub fn save_entity(&self, entity: Entity) -> JsonValue {
+        let mut json = json::object! { id: entity.id() };
+        for (name, storage) in self.storage_names.iter().zip(self.storages.iter()) {
+            if let Some(component) = storage.save(entity) {
+                json[*name] = component;
+            }
+        }
+        if let Some(untyped) = self.untyped.get(entity) {
+            for (key, value) in untyped.entries() {
+                json[key] = value.clone();
+            }
+        }
+        return json;
+    }
+
+    /** Replace all entities with the ones in the json array */
+    pub fn load_entities(&mut self, json: &JsonValue) -> IslandResult<()> {
+        self.clear();
+        for entity in json.members() {
+            self.load_entity(entity)?;
+        }
+        return Ok(());
+    }
+
+    /** Save all entities as a json array */
+    pub fn save_entities(&self) -> JsonValue {
+        let mut json = JsonValue::new_array();
+        for entity in self.entities.iter() {
+            // Pushing onto an array can't fail
+            let _ = json.push(self.save_entity(*entity));
+        }
+        return json;
+    }
+
+    /** Get the index of the storage of a component type by its json name */
+    fn storage_index(&self, name: &str) -> Option<usize> {
+        return self.storage_names.iter().position(|n| *n == name);
+    }
+
+    /** Get the untyped components of the entity, creating the object if needed */
+    fn untyped_mut(&mut self, entity: Entity) -> &mut JsonValue {
+        if !self.untyped.contains(entity) {
+            self.untyped.insert(entity, JsonValue::new_object());
+        }
+        return self.untyped.get_mut(entity).unwrap();
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/** A handle to an entity and its components */
+pub struct EntityRef<'r> {
+    entity: Entity,
+    registry: &'r Registry,
+}
+
+impl<'r> EntityRef<'r> {
+    /** Get the entity */
+    pub fn entity(&self) -> Entity {
+        return self.entity;
+    }
+
+    /** Get a component of the entity */
+    pub fn get<T: Component>(&self) -> Option<&'r T> {
+        return self.registry.get::<T>(self.entity);
+    }
+
+    /** Get the entity as a json object */
+    pub fn to_json(&self) -> JsonValue {
+        return self.registry.save_entity(self.entity);
+    }
+}
+
+/** A mutable handle to an entity and its components */
+pub struct EntityMut<'r> {
+    entity: Entity,
+    registry: &'r mut Registry,
+}
+
+impl<'r> EntityMut<'r> {
+    /** Get the entity */
+    pub fn entity(&self) -> Entity {
+        return self.entity;
+    }
+
+    /** Get a component of the entity */
+    pub fn get<T: Component>(&self) -> Option<&T> {
+        return self.registry.get::<T>(self.entity);
+    }
+
+    /** Get a component of the entity mutably */
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T> {
+        return self.registry.get_mut::<T>(self.entity);
+    }
+
+    /** Add a component to the entity */
+    pub fn insert<T: Component>(&mut self, component: T) -> &mut Self {
+        self.registry.insert(self.entity, component);
+        return self;
+    }
+
+    /** Remove a component from the entity */
+    pub fn remove<T: Component>(&mut self) -> Option<T> {
+        return self.registry.remove::<T>(self.entity);
+    }
+
+    /** Get the entity as a json object */
+    pub fn to_json(&self) -> JsonValue {
+        return self.registry.save_entity(self.entity);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use macroquad::prelude::{vec2, Vec2};
+
+    /** A component that is not registered by default, shared with the tests of other modules */
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct Health(pub i64);
+
+    impl Component for Health {
+        const NAME: &'static str = "health";
+
+        fn from_json(json: &JsonValue) -> IslandResult<Self> {
+            return json
+                .as_i64()
+                .map(Self)
+                .ok_or_else(|| IslandError::new(format!("Health has to be a whole number: {}", json)));
+        }
+
+        fn to_json(&self) -> JsonValue {
+            return self.0.into();
+        }
+    }
+
+    #[test]
+    fn spawned_entities_get_increasing_ids_in_scene_order() {
+        let mut registry = Registry::new();
+        let entities = (0..3).map(|_| registry.spawn()).collect::<Vec<_>>();
+        assert_eq!(entities.iter().map(Entity::id).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(registry.entities(), &entities[..]);
+        assert!(entities.iter().all(|entity| registry.contains(*entity)));
+    }
+
+    #[test]
+    fn despawning_removes_the_components_and_keeps_the_id_used() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn();
+        let other = registry.spawn();
+        registry.insert(entity, Position(vec2(1.0, 2.0)));
+        registry.insert(other, Position(vec2(3.0, 4.0)));
+
+        assert!(registry.despawn(entity));
+        assert!(!registry.despawn(entity));
+        assert!(!registry.contains(entity));
+        assert_eq!(registry.get::<Position>(entity), None);
+        // The entity that was moved into the hole of the storage keeps its component
+        assert_eq!(registry.get::<Position>(other), Some(&Position(vec2(3.0, 4.0))));
+        assert_eq!(registry.spawn().id(), 2);
+    }
+
+    #[test]
+    fn despawned_entities_are_replaced_by_the_last_one() {
+        let mut registry = Registry::new();
+        let entities = (0..4).map(|_| registry.spawn()).collect::<Vec<_>>();
+        assert!(registry.despawn(entities[1]));
+        assert_eq!(registry.entities(), &[entities[0], entities[3], entities[2]]);
+        // The moved entity can be despawned from its new place
+        assert!(registry.despawn(entities[3]));
+        assert_eq!(registry.entities(), &[entities[0], entities[2]]);
+        assert!(registry.despawn(entities[2]));
+        assert_eq!(registry.entities(), &[entities[0]]);
+        assert!(registry.contains(entities[0]));
+        assert!(!registry.contains(entities[3]));
+    }
+
+    #[test]
+    fn inserting_replaces_and_removing_returns_the_component() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn();
+        registry.insert(entity, Name("first".into()));
+        registry.insert(entity, Name("second".into()));
+        assert_eq!(registry.get::<Name>(entity), Some(&Name("second".into())));
+        assert_eq!(registry.remove::<Name>(entity), Some(Name("second".into())));
+        assert_eq!(registry.remove::<Name>(entity), None);
+
+        // Unregistered types are registered when they are first inserted
+        registry.insert(entity, Health(3));
+        registry.get_mut::<Health>(entity).unwrap().0 -= 1;
+        assert_eq!(registry.get::<Health>(entity), Some(&Health(2)));
+    }
+
+    #[test]
+    fn queries_yield_the_entities_with_all_components_in_scene_order() {
+        let mut registry = Registry::new();
+        let mut entities = vec![];
+        for index in 0..4 {
+            let entity = registry.spawn();
+            registry.insert(entity, Position(vec2(index as f32, 0.0)));
+            if index % 2 == 1 {
+                registry.insert(entity, Scale(Vec2::ONE));
+            }
+            entities.push(entity);
+        }
+        // Removing a component reorders the storage, the query still follows the scene
+        registry.remove::<Position>(entities[0]);
+        registry.insert(entities[0], Position(Vec2::ZERO));
+
+        let positioned = registry.query::<Position>().map(|(entity, _)| entity).collect::<Vec<_>>();
+        assert_eq!(positioned, entities);
+        let scaled = registry
+            .query::<(Position, Scale)>()
+            .map(|(entity, (position, _))| (entity, position.0.x))
+            .collect::<Vec<_>>();
+        assert_eq!(scaled, [(entities[1], 1.0), (entities[3], 3.0)]);
+
+        for (_, position) in registry.query_mut::<Position>() {
+            position.0.y = 5.0;
+        }
+        assert!(registry.query::<Position>().all(|(_, position)| position.0.y == 5.0));
+    }
+
+    #[test]
+    fn entities_survive_the_json_round_trip() {
+        let mut registry = Registry::new();
+        registry.register::<Health>();
+        let json = json::array![
+            { id: 3, position: { x: 1, y: 2 }, name: "player", health: 10, custom: { a: [1, 2] } },
+            { id: 7, tag: "enemy", sprite: { texture: "enemy", dest_size: { x: 16, y: 16 } } },
+        ];
+        registry.load_entities(&json).unwrap();
+        assert_eq!(registry.get::<Health>(Entity::from_id(3)), Some(&Health(10)));
+        assert_eq!(registry.untyped(Entity::from_id(3)).unwrap()["custom"], json::object! { a: [1, 2] });
+
+        let saved = registry.save_entities();
+        let mut loaded = Registry::new();
+        loaded.register::<Health>();
+        loaded.load_entities(&saved).unwrap();
+        assert_eq!(loaded.save_entities(), saved);
+        assert_eq!(loaded.entities(), [Entity::from_id(3), Entity::from_id(7)]);
+        assert_eq!(loaded.find_by_name("player"), Some(Entity::from_id(3)));
+        assert_eq!(loaded.find_by_tag("enemy"), [Entity::from_id(7)]);
+    }
+
+    #[test]
+    fn invalid_entities_are_not_left_half_loaded() {
+        let mut registry = Registry::new();
+        assert!(registry.load_entity(&json::object! { name: "ok", position: "nowhere" }).is_err());
+        assert!(registry.entities().is_empty());
+        assert_eq!(registry.find_by_name("ok"), None);
+        assert!(registry.load_entity(&json::array![]).is_err());
+    }
+
+    #[test]
+    fn empty_registries_keep_the_component_types_and_ids() {
+        let mut registry = Registry::new();
+        registry.register::<Health>();
+        registry.load_entity(&json::object! { id: 5, health: 1 }).unwrap();
+
+        let mut empty = registry.empty_like();
+        assert!(empty.entities().is_empty());
+        assert!(empty.validate_component(Health::NAME, &JsonValue::from("full")).unwrap().is_err());
+        let entity = empty.load_entity(&json::object! { health: 2 }).unwrap();
+        assert_eq!(entity.id(), 6);
+        assert_eq!(empty.get::<Health>(entity), Some(&Health(2)));
+        assert!(empty.untyped(entity).is_none());
+    }
+
+    #[test]
+    fn components_are_edited_by_name_in_place() {
+        let mut registry = Registry::new();
+        let entity = registry.load_entity(&json::object! { id: 2, name: "player", custom: 1 }).unwrap();
+        let other = registry.spawn();
+
+        registry.load_component(entity, Position::NAME, &json::object! { x: 1, y: 2 }).unwrap();
+        registry.load_component(entity, "custom", &JsonValue::from(5)).unwrap();
+        assert!(registry.load_component(entity, Position::NAME, &JsonValue::from("nowhere")).is_err());
+        assert!(registry.load_component(Entity::from_id(9), Name::NAME, &JsonValue::from("ghost")).is_err());
+        assert_eq!(registry.get::<Position>(entity), Some(&Position(vec2(1.0, 2.0))));
+        assert_eq!(registry.save_component(entity, "custom"), Some(JsonValue::from(5)));
+        assert_eq!(registry.component_names(entity), ["position", "name", "custom"]);
+
+        assert!(registry.remove_component(entity, Name::NAME));
+        assert!(registry.remove_component(entity, "custom"));
+        assert!(!registry.remove_component(entity, "custom"));
+        assert!(!registry.has_component(entity, Name::NAME));
+        assert_eq!(registry.save_component(entity, "custom"), None);
+        // The other entities and the ids are left alone
+        assert_eq!(registry.entities(), [entity, other]);
+        assert_eq!(registry.find_by_name("player"), None);
+    }
+
+    #[test]
+    fn load_entity_keeps_ids_and_continues_after_them() {
+        let mut registry = Registry::new();
+        let entity = registry.load_entity(&json::object! { id: 41 }).unwrap();
+        assert_eq!(entity.id(), 41);
+        assert_eq!(registry.spawn().id(), 42);
+    }
+
+    #[test]
+    fn load_entity_assigns_a_new_id_when_the_id_is_too_large() {
+        let mut registry = Registry::new();
+        for id in [MAX_ENTITY_ID + 1, u64::MAX].iter() {
+            let entity = registry.load_entity(&json::object! { id: *id }).unwrap();
+            assert!(entity.id() <= MAX_ENTITY_ID);
+        }
+        assert_eq!(registry.next_id(), 2);
+
+        let entity = registry.load_entity(&json::object! { id: MAX_ENTITY_ID }).unwrap();
+        assert_eq!(entity.id(), MAX_ENTITY_ID);
+
+        // The ids are used up, so spawning falls back to the lowest free one
+        registry.despawn(Entity::from_id(0));
+        assert_eq!(registry.next_id(), 0);
+        assert_eq!(registry.spawn().id(), 0);
+        assert_eq!(registry.spawn().id(), 2);
+        registry.reserve_ids(u64::MAX);
+        assert_eq!(registry.next_id(), 3);
+        let saved = registry.save_entities();
+        assert!(saved.members().all(|entity| entity["id"].as_u64().unwrap() <= MAX_ENTITY_ID));
+    }
+}
@@ -0,0 +1,183 @@
+//! Advancing the sprite animations
+use crate::{
+    assets::{animations, AnimationClip, AnimationMode},
+    ecs::{
+        components::{Animation, Sprite},
+        storage::{Entity, Registry},
+    },
+};
+
+/**
+Advance the animations by the frame time and set the source rect of their sprites to the current frame.
+Returns the entities whose clip ended with the name of the clip, a looping clip ends every time it starts over.
+Animations of clips that don't exist are left alone.
+*/
+pub(crate) fn advance_animations(registry: &mut Registry, frame_time: f32) -> Vec<(Entity, String)> {
+    let mut finished = vec![];
+    let mut frames = vec![];
+    for (entity, animation) in registry.query_mut::<Animation>() {
+        let clip = match animations().get(&animation.clip) {
+            Some(clip) if !clip.frames.is_empty() => clip,
+            _ => continue,
+        };
+        if advance(animation, clip, frame_time) {
+            finished.push((entity, animation.clip.clone()));
+        }
+        frames.push((entity, clip.frames[animation.frame]));
+    }
+
+    for (entity, frame) in frames {
+        if let Some(sprite) = registry.get_mut::<Sprite>(entity) {
+            sprite.source_rec = Some(frame);
+        }
+    }
+    return finished;
+}
+
+/** Advance an animation of the clip by the frame time, returns if the clip ended */
+fn advance(animation: &mut Animation, clip: &AnimationClip, frame_time: f32) -> bool {
+    let last = clip.frames.len() - 1;
+    // The clip could have gotten shorter since the frame was set
+    animation.frame = animation.frame.min(last);
+    if !animation.playing {
+        return false;
+    }
+
+    let mut ended = false;
+    let frame_duration = 1.0 / clip.fps;
+    animation.time += frame_time;
+    while animation.playing && animation.time >= frame_duration {
+        animation.time -= frame_duration;
+        match clip.mode {
+            AnimationMode::Loop if animation.frame >= last => {
+                animation.frame = 0;
+                ended = true;
+            }
+            AnimationMode::Once if animation.frame >= last => {
+                animation.playing = false;
+                animation.time = 0.0;
+                ended = true;
+            }
+            AnimationMode::Loop | AnimationMode::Once => animation.frame += 1,
+            AnimationMode::PingPong if last == 0 => ended = true,
+            AnimationMode::PingPong if animation.backwards => {
+                animation.frame = animation.frame.saturating_sub(1);
+                if animation.frame == 0 {
+                    animation.backwards = false;
+                    ended = true;
+                }
+            }
+            AnimationMode::PingPong => {
+                animation.frame += 1;
+                if animation.frame >= last {
+                    animation.backwards = true;
+                }
+            }
+        }
+    }
+    return ended;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::Rect;
+
+    /** A clip of `frames` frames at 4 frames per second, so a frame takes an exact quarter second */
+    fn clip(frames: usize, mode: AnimationMode) -> AnimationClip {
+        return AnimationClip {
+            frames: (0..frames).map(|i| Rect::new(i as f32 * 16.0, 0.0, 16.0, 16.0)).collect(),
+            fps: 4.0,
+            mode,
+        };
+    }
+
+    /** Advance a frame at a time, returns the frame and if the clip ended after each one */
+    fn run(animation: &mut Animation, clip: &AnimationClip, frames: usize) -> Vec<(usize, bool)> {
+        return (0..frames)
+            .map(|_| {
+                let ended = advance(animation, clip, 0.25);
+                return (animation.frame, ended);
+            })
+            .collect();
+    }
+
+    #[test]
+    fn loop_clips_start_over_and_end_every_time() {
+        let clip = clip(3, AnimationMode::Loop);
+        let mut animation = Animation::new("walk");
+        assert_eq!(run(&mut animation, &clip, 4), vec![(1, false), (2, false), (0, true), (1, false)]);
+
+        // Less than a frame keeps the frame, long frames skip several
+        assert!(!advance(&mut animation, &clip, 0.125));
+        assert_eq!((animation.frame, animation.time), (1, 0.125));
+        assert!(advance(&mut animation, &clip, 0.625));
+        assert_eq!((animation.frame, animation.time), (1, 0.0));
+        assert!(animation.playing);
+    }
+
+    #[test]
+    fn once_clips_stop_on_their_last_frame() {
+        let clip = clip(3, AnimationMode::Once);
+        let mut animation = Animation::new("attack");
+        assert_eq!(run(&mut animation, &clip, 3), vec![(1, false), (2, false), (2, true)]);
+        assert!(!animation.playing);
+        assert_eq!(animation.time, 0.0);
+
+        // A stopped clip does not end again
+        assert_eq!(run(&mut animation, &clip, 2), vec![(2, false), (2, false)]);
+    }
+
+    #[test]
+    fn ping_pong_clips_turn_around_and_end_back_at_the_start() {
+        let clip = clip(3, AnimationMode::PingPong);
+        let mut animation = Animation::new("bob");
+        assert_eq!(
+            run(&mut animation, &clip, 6),
+            vec![(1, false), (2, false), (1, false), (0, true), (1, false), (2, false)]
+        );
+        assert!(animation.backwards);
+
+        // A single frame ends every frame
+        let single = self::clip(1, AnimationMode::PingPong);
+        let mut animation = Animation::new("idle");
+        assert_eq!(run(&mut animation, &single, 2), vec![(0, true), (0, true)]);
+    }
+
+    #[test]
+    fn frames_past_a_clip_that_got_shorter_are_clamped() {
+        let clip = clip(3, AnimationMode::Loop);
+        let mut animation = Animation::new("walk");
+        animation.frame = 7;
+        animation.playing = false;
+        assert!(!advance(&mut animation, &clip, 0.25));
+        assert_eq!(animation.frame, 2);
+
+        // A playing one goes on from the last frame
+        animation.frame = 7;
+        animation.playing = true;
+        assert_eq!(run(&mut animation, &clip, 2), vec![(0, true), (1, false)]);
+    }
+
+    #[test]
+    fn sprites_show_the_current_frame_and_ended_clips_are_returned() {
+        animations().insert("ie_test_blink".into(), clip(2, AnimationMode::Loop));
+        let mut registry = Registry::new();
+        let sprite = json::object! { texture: "hero", dest_size: { x: 16, y: 16 } };
+        let blinking = registry
+            .load_entity(&json::object! { sprite: sprite.clone(), animation: { clip: "ie_test_blink" } })
+            .unwrap();
+        let missing = registry
+            .load_entity(&json::object! { sprite: sprite, animation: { clip: "ie_test_missing" } })
+            .unwrap();
+
+        assert!(advance_animations(&mut registry, 0.25).is_empty());
+        assert_eq!(registry.get::<Sprite>(blinking).unwrap().source_rec, Some(Rect::new(16.0, 0.0, 16.0, 16.0)));
+        assert_eq!(advance_animations(&mut registry, 0.25), vec![(blinking, "ie_test_blink".to_string())]);
+        assert_eq!(registry.get::<Sprite>(blinking).unwrap().source_rec, Some(Rect::new(0.0, 0.0, 16.0, 16.0)));
+
+        // Animations of clips that don't exist are left alone
+        assert_eq!(registry.get::<Animation>(missing).unwrap().frame, 0);
+        assert_eq!(registry.get::<Sprite>(missing).unwrap().source_rec, None);
+    }
+}
@@ -0,0 +1,665 @@
+//! Finding the overlaps of colliders, for contact events and the collision queries of scripts
+use macroquad::prelude::{vec2, Rect, Vec2};
+
+use crate::ecs::{
+    components::{Collider, ColliderShape, Position, Scale},
+    storage::{Entity, Registry},
+};
+
+/** The most cells a collider is put in, larger ones are checked against everything */
+const MAX_CELLS: i64 = 64;
+
+/** A collider placed in the world */
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+    Rect(Rect),
+    Circle { center: Vec2, radius: f32 },
+    Polygon(Vec<Vec2>),
+}
+
+impl Shape {
+    /** Place a collider at an entity's position and scale */
+    pub(crate) fn new(position: Vec2, scale: Vec2, collider: &Collider) -> Self {
+        let origin = position + collider.offset * scale;
+        return match &collider.shape {
+            ColliderShape::Rect { size } => {
+                // A negative scale flips the box to the other side of the position
+                let (a, b) = (origin, origin + *size * scale);
+                let min = a.min(b);
+                Shape::Rect(Rect::new(min.x, min.y, (b.x - a.x).abs(), (b.y - a.y).abs()))
+            }
+            ColliderShape::Circle { radius } => Shape::Circle {
+                center: origin,
+                radius: radius * scale.x.abs().max(scale.y.abs()),
+            },
+            ColliderShape::Polygon { points } => {
+                Shape::Polygon(points.iter().map(|point| origin + *point * scale).collect())
+            }
+        };
+    }
+
+    /** Get the box around the shape */
+    pub(crate) fn bounds(&self) -> Rect {
+        return match self {
+            Shape::Rect(rect) => *rect,
+            Shape::Circle { center, radius } => {
+                Rect::new(center.x - radius, center.y - radius, radius * 2.0, radius * 2.0)
+            }
+            Shape::Polygon(points) => {
+                let min = points.iter().fold(vec2(f32::INFINITY, f32::INFINITY), |min, point| min.min(*point));
+                let max = points.iter().fold(vec2(f32::NEG_INFINITY, f32::NEG_INFINITY), |max, point| max.max(*point));
+                Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+            }
+        };
+    }
+
+    /** Check if the point is inside the shape or on its edge */
+    pub(crate) fn contains(&self, point: Vec2) -> bool {
+        return match self {
+            Shape::Rect(rect) => {
+                point.x >= rect.x && point.x <= rect.x + rect.w && point.y >= rect.y && point.y <= rect.y + rect.h
+            }
+            Shape::Circle { center, radius } => point.distance_squared(*center) <= radius * radius,
+            Shape::Polygon(points) => {
+                // Count the edges a ray to the right crosses
+                let mut inside = false;
+                for (a, b) in edges(points) {
+                    if (a.y > point.y) != (b.y > point.y) && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        };
+    }
+
+    /** Check if the shapes overlap, shapes that only touch don't */
+    pub(crate) fn overlaps(&self, other: &Shape) -> bool {
+        return match (self, other) {
+            (Shape::Rect(a), Shape::Rect(b)) => {
+                a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+            }
+            (Shape::Circle { center: a, radius: ra }, Shape::Circle { center: b, radius: rb }) => {
+                a.distance_squared(*b) < (ra + rb) * (ra + rb)
+            }
+            (Shape::Rect(rect), Shape::Circle { center, radius })
+            | (Shape::Circle { center, radius }, Shape::Rect(rect)) => {
+                let closest = center.max(rect.point()).min(rect.point() + rect.size());
+                closest.distance_squared(*center) < radius * radius
+            }
+            (Shape::Circle { center, radius }, shape) | (shape, Shape::Circle { center, radius }) => {
+                polygon_overlaps_circle(&shape.points(), *center, *radius)
+            }
+            (a, b) => polygons_overlap(&a.points(), &b.points()),
+        };
+    }
+
+    /**
+    Find where a ray hits the shape, with the direction normalized.
+    Returns the distance and the normal of the edge it hit, a ray starting inside hits at 0 with a zero normal.
+    */
+    pub(crate) fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<(f32, Vec2)> {
+        if self.contains(origin) {
+            return Some((0.0, Vec2::ZERO));
+        }
+        let hit = match self {
+            Shape::Rect(rect) => raycast_rect(*rect, origin, direction),
+            Shape::Circle { center, radius } => {
+                // Solve |origin + direction * t - center| = radius for the nearest t
+                let offset = origin - *center;
+                let b = offset.dot(direction);
+                let c = offset.length_squared() - radius * radius;
+                let discriminant = b * b - c;
+                if b > 0.0 || discriminant < 0.0 {
+                    None
+                } else {
+                    let distance = -b - discriminant.sqrt();
+                    Some((distance, (origin + direction * distance - *center).normalize_or_zero()))
+                }
+            }
+            Shape::Polygon(points) => edges(points)
+                .filter_map(|(a, b)| {
+                    let distance = raycast_segment(origin, direction, a, b)?;
+                    let mut normal = vec2(b.y - a.y, a.x - b.x).normalize_or_zero();
+                    if normal.dot(direction) > 0.0 {
+                        normal = -normal;
+                    }
+                    return Some((distance, normal));
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0)),
+        };
+        // Adding zero turns the negative zeros of the normals into zeros
+        return hit
+            .filter(|(distance, _)| *distance <= max_distance)
+            .map(|(distance, normal)| (distance, normal + Vec2::ZERO));
+    }
+
+    /** Get the corners of a rect or polygon */
+    fn points(&self) -> Vec<Vec2> {
+        return match self {
+            Shape::Rect(rect) => vec![
+                rect.point(),
+                vec2(rect.x + rect.w, rect.y),
+                rect.point() + rect.size(),
+                vec2(rect.x, rect.y + rect.h),
+            ],
+            Shape::Polygon(points) => points.clone(),
+            Shape::Circle { center, .. } => vec![*center],
+        };
+    }
+}
+
+/** Iterate the edges of a polygon, including the one closing it */
+fn edges(points: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    return points.iter().zip(points.iter().cycle().skip(1)).map(|(a, b)| (*a, *b));
+}
+
+/** Project points onto an axis, returning the smallest and largest value */
+fn project(points: &[Vec2], axis: Vec2) -> (f32, f32) {
+    return points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), point| {
+        let value = point.dot(axis);
+        return (min.min(value), max.max(value));
+    });
+}
+
+/** Check if two convex polygons overlap, looking for an edge normal that separates them */
+fn polygons_overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    return edges(a).chain(edges(b)).all(|(start, end)| {
+        let axis = vec2(end.y - start.y, start.x - end.x);
+        let (a_min, a_max) = project(a, axis);
+        let (b_min, b_max) = project(b, axis);
+        return a_max > b_min && b_max > a_min;
+    });
+}
+
+/** Check if a convex polygon overlaps a circle */
+fn polygon_overlaps_circle(points: &[Vec2], center: Vec2, radius: f32) -> bool {
+    let closest = points
+        .iter()
+        .copied()
+        .min_by(|a, b| a.distance_squared(center).total_cmp(&b.distance_squared(center)));
+    let closest = match closest {
+        Some(closest) => closest,
+        None => return false,
+    };
+    // The edge normals and the axis from the closest corner to the center can separate them
+    let axes = edges(points).map(|(start, end)| vec2(end.y - start.y, start.x - end.x));
+    return axes.chain(std::iter::once(center - closest)).all(|axis| {
+        let axis = axis.normalize_or_zero();
+        if axis == Vec2::ZERO {
+            return true;
+        }
+        let (min, max) = project(points, axis);
+        let center = center.dot(axis);
+        return max > center - radius && center + radius > min;
+    });
+}
+
+/** Find where a ray enters a rect with the slab method, returning the distance and the normal of the side */
+fn raycast_rect(rect: Rect, origin: Vec2, direction: Vec2) -> Option<(f32, Vec2)> {
+    let (mut near, mut far) = (f32::NEG_INFINITY, f32::INFINITY);
+    let mut normal = Vec2::ZERO;
+    for (axis, min, size) in [(Vec2::X, rect.x, rect.w), (Vec2::Y, rect.y, rect.h)].iter() {
+        let (start, step) = (origin.dot(*axis), direction.dot(*axis));
+        if step == 0.0 {
+            if start < *min || start > min + size {
+                return None;
+            }
+            continue;
+        }
+        let (mut enter, mut exit) = ((min - start) / step, (min + size - start) / step);
+        if enter > exit {
+            std::mem::swap(&mut enter, &mut exit);
+        }
+        if enter > near {
+            near = enter;
+            normal = -*axis * step.signum();
+        }
+        far = far.min(exit);
+    }
+    if near > far || near < 0.0 {
+        return None;
+    }
+    return Some((near, normal));
+}
+
+/** Find the distance along a ray to where it crosses a segment */
+fn raycast_segment(origin: Vec2, direction: Vec2, a: Vec2, b: Vec2) -> Option<f32> {
+    let edge = b - a;
+    let denominator = direction.perp_dot(edge);
+    if denominator == 0.0 {
+        return None;
+    }
+    let offset = a - origin;
+    let distance = offset.perp_dot(edge) / denominator;
+    let along = offset.perp_dot(direction) / denominator;
+    if distance >= 0.0 && (0.0..=1.0).contains(&along) {
+        return Some(distance);
+    }
+    return None;
+}
+
+/** Where a ray hit a collider */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RayHit {
+    pub entity: Entity,
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub distance: f32,
+}
+
+/**
+The colliders of the world in a spatial hash.
+Each collider is put in the grid cells its bounds cover, so only colliders sharing a cell are compared.
+*/
+#[derive(Debug, Default)]
+pub(crate) struct Colliders {
+    // The placed colliders in scene order
+    shapes: Vec<(Entity, Shape)>,
+    cell_size: f32,
+    // The indices of the colliders in each cell
+    cells: hashbrown::HashMap<(i64, i64), Vec<usize>>,
+    // The colliders covering too many cells, they are in no cell
+    large: Vec<usize>,
+}
+
+impl Colliders {
+    /**
+    Place the colliders of the entities with a position, scaled by their scale if they have one.
+    The cells are twice the average collider size, so most colliders are in a few cells.
+    */
+    pub(crate) fn new(registry: &Registry) -> Self {
+        let shapes = registry
+            .query::<(Position, Collider)>()
+            .map(|(entity, (position, collider))| {
+                let scale = registry.get::<Scale>(entity).map(|scale| scale.0).unwrap_or(Vec2::ONE);
+                return (entity, Shape::new(position.0, scale, collider));
+            })
+            .collect::<Vec<_>>();
+        let average = shapes
+            .iter()
+            .map(|(_, shape)| {
+                let bounds = shape.bounds();
+                return bounds.w.max(bounds.h);
+            })
+            .filter(|size| size.is_finite())
+            .sum::<f32>()
+            / shapes.len().max(1) as f32;
+
+        let mut result = Self {
+            shapes,
+            cell_size: (average * 2.0).max(1.0),
+            ..Default::default()
+        };
+        for index in 0..result.shapes.len() {
+            match result.cells_of(result.shapes[index].1.bounds()) {
+                Some(cells) => {
+                    for cell in cells {
+                        result.cells.entry(cell).or_default().push(index);
+                    }
+                }
+                None => result.large.push(index),
+            }
+        }
+        return result;
+    }
+
+    /** Get the cells a box covers, none if it covers too many */
+    fn cells_of(&self, bounds: Rect) -> Option<Vec<(i64, i64)>> {
+        let min = (bounds.point() / self.cell_size).floor();
+        let max = ((bounds.point() + bounds.size()) / self.cell_size).floor();
+        if !(min.is_finite() && max.is_finite()) {
+            return None;
+        }
+        let (min_x, min_y, max_x, max_y) = (min.x as i64, min.y as i64, max.x as i64, max.y as i64);
+        if (max_x - min_x + 1) * (max_y - min_y + 1) > MAX_CELLS {
+            return None;
+        }
+        return Some((min_y..=max_y).flat_map(|y| (min_x..=max_x).map(move |x| (x, y))).collect());
+    }
+
+    /** Get the indices of the colliders that might overlap the box, in scene order */
+    fn candidates(&self, bounds: Rect) -> Vec<usize> {
+        let mut result = self.large.clone();
+        match self.cells_of(bounds) {
+            Some(cells) => {
+                for cell in cells {
+                    result.extend(self.cells.get(&cell).into_iter().flatten());
+                }
+            }
+            None => result = (0..self.shapes.len()).collect(),
+        }
+        result.sort_unstable();
+        result.dedup();
+        return result;
+    }
+
+    /** Get the pairs of overlapping colliders, the entity with the lower id first, sorted */
+    pub(crate) fn contacts(&self) -> Vec<(Entity, Entity)> {
+        let mut contacts = vec![];
+        for (index, (entity, shape)) in self.shapes.iter().enumerate() {
+            for other in self.candidates(shape.bounds()) {
+                // Each pair is compared once, from the collider that comes first
+                if other <= index {
+                    continue;
+                }
+                let (other_entity, other_shape) = &self.shapes[other];
+                if shape.overlaps(other_shape) {
+                    contacts.push((*entity.min(other_entity), *entity.max(other_entity)));
+                }
+            }
+        }
+        contacts.sort_unstable();
+        return contacts;
+    }
+
+    /** Get the entities whose collider contains the point, in scene order */
+    pub(crate) fn overlap_point(&self, point: Vec2) -> Vec<Entity> {
+        return self
+            .candidates(Rect::new(point.x, point.y, 0.0, 0.0))
+            .into_iter()
+            .filter(|index| self.shapes[*index].1.contains(point))
+            .map(|index| self.shapes[index].0)
+            .collect();
+    }
+
+    /** Get the entities whose collider overlaps the rect, in scene order */
+    pub(crate) fn overlap_rect(&self, rect: Rect) -> Vec<Entity> {
+        let query = Shape::Rect(rect);
+        return self
+            .candidates(rect)
+            .into_iter()
+            .filter(|index| self.shapes[*index].1.overlaps(&query))
+            .map(|index| self.shapes[index].0)
+            .collect();
+    }
+
+    /**
+    Find the first collider a ray hits within the distance, the direction doesn't have to be normalized.
+    The cells are walked along the ray, so only the colliders near it are tested.
+    */
+    pub(crate) fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<RayHit> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec2::ZERO || self.shapes.is_empty() || !origin.is_finite() || max_distance.is_nan() {
+            return None;
+        }
+        let mut best: Option<(usize, f32, Vec2)> = None;
+        let test = |index: usize, best: &mut Option<(usize, f32, Vec2)>| {
+            let limit = best.map(|(_, distance, _)| distance).unwrap_or(max_distance);
+            if let Some((distance, normal)) = self.shapes[index].1.raycast(origin, direction, limit) {
+                if best.map(|(best_index, best_distance, _)| {
+                    return distance < best_distance || (distance == best_distance && index < best_index);
+                }) != Some(false)
+                {
+                    *best = Some((index, distance, normal));
+                }
+            }
+        };
+        for index in self.large.iter() {
+            test(*index, &mut best);
+        }
+
+        let bounds = self.cells.keys().fold((i64::MAX, i64::MAX, i64::MIN, i64::MIN), |bounds, (x, y)| {
+            return (bounds.0.min(*x), bounds.1.min(*y), bounds.2.max(*x), bounds.3.max(*y));
+        });
+        if let Some((enter, exit)) = self.clip_to_cells(bounds, origin, direction, max_distance) {
+            // Walk the cells the ray passes through from where it enters them, until it leaves them behind
+            let start = origin + direction * enter;
+            // The start is on the bounds, keep rounding from moving it off them
+            let (mut cell_x, mut cell_y) = (
+                ((start.x / self.cell_size).floor() as i64).clamp(bounds.0, bounds.2),
+                ((start.y / self.cell_size).floor() as i64).clamp(bounds.1, bounds.3),
+            );
+            let step = (direction.x.signum() as i64, direction.y.signum() as i64);
+            let boundary = |cell: i64, start: f32, direction: f32| {
+                if direction == 0.0 {
+                    return f32::INFINITY;
+                }
+                let edge = if direction > 0.0 { cell + 1 } else { cell } as f32 * self.cell_size;
+                return enter + (edge - start) / direction;
+            };
+            let delta = vec2(self.cell_size / direction.x.abs(), self.cell_size / direction.y.abs());
+            let mut next = vec2(boundary(cell_x, start.x, direction.x), boundary(cell_y, start.y, direction.y));
+            let mut travelled = enter;
+            loop {
+                if let Some(indices) = self.cells.get(&(cell_x, cell_y)) {
+                    for index in indices {
+                        test(*index, &mut best);
+                    }
+                }
+                // A hit before the next cell can't be beaten by colliders further along
+                if best.map(|(_, distance, _)| distance <= travelled.max(next.x.min(next.y))) == Some(true) {
+                    break;
+                }
+                travelled = next.x.min(next.y);
+                // A distance that is not finite never gets past the exit, so it has to stop the walk too
+                if travelled > exit || !travelled.is_finite() {
+                    break;
+                }
+                if next.x < next.y {
+                    cell_x += step.0;
+                    next.x += delta.x;
+                } else {
+                    cell_y += step.1;
+                    next.y += delta.y;
+                }
+                let outside = (step.0 < 0 && cell_x < bounds.0)
+                    || (step.1 < 0 && cell_y < bounds.1)
+                    || (step.0 > 0 && cell_x > bounds.2)
+                    || (step.1 > 0 && cell_y > bounds.3)
+                    || (step.0 == 0 && (cell_x < bounds.0 || cell_x > bounds.2))
+                    || (step.1 == 0 && (cell_y < bounds.1 || cell_y > bounds.3));
+                if outside {
+                    break;
+                }
+            }
+        }
+
+        return best.map(|(index, distance, normal)| RayHit {
+            entity: self.shapes[index].0,
+            point: origin + direction * distance,
+            normal,
+            distance,
+        });
+    }
+
+    /**
+    Get the distances along a ray where it enters and leaves the cells within the bounds, clipped to the distance.
+    None if the ray misses them, so a ray far from the colliders doesn't walk the empty cells on its way.
+    */
+    fn clip_to_cells(
+        &self,
+        bounds: (i64, i64, i64, i64),
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+    ) -> Option<(f32, f32)> {
+        if bounds.0 > bounds.2 || bounds.1 > bounds.3 {
+            return None;
+        }
+        let min = vec2(bounds.0 as f32, bounds.1 as f32) * self.cell_size;
+        let max = vec2((bounds.2 + 1) as f32, (bounds.3 + 1) as f32) * self.cell_size;
+        let (mut enter, mut exit) = (0.0f32, max_distance);
+        for (origin, direction, min, max) in [(origin.x, direction.x, min.x, max.x), (origin.y, direction.y, min.y, max.y)] {
+            if direction == 0.0 {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let (a, b) = ((min - origin) / direction, (max - origin) / direction);
+            enter = enter.max(a.min(b));
+            exit = exit.min(a.max(b));
+        }
+        if enter > exit {
+            return None;
+        }
+        return Some((enter, exit));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Shape {
+        return Shape::Rect(Rect::new(x, y, w, h));
+    }
+
+    fn circle(x: f32, y: f32, radius: f32) -> Shape {
+        return Shape::Circle {
+            center: vec2(x, y),
+            radius,
+        };
+    }
+
+    /** A diamond with its corners a distance of 1 from the center */
+    fn diamond(x: f32, y: f32) -> Shape {
+        return Shape::Polygon(vec![vec2(x, y - 1.0), vec2(x + 1.0, y), vec2(x, y + 1.0), vec2(x - 1.0, y)]);
+    }
+
+    /** The polygon covering the same square as `rect(0.0, 0.0, 2.0, 2.0)` */
+    fn square() -> Shape {
+        return Shape::Polygon(vec![vec2(0.0, 0.0), vec2(2.0, 0.0), vec2(2.0, 2.0), vec2(0.0, 2.0)]);
+    }
+
+    #[test]
+    fn each_pair_of_shapes_overlaps() {
+        let pairs = [
+            (rect(0.0, 0.0, 2.0, 2.0), rect(1.0, 1.0, 2.0, 2.0)),
+            (circle(0.0, 0.0, 1.0), circle(1.5, 0.0, 1.0)),
+            (rect(0.0, 0.0, 2.0, 2.0), circle(2.5, 1.0, 1.0)),
+            (rect(0.0, 0.0, 2.0, 2.0), diamond(2.5, 1.0)),
+            (circle(0.0, 0.0, 1.0), diamond(1.5, 0.0)),
+            (diamond(0.0, 0.0), diamond(1.5, 0.0)),
+        ];
+        for (a, b) in pairs.iter() {
+            assert!(a.overlaps(b), "{:?} should overlap {:?}", a, b);
+            assert!(b.overlaps(a), "{:?} should overlap {:?}", b, a);
+        }
+    }
+
+    #[test]
+    fn each_pair_of_shapes_can_miss() {
+        let pairs = [
+            (rect(0.0, 0.0, 2.0, 2.0), rect(3.0, 0.0, 2.0, 2.0)),
+            (circle(0.0, 0.0, 1.0), circle(2.5, 0.0, 1.0)),
+            // The corner of the rect is outside the circle, while their bounds overlap
+            (rect(0.0, 0.0, 2.0, 2.0), circle(2.8, 2.8, 1.0)),
+            (rect(0.0, 0.0, 2.0, 2.0), diamond(3.5, 1.0)),
+            (circle(0.0, 0.0, 1.0), diamond(1.6, 1.6)),
+            (diamond(0.0, 0.0), diamond(1.6, 1.6)),
+        ];
+        for (a, b) in pairs.iter() {
+            assert!(!a.overlaps(b), "{:?} should miss {:?}", a, b);
+            assert!(!b.overlaps(a), "{:?} should miss {:?}", b, a);
+        }
+    }
+
+    #[test]
+    fn shapes_that_only_touch_do_not_overlap() {
+        let pairs = [
+            (rect(0.0, 0.0, 2.0, 2.0), rect(2.0, 0.0, 2.0, 2.0)),
+            (circle(0.0, 0.0, 1.0), circle(2.0, 0.0, 1.0)),
+            (rect(0.0, 0.0, 2.0, 2.0), circle(3.0, 1.0, 1.0)),
+            (rect(0.0, 0.0, 2.0, 2.0), diamond(3.0, 1.0)),
+            (circle(0.0, 0.0, 1.0), diamond(2.0, 0.0)),
+            (diamond(0.0, 0.0), diamond(2.0, 0.0)),
+        ];
+        for (a, b) in pairs.iter() {
+            assert!(!a.overlaps(b), "{:?} should only touch {:?}", a, b);
+        }
+        // Points on the edge are inside
+        assert!(rect(0.0, 0.0, 2.0, 2.0).contains(vec2(2.0, 1.0)));
+        assert!(circle(0.0, 0.0, 1.0).contains(vec2(0.0, 1.0)));
+    }
+
+    #[test]
+    fn shapes_are_placed_by_position_scale_and_offset() {
+        let collider = Collider {
+            shape: ColliderShape::Rect { size: vec2(2.0, 1.0) },
+            offset: vec2(1.0, 0.0),
+        };
+        assert_eq!(Shape::new(vec2(10.0, 0.0), vec2(2.0, 1.0), &collider), rect(12.0, 0.0, 4.0, 1.0));
+        // A negative scale flips the box
+        assert_eq!(Shape::new(vec2(10.0, 0.0), vec2(-1.0, 1.0), &collider), rect(7.0, 0.0, 2.0, 1.0));
+
+        let collider = Collider {
+            shape: ColliderShape::Circle { radius: 1.0 },
+            offset: Vec2::ZERO,
+        };
+        assert_eq!(Shape::new(Vec2::ZERO, vec2(1.0, -3.0), &collider), circle(0.0, 0.0, 3.0));
+    }
+
+    #[test]
+    fn rays_hit_the_near_side_of_each_shape() {
+        let origin = vec2(-5.0, 1.0);
+        let hits = [
+            (rect(0.0, 0.0, 2.0, 2.0), 5.0),
+            (circle(1.0, 1.0, 1.0), 5.0),
+            (square(), 5.0),
+        ];
+        for (shape, distance) in hits.iter() {
+            let (hit, normal) = shape.raycast(origin, Vec2::X, f32::INFINITY).unwrap();
+            assert!((hit - distance).abs() < 1e-5, "{:?} was hit at {}", shape, hit);
+            assert_eq!(normal, -Vec2::X, "{:?} was hit with the normal {}", shape, normal);
+        }
+    }
+
+    #[test]
+    fn rays_starting_inside_hit_right_away() {
+        for shape in [rect(0.0, 0.0, 2.0, 2.0), circle(1.0, 1.0, 1.0), square()].iter() {
+            assert_eq!(shape.raycast(vec2(1.0, 1.0), Vec2::Y, 10.0), Some((0.0, Vec2::ZERO)));
+        }
+    }
+
+    #[test]
+    fn rays_miss_shapes_behind_beside_or_too_far() {
+        for shape in [rect(0.0, 0.0, 2.0, 2.0), circle(1.0, 1.0, 1.0), square()].iter() {
+            // Pointing away
+            assert_eq!(shape.raycast(vec2(-5.0, 1.0), -Vec2::X, f32::INFINITY), None);
+            // Passing beside
+            assert_eq!(shape.raycast(vec2(-5.0, 5.0), Vec2::X, f32::INFINITY), None);
+            // Stopping short
+            assert_eq!(shape.raycast(vec2(-5.0, 1.0), Vec2::X, 4.0), None);
+        }
+    }
+
+    #[test]
+    fn colliders_find_contacts_points_and_the_nearest_ray_hit() {
+        let mut registry = Registry::new();
+        let mut spawn = |x: f32, shape: ColliderShape| {
+            let entity = registry.spawn();
+            registry.insert(entity, Position(vec2(x, 0.0)));
+            registry.insert(entity, Collider {
+                shape,
+                offset: Vec2::ZERO,
+            });
+            return entity;
+        };
+        let far = spawn(20.0, ColliderShape::Rect { size: vec2(2.0, 2.0) });
+        let near = spawn(10.0, ColliderShape::Rect { size: vec2(2.0, 2.0) });
+        let touching = spawn(12.5, ColliderShape::Circle { radius: 0.5 });
+        let overlapping = spawn(11.0, ColliderShape::Circle { radius: 0.5 });
+        let colliders = Colliders::new(&registry);
+
+        assert_eq!(colliders.contacts(), [(near, overlapping)]);
+        assert!(colliders.overlap_point(vec2(12.5, 0.0)).contains(&touching));
+        assert_eq!(colliders.overlap_point(vec2(11.2, 0.1)), [near, overlapping]);
+        assert_eq!(colliders.overlap_rect(Rect::new(19.0, 0.0, 2.0, 2.0)), [far]);
+
+        let hit = colliders.raycast(vec2(0.0, 1.0), vec2(2.0, 0.0), f32::INFINITY).unwrap();
+        assert_eq!((hit.entity, hit.point, hit.normal, hit.distance), (near, vec2(10.0, 1.0), -Vec2::X, 10.0));
+        let hit = colliders.raycast(vec2(30.0, 1.0), -Vec2::X, f32::INFINITY).unwrap();
+        assert_eq!(hit.entity, far);
+        assert_eq!(colliders.raycast(vec2(0.0, 5.0), Vec2::X, f32::INFINITY), None);
+        assert_eq!(colliders.raycast(vec2(0.0, 1.0), Vec2::X, 9.0), None);
+
+        // Rays from far away skip the empty cells on their way, rays that can't be walked hit nothing
+        let hit = colliders.raycast(vec2(1e7, 1.0), -Vec2::X, f32::INFINITY).unwrap();
+        assert_eq!(hit.entity, far);
+        assert_eq!(colliders.raycast(vec2(-1e6, -1e6), vec2(1.0, 1.0), f32::INFINITY), None);
+        assert_eq!(colliders.raycast(vec2(f32::NAN, 0.0), Vec2::X, f32::INFINITY), None);
+        assert_eq!(colliders.raycast(vec2(0.0, 1.0), Vec2::X, f32::NAN), None);
+    }
+}
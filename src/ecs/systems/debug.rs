@@ -1,15 +1,123 @@
-use crate::{ecs::World, error::IslandResult};
-use macroquad::prelude::{is_key_pressed, KeyCode};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    assets::{reload_sprite, sprite_files},
+    ecs::{
+        components::Script,
+        script::{evict_modules, module_name, root_modules},
+        World,
+    },
+    error,
+    error::IslandResult,
+};
+use macroquad::prelude::KeyCode;
 
 /** Handles scene/script reloading */
 pub(crate) fn reload_systems(world: &mut World) -> IslandResult<()> {
     // Reload the scripts
-    if is_key_pressed(KeyCode::F4) {
+    if world.input.is_key_pressed(KeyCode::F4) {
         world.reload_scripts()?;
     }
     // Reload the scene
-    if is_key_pressed(KeyCode::F5) {
+    if world.input.is_key_pressed(KeyCode::F5) {
         world.reload_scene()?;
     }
+    // Reload the files that changed on disk
+    hot_reload(world);
     return Ok(());
 }
+
+/**
+Reload the scene, script and sprite files that changed on disk.
+Only changed scripts are reloaded so the scene keeps its state,
+a changed scene or prefab file loads the scene file again.
+The modules under the scripts root are watched too, a changed one is imported again by the scripts that use it.
+Errors are logged instead of returned, a file saved half-way should not stop the game.
+*/
+fn hot_reload(world: &mut World) {
+    if !world.file_watcher.due(world.time.frame_time()) {
+        return;
+    }
+
+    // Watch the files the world is currently made of
+    let mut scripts = world.scene["scripts"]
+        .members()
+        .filter_map(|script| script.as_str())
+        .map(String::from)
+        .collect::<Vec<_>>();
+    for (_, script) in world.registry.query::<Script>() {
+        if !scripts.contains(&script.0) {
+            scripts.push(script.0.clone());
+        }
+    }
+    // Looking through sys.modules is slow, the modules only change when a script is run
+    if world.script_modules.is_none() {
+        let modules = root_modules(world.py, world.scripts_root()).unwrap_or_else(|e| {
+            error!("Failed to find the script modules to hot reload: {}", e);
+            return vec![];
+        });
+        world.script_modules = Some(modules);
+    }
+    let modules = world.script_modules.clone().unwrap_or_default();
+    let mut files = vec![];
+    files.extend(world.scene_path.clone());
+    files.extend(world.prefabs.paths().map(PathBuf::from));
+    files.extend(scripts.iter().map(PathBuf::from));
+    files.extend(modules.iter().map(|(_, path)| path.clone()));
+    files.extend(sprite_files().values().map(|(path, _)| PathBuf::from(path)));
+    world.file_watcher.set_files(files);
+
+    let mut reload_scene = false;
+    let mut changed_scripts = vec![];
+    let mut changed_modules = vec![];
+    for path in world.file_watcher.changed() {
+        let is_prefab = world.prefabs.paths().any(|prefab| Path::new(prefab) == path);
+        if world.scene_path.as_ref() == Some(&path) || is_prefab {
+            reload_scene = true;
+            continue;
+        }
+        changed_scripts.extend(scripts.iter().filter(|script| Path::new(script) == path).cloned());
+        changed_modules.extend(modules.iter().filter(|(_, file)| *file == path).map(|(name, _)| name.clone()));
+        let sprites = sprite_files()
+            .iter()
+            .filter(|(_, (sprite_path, _))| Path::new(sprite_path) == path)
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        for sprite in sprites {
+            if let Err(e) = reload_sprite(&sprite) {
+                error!("Failed to hot reload sprite: {:#}", e);
+            }
+        }
+    }
+
+    // Forget the changed modules and their importers, then run the scripts among them again
+    if !changed_modules.is_empty() {
+        match evict_modules(world.py, &modules, &changed_modules) {
+            Ok(stale) => {
+                let root = world.scripts_root().to_path_buf();
+                let importers = scripts
+                    .iter()
+                    .filter(|script| stale.contains(&module_name(&root, Path::new(script))))
+                    .filter(|script| !changed_scripts.contains(script))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                changed_scripts.extend(importers);
+            }
+            Err(e) => error!("Failed to hot reload modules: {}", e),
+        }
+    }
+    for script in changed_scripts {
+        if let Err(e) = world.reload_script(&script) {
+            error!("Failed to hot reload script: {:#}", e);
+        }
+    }
+
+    if reload_scene {
+        if let Some(path) = world.scene_path.clone() {
+            world.prefabs.clear();
+            if let Err(e) = world.load_scene(path) {
+                error!("Failed to hot reload scene: {:#}", e);
+            }
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use json::JsonValue;
-use macroquad::prelude::{Rect, Vec2};
+use macroquad::prelude::{Color, Rect, Vec2};
 
 use crate::prelude::{IslandError, IslandResult};
 
+pub mod animation;
+pub mod collision;
 pub mod debug;
 pub mod render;
 
@@ -12,16 +14,18 @@ pub trait JsonConvert {
     fn as_vec2(&self) -> IslandResult<Vec2>;
     /** Get the value as a macroquad::math::Rect */
     fn as_rect(&self) -> IslandResult<Rect>;
+    /** Get the value as a macroquad::color::Color, alpha is 1 if it is not set */
+    fn as_color(&self) -> IslandResult<Color>;
 }
 
 impl JsonConvert for JsonValue {
     fn as_vec2(&self) -> IslandResult<Vec2> {
         let x = self["x"]
             .as_f32()
-            .ok_or(IslandError::new(format!("Failed to convert: {} to vec2", self.to_string())))?;
+            .ok_or(IslandError::new(format!("Failed to convert: {} to vec2", self)))?;
         let y = self["y"]
             .as_f32()
-            .ok_or(IslandError::new(format!("Failed to convert: {} to vec2", self.to_string())))?;
+            .ok_or(IslandError::new(format!("Failed to convert: {} to vec2", self)))?;
 
         return Ok(Vec2::new(x, y));
     }
@@ -29,17 +33,88 @@ impl JsonConvert for JsonValue {
     fn as_rect(&self) -> IslandResult<Rect> {
         let x = self["x"]
             .as_f32()
-            .ok_or(IslandError::new(format!("Failed to convert: {} to rect", self.to_string())))?;
+            .ok_or(IslandError::new(format!("Failed to convert: {} to rect", self)))?;
         let y = self["y"]
             .as_f32()
-            .ok_or(IslandError::new(format!("Failed to convert: {} to rect", self.to_string())))?;
+            .ok_or(IslandError::new(format!("Failed to convert: {} to rect", self)))?;
         let w = self["w"]
             .as_f32()
-            .ok_or(IslandError::new(format!("Failed to convert: {} to rect", self.to_string())))?;
+            .ok_or(IslandError::new(format!("Failed to convert: {} to rect", self)))?;
         let h = self["h"]
             .as_f32()
-            .ok_or(IslandError::new(format!("Failed to convert: {} to rect", self.to_string())))?;
+            .ok_or(IslandError::new(format!("Failed to convert: {} to rect", self)))?;
 
         return Ok(Rect::new(x, y, w, h));
     }
+
+    fn as_color(&self) -> IslandResult<Color> {
+        let r = self["r"]
+            .as_f32()
+            .ok_or(IslandError::new(format!("Failed to convert: {} to color", self)))?;
+        let g = self["g"]
+            .as_f32()
+            .ok_or(IslandError::new(format!("Failed to convert: {} to color", self)))?;
+        let b = self["b"]
+            .as_f32()
+            .ok_or(IslandError::new(format!("Failed to convert: {} to color", self)))?;
+        let a = if self.has_key("a") {
+            self["a"]
+                .as_f32()
+                .ok_or(IslandError::new(format!("Failed to convert: {} to color", self)))?
+        } else {
+            1.0
+        };
+
+        return Ok(Color::new(r, g, b, a));
+    }
+}
+
+/** Convert a f32 into a json number without picking up f64 rounding noise */
+pub fn f32_to_json(value: f32) -> JsonValue {
+    return value.to_string().parse::<f64>().unwrap_or(0.0).into();
+}
+
+/** Convert a glam::Vec2 into a json object */
+pub fn vec2_to_json(value: Vec2) -> JsonValue {
+    return json::object! {
+        x: f32_to_json(value.x),
+        y: f32_to_json(value.y),
+    };
+}
+
+/** Convert a macroquad::math::Rect into a json object */
+pub fn rect_to_json(value: Rect) -> JsonValue {
+    return json::object! {
+        x: f32_to_json(value.x),
+        y: f32_to_json(value.y),
+        w: f32_to_json(value.w),
+        h: f32_to_json(value.h),
+    };
+}
+
+/** Convert a macroquad::color::Color into a json object */
+pub fn color_to_json(value: Color) -> JsonValue {
+    return json::object! {
+        r: f32_to_json(value.r),
+        g: f32_to_json(value.g),
+        b: f32_to_json(value.b),
+        a: f32_to_json(value.a),
+    };
+}
+
+/** Copy a json value with the keys of every object sorted */
+pub fn sorted_json(value: &JsonValue) -> JsonValue {
+    return match value {
+        JsonValue::Object(object) => {
+            let mut entries = object.iter().collect::<Vec<_>>();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let mut result = JsonValue::new_object();
+            for (key, value) in entries {
+                result[key] = sorted_json(value);
+            }
+            result
+        }
+        JsonValue::Array(array) => JsonValue::Array(array.iter().map(sorted_json).collect()),
+        _ => value.clone(),
+    };
 }
@@ -1,67 +1,199 @@
-use crate::{assets::get_sprite, error::IslandResult};
-use json::JsonValue;
+use crate::{
+    assets::{get_sprite, tilesets, SpriteHandle, Tileset},
+    ecs::{
+        components::{
+            Camera, Position, Scale, Sprite, TileLayer, Tilemap, TILE_FLIPPED_DIAGONALLY,
+            TILE_FLIPPED_HORIZONTALLY, TILE_FLIPPED_VERTICALLY,
+        },
+        storage::{Entity, Registry},
+    },
+    error::IslandResult,
+};
 use macroquad::prelude::*;
-
-use super::JsonConvert;
+use std::{cmp::Ordering, f32::consts::FRAC_PI_2};
 
 /** Render the transforms */
-pub(crate) fn render_transforms(scene: &JsonValue) -> IslandResult<()> {
-    for entity in scene["entities"].members() {
-        if entity.has_key("position") && entity.has_key("scale") {
-            // Get the components
-            let position = &entity["position"];
-            let scale = &entity["scale"];
-
-            draw_rectangle(
-                position["x"]
-                    .as_f32()
-                    .expect("position's x value should be a f32"),
-                position["y"]
-                    .as_f32()
-                    .expect("position's y value should be a f32"),
-                16.0 * scale["x"]
-                    .as_f32()
-                    .expect("scale's x value should be a f32"),
-                16.0 * scale["y"]
-                    .as_f32()
-                    .expect("scale's y value should be a f32"),
-                GRAY,
-            );
+pub(crate) fn render_transforms(registry: &Registry) -> IslandResult<()> {
+    for (_, (position, scale)) in registry.query::<(Position, Scale)>() {
+        draw_rectangle(
+            position.0.x,
+            position.0.y,
+            16.0 * scale.0.x,
+            16.0 * scale.0.y,
+            GRAY,
+        );
+    }
+
+    return Ok(());
+}
+
+/** Something drawn in layer order, placed where it is drawn */
+enum Drawable<'a> {
+    /** A sprite drawn at its top left with its scaled size, rotated around the pivot at its position */
+    Sprite {
+        sprite: &'a Sprite,
+        top_left: Vec2,
+        size: Vec2,
+        pivot: Vec2,
+    },
+    /** A tile layer with the top left of the map at the position */
+    Tiles {
+        tilemap: &'a Tilemap,
+        layer: &'a TileLayer,
+        position: Vec2,
+        scale: Vec2,
+    },
+}
+
+/**
+Render the sprites and the tile layers of tilemaps.
+They are drawn by layer, tile layers first. Within a layer sprites are drawn by their y position if `y_sort` is set,
+otherwise in entity order. Only the tiles inside the view are drawn.
+Movement from the last fixed update is interpolated by alpha, so sprites move smoothly between fixed updates.
+*/
+pub(crate) fn render_sprites(
+    registry: &Registry,
+    fixed_motion: &hashbrown::HashMap<Entity, Vec2>,
+    alpha: f32,
+    y_sort: bool,
+    view: Rect,
+) -> IslandResult<()> {
+    for drawable in draw_order(registry, fixed_motion, alpha, y_sort) {
+        match drawable {
+            Drawable::Sprite {
+                sprite,
+                top_left,
+                size,
+                pivot,
+            } => draw_sprite(sprite, top_left, size, pivot),
+            Drawable::Tiles {
+                tilemap,
+                layer,
+                position,
+                scale,
+            } => draw_tile_layer(position, scale, tilemap, layer, view),
         }
     }
 
     return Ok(());
 }
 
-/** Render the sprites */
-pub(crate) fn render_sprites(scene: &JsonValue) -> IslandResult<()> {
-    for entity in scene["entities"].members() {
-        if entity.has_key("position") && entity.has_key("scale") && entity.has_key("sprite") {
-            // Get the components
-            let position = entity["position"].as_vec2()?;
-            let scale = entity["scale"].as_vec2()?;
-            let sprite = &entity["sprite"];
-
-            let name = sprite["texture"].as_str().unwrap();
-            let dest = sprite["dest_size"].as_vec2()?;
-            let source = sprite["source_rec"].as_rect();
-            let flip_x = sprite["flip_x"].as_bool().unwrap();
-            let flip_y = sprite["flip_y"].as_bool().unwrap();
-            let texture = get_sprite(name);
+/** Get the sprites and visible tile layers in the order they are drawn in, placed where they are drawn */
+fn draw_order<'r>(
+    registry: &'r Registry,
+    fixed_motion: &hashbrown::HashMap<Entity, Vec2>,
+    alpha: f32,
+    y_sort: bool,
+) -> Vec<Drawable<'r>> {
+    let interpolate = |entity: Entity, position: &Position| {
+        return match fixed_motion.get(&entity) {
+            Some(motion) => position.0 - *motion * (1.0 - alpha),
+            None => position.0,
+        };
+    };
+    // Each drawable with its layer and the y it is sorted by within the layer, tile layers go first
+    let mut drawables = vec![];
+    for (entity, (position, scale, tilemap)) in registry.query::<(Position, Scale, Tilemap)>() {
+        let position = interpolate(entity, position);
+        for layer in tilemap.layers.iter().filter(|layer| layer.visible) {
+            let drawable = Drawable::Tiles {
+                tilemap,
+                layer,
+                position,
+                scale: scale.0,
+            };
+            drawables.push((layer.layer, f32::NEG_INFINITY, drawable));
+        }
+    }
+    for (entity, (position, scale, sprite)) in registry.query::<(Position, Scale, Sprite)>() {
+        let position = interpolate(entity, position);
+        let size = sprite.dest_size * scale.0;
+        let drawable = Drawable::Sprite {
+            sprite,
+            // The pivot is placed at the position
+            top_left: position - sprite.pivot * size,
+            size,
+            pivot: position,
+        };
+        let sort_y = if y_sort { position.y } else { f32::NEG_INFINITY };
+        drawables.push((sprite.layer, sort_y, drawable));
+    }
+    // The sort is stable, so sprites on the same layer and row keep their entity order
+    drawables.sort_by(|a, b| {
+        return a.0.cmp(&b.0).then(a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+    });
+    return drawables.into_iter().map(|(_, _, drawable)| drawable).collect();
+}
+
+/** Draw a sprite at its top left, rotated around the pivot */
+fn draw_sprite(sprite: &Sprite, top_left: Vec2, size: Vec2, pivot: Vec2) {
+    let handle = get_sprite(&sprite.texture);
+    draw_texture_ex(
+        handle.texture,
+        top_left.x,
+        top_left.y,
+        sprite.color,
+        DrawTextureParams {
+            dest_size: Some(size),
+            source: Some(handle.source(sprite.source_rec)),
+            rotation: sprite.rotation,
+            flip_x: sprite.flip_x,
+            flip_y: sprite.flip_y,
+            pivot: Some(pivot),
+        },
+    );
+}
 
+/**
+Draw the tiles of a layer that are inside the view, with the top left of the map at the position.
+Tiles of tilesets missing from the asset descriptor are not drawn.
+*/
+fn draw_tile_layer(position: Vec2, scale: Vec2, tilemap: &Tilemap, layer: &TileLayer, view: Rect) {
+    let size = tilemap.tile_size * scale;
+    if size.x <= 0.0 || size.y <= 0.0 {
+        return;
+    }
+    // The range of tiles the view covers
+    let first = ((view.point() - position) / size).floor().max(Vec2::ZERO);
+    let last = ((view.point() + view.size() - position) / size).ceil().max(Vec2::ZERO);
+    let columns = first.x as usize..(last.x as usize).min(tilemap.width);
+    let rows = first.y as usize..(last.y as usize).min(tilemap.height);
+    let color = Color::new(1.0, 1.0, 1.0, layer.opacity);
+
+    // Look each tileset up once, the tiles of a tileset share its texture
+    let mut resolved: hashbrown::HashMap<&str, Option<(SpriteHandle, &Tileset)>> = hashbrown::HashMap::new();
+    for row in rows {
+        for column in columns.clone() {
+            // Layers built in rust can be shorter than the map
+            let index = row.checked_mul(tilemap.width).and_then(|index| index.checked_add(column));
+            let gid = match index.and_then(|index| layer.data.get(index)) {
+                Some(gid) => *gid,
+                None => continue,
+            };
+            let (tileset, id) = match tilemap.resolve(gid) {
+                Some(tile) => tile,
+                None => continue,
+            };
+            let (handle, tileset) = match resolved.entry(tileset.name.as_str()).or_insert_with(|| {
+                return tilesets()
+                    .get(&tileset.name)
+                    .map(|tileset| (get_sprite(&tileset.sprite), tileset));
+            }) {
+                Some(found) => *found,
+                None => continue,
+            };
+
+            let top_left = position + vec2(column as f32, row as f32) * size;
+            let (rotation, flip_x, flip_y) = tile_transform(gid);
             draw_texture_ex(
-                *texture,
-                position.x,
-                position.y,
-                WHITE,
+                handle.texture,
+                top_left.x,
+                top_left.y,
+                color,
                 DrawTextureParams {
-                    dest_size: Some(dest * scale),
-                    source: if let Ok(source) = source {
-                        Some(source)
-                    } else {
-                        None
-                    },
-                    rotation: 0.0,
+                    dest_size: Some(size),
+                    source: Some(handle.source(Some(tileset.source_rect(id)))),
+                    rotation,
                     flip_x,
                     flip_y,
                     pivot: None,
@@ -69,26 +201,142 @@ pub(crate) fn render_sprites(scene: &JsonValue) -> IslandResult<()> {
             );
         }
     }
+}
 
-    return Ok(());
+/**
+Get the rotation and flips that draw a tile like its gid's flip bits say.
+Swapping the axes is the same as flipping vertically and rotating a quarter turn clockwise,
+the horizontal and vertical flips after it turn into vertical and horizontal ones before the rotation.
+*/
+fn tile_transform(gid: u32) -> (f32, bool, bool) {
+    let horizontal = gid & TILE_FLIPPED_HORIZONTALLY != 0;
+    let vertical = gid & TILE_FLIPPED_VERTICALLY != 0;
+    if gid & TILE_FLIPPED_DIAGONALLY != 0 {
+        return (FRAC_PI_2, vertical, !horizontal);
+    }
+    return (0.0, horizontal, vertical);
 }
 
 /** Handle the camera on the scene */
-pub(crate) fn handle_camera(scene: &JsonValue) -> IslandResult<()> {
-    let camera = &scene["camera"];
-    let position = camera["position"].as_vec2()?;
-    let zoom = &camera["zoom"].as_vec2()?;
+pub(crate) fn handle_camera(camera: &Camera) -> IslandResult<()> {
     let zoom = (
-        zoom.x / screen_width() * 2.0,
-        zoom.y / -screen_height() * 2.0,
+        camera.zoom.x / screen_width() * 2.0,
+        camera.zoom.y / -screen_height() * 2.0,
     );
 
     set_camera(&Camera2D {
         rotation: 0.0,
         zoom: zoom.into(),
-        target: position,
+        target: camera.position,
         ..Default::default()
     });
 
     return Ok(());
 }
+
+/** Get the part of the world the camera shows on a screen of the size */
+pub(crate) fn view_rect(camera: &Camera, screen_size: Vec2) -> Rect {
+    let a = screen_to_world(camera, Vec2::ZERO, screen_size);
+    let b = screen_to_world(camera, screen_size, screen_size);
+    let (min, max) = (a.min(b), a.max(b));
+    return Rect::new(min.x, min.y, max.x - min.x, max.y - min.y);
+}
+
+/** Convert a position in pixels on the screen to the world, the inverse of the camera set by `handle_camera` */
+pub(crate) fn screen_to_world(camera: &Camera, position: Vec2, screen_size: Vec2) -> Vec2 {
+    return camera.position + (position - screen_size / 2.0) / camera.zoom;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprite(texture: &str, x: f32, y: f32, layer: i32) -> json::JsonValue {
+        return json::object! {
+            position: { x: x, y: y },
+            scale: { x: 1, y: 1 },
+            sprite: { texture: texture, dest_size: { x: 16, y: 16 }, layer: layer },
+        };
+    }
+
+    /** The textures of the sprites and the names of the tile layers, in draw order */
+    fn names(drawables: &[Drawable]) -> Vec<String> {
+        return drawables
+            .iter()
+            .map(|drawable| match drawable {
+                Drawable::Sprite { sprite, .. } => sprite.texture.clone(),
+                Drawable::Tiles { layer, .. } => layer.name.clone(),
+            })
+            .collect();
+    }
+
+    fn scene() -> Registry {
+        let mut registry = Registry::new();
+        let entities = json::array![
+            sprite("tree", 0.0, 80.0, 1),
+            sprite("hero", 0.0, 50.0, 0),
+            sprite("coin", 0.0, 10.0, 0),
+            {
+                position: { x: 0, y: 0 },
+                scale: { x: 1, y: 1 },
+                tilemap: {
+                    width: 1,
+                    height: 1,
+                    tile_size: { x: 16, y: 16 },
+                    tilesets: [],
+                    layers: [
+                        { name: "ground", data: [0] },
+                        { name: "roofs", data: [0], layer: 2 },
+                        { name: "hidden", data: [0], visible: false },
+                    ],
+                },
+            },
+            sprite("chest", 0.0, 50.0, 0),
+        ];
+        for entity in entities.members() {
+            registry.load_entity(entity).unwrap();
+        }
+        return registry;
+    }
+
+    #[test]
+    fn drawables_are_drawn_by_layer_with_tiles_first() {
+        let registry = scene();
+        let drawables = draw_order(&registry, &hashbrown::HashMap::new(), 1.0, false);
+        assert_eq!(names(&drawables), vec!["ground", "hero", "coin", "chest", "tree", "roofs"]);
+    }
+
+    #[test]
+    fn y_sorted_sprites_are_drawn_by_row_then_entity_order() {
+        let registry = scene();
+        let drawables = draw_order(&registry, &hashbrown::HashMap::new(), 1.0, true);
+        assert_eq!(names(&drawables), vec!["ground", "coin", "hero", "chest", "tree", "roofs"]);
+    }
+
+    #[test]
+    fn sprites_are_placed_by_their_pivot_and_interpolated() {
+        let mut registry = Registry::new();
+        let entity = registry
+            .load_entity(&json::object! {
+                position: { x: 100, y: 100 },
+                scale: { x: 2, y: 2 },
+                sprite: { texture: "hero", dest_size: { x: 16, y: 8 }, pivot: { x: 0.5, y: 1 } },
+            })
+            .unwrap();
+        let place = |drawables: Vec<Drawable>| match drawables.as_slice() {
+            [Drawable::Sprite {
+                top_left, size, pivot, ..
+            }] => (*top_left, *size, *pivot),
+            _ => panic!("Expected a single sprite"),
+        };
+
+        let drawables = draw_order(&registry, &hashbrown::HashMap::new(), 1.0, false);
+        assert_eq!(place(drawables), (vec2(84.0, 84.0), vec2(32.0, 16.0), vec2(100.0, 100.0)));
+
+        // A quarter of the way from the last fixed update, three quarters of the motion are still to come
+        let mut fixed_motion = hashbrown::HashMap::new();
+        fixed_motion.insert(entity, vec2(8.0, 0.0));
+        let drawables = draw_order(&registry, &fixed_motion, 0.25, false);
+        assert_eq!(place(drawables), (vec2(78.0, 84.0), vec2(32.0, 16.0), vec2(94.0, 100.0)));
+    }
+}
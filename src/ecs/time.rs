@@ -0,0 +1,206 @@
+//! Frame timing and the fixed-timestep clock
+
+use crate::error::{IslandError, IslandResult};
+
+/** The settings for running fixed updates at a constant rate */
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    // The amount of fixed updates per second
+    pub tick_rate: f32,
+    // The most fixed updates run in a single frame, the rest of the time is dropped
+    pub max_steps: u32,
+}
+
+impl FixedTimestep {
+    /** Check that the settings can run, with a tick rate above 0 */
+    fn validate(&self) -> IslandResult<()> {
+        if !(self.tick_rate > 0.0 && self.tick_rate.is_finite()) {
+            return Err(IslandError::new(format!(
+                "The fixed timestep has to have a tick rate above 0, found {}",
+                self.tick_rate
+            )));
+        }
+        return Ok(());
+    }
+
+    /** Get the duration of a single fixed update in seconds */
+    pub fn step(&self) -> f32 {
+        return 1.0 / self.tick_rate;
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        return Self {
+            tick_rate: 60.0,
+            max_steps: 5,
+        };
+    }
+}
+
+/** Keeps track of the frame time and the fixed-timestep accumulator */
+#[derive(Debug, Clone, Default)]
+pub struct Time {
+    // The time the last frame took
+    frame_time: f32,
+    // The fixed-timestep settings, fixed updates are disabled if none
+    fixed_timestep: Option<FixedTimestep>,
+    // The time that has not been consumed by fixed updates yet
+    accumulator: f32,
+    // Set while the fixed update stage is running
+    in_fixed_update: bool,
+}
+
+impl Time {
+    /** Construct a new clock, fails if the fixed timestep has no tick rate above 0 */
+    pub fn new(fixed_timestep: Option<FixedTimestep>) -> IslandResult<Self> {
+        let mut result = Self::default();
+        result.set_fixed_timestep(fixed_timestep)?;
+        return Ok(result);
+    }
+
+    /** Get the delta time of the current stage, the fixed step during fixed updates */
+    pub fn delta(&self) -> f32 {
+        if self.in_fixed_update {
+            if let Some(fixed_timestep) = self.fixed_timestep {
+                return fixed_timestep.step();
+            }
+        }
+        return self.frame_time;
+    }
+
+    /** Get the time the last frame took */
+    pub fn frame_time(&self) -> f32 {
+        return self.frame_time;
+    }
+
+    /** Get the fixed-timestep settings */
+    pub fn fixed_timestep(&self) -> Option<FixedTimestep> {
+        return self.fixed_timestep;
+    }
+
+    /**
+    Set the fixed-timestep settings, none disables fixed updates.
+    Fails and keeps the current settings if the tick rate is not above 0.
+    */
+    pub fn set_fixed_timestep(&mut self, fixed_timestep: Option<FixedTimestep>) -> IslandResult<()> {
+        if let Some(fixed_timestep) = &fixed_timestep {
+            fixed_timestep.validate()?;
+        }
+        self.fixed_timestep = fixed_timestep;
+        self.accumulator = 0.0;
+        return Ok(());
+    }
+
+    /**
+    Get how far the clock is between the last and the next fixed update, from 0 to 1.
+    Used to interpolate rendering between fixed updates.
+    */
+    pub fn alpha(&self) -> f32 {
+        if let Some(fixed_timestep) = self.fixed_timestep {
+            return (self.accumulator / fixed_timestep.step()).clamp(0.0, 1.0);
+        }
+        return 1.0;
+    }
+
+    /** Advance the clock by a frame and get the amount of fixed updates to run */
+    pub(crate) fn advance(&mut self, frame_time: f32) -> u32 {
+        self.frame_time = frame_time;
+        let fixed_timestep = match self.fixed_timestep {
+            Some(fixed_timestep) => fixed_timestep,
+            None => return 0,
+        };
+
+        let step = fixed_timestep.step();
+        self.accumulator += frame_time;
+        let mut steps = (self.accumulator / step) as u32;
+        if steps > fixed_timestep.max_steps {
+            // Drop the time we can't catch up on instead of spiraling
+            steps = fixed_timestep.max_steps;
+            self.accumulator %= step;
+        } else {
+            self.accumulator -= steps as f32 * step;
+        }
+        return steps;
+    }
+
+    /** Mark the start or end of the fixed update stage */
+    pub(crate) fn set_in_fixed_update(&mut self, in_fixed_update: bool) {
+        self.in_fixed_update = in_fixed_update;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** A clock with fixed updates every quarter second, so the sums are exact */
+    fn clock(max_steps: u32) -> Time {
+        return Time::new(Some(FixedTimestep {
+            tick_rate: 4.0,
+            max_steps,
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn frame_time_accumulates_into_fixed_steps() {
+        let mut time = clock(5);
+        assert_eq!(time.advance(0.125), 0);
+        assert_eq!(time.alpha(), 0.5);
+        assert_eq!(time.advance(0.125), 1);
+        assert_eq!(time.alpha(), 0.0);
+        assert_eq!(time.advance(0.625), 2);
+        assert_eq!(time.alpha(), 0.5);
+        assert_eq!(time.frame_time(), 0.625);
+    }
+
+    #[test]
+    fn steps_past_the_max_are_dropped() {
+        let mut time = clock(2);
+        // Five steps of time, only two run and the whole steps left over are dropped
+        assert_eq!(time.advance(1.375), 2);
+        assert_eq!(time.alpha(), 0.5);
+        assert_eq!(time.advance(0.125), 1);
+        assert_eq!(time.alpha(), 0.0);
+    }
+
+    #[test]
+    fn the_delta_is_the_fixed_step_during_fixed_updates() {
+        let mut time = clock(5);
+        time.advance(0.5);
+        assert_eq!(time.delta(), 0.5);
+        time.set_in_fixed_update(true);
+        assert_eq!(time.delta(), 0.25);
+        time.set_in_fixed_update(false);
+        assert_eq!(time.delta(), 0.5);
+    }
+
+    #[test]
+    fn without_a_fixed_timestep_no_fixed_updates_run() {
+        let mut time = Time::new(None).unwrap();
+        assert_eq!(time.advance(10.0), 0);
+        assert_eq!(time.alpha(), 1.0);
+
+        // Changing the settings starts the accumulator over
+        time.set_fixed_timestep(Some(FixedTimestep::default())).unwrap();
+        assert_eq!(time.alpha(), 0.0);
+    }
+
+    #[test]
+    fn tick_rates_have_to_be_above_0() {
+        for tick_rate in [0.0, -60.0, f32::NAN, f32::INFINITY].iter() {
+            let fixed_timestep = FixedTimestep {
+                tick_rate: *tick_rate,
+                max_steps: 5,
+            };
+            assert!(Time::new(Some(fixed_timestep)).is_err(), "{}", tick_rate);
+
+            // The previous settings are kept
+            let mut time = clock(5);
+            let e = time.set_fixed_timestep(Some(fixed_timestep)).unwrap_err();
+            assert!(e.reason().contains("tick rate above 0"), "{}", e);
+            assert_eq!(time.fixed_timestep().unwrap().tick_rate, 4.0);
+        }
+    }
+}
@@ -0,0 +1,506 @@
+//! Validation of scene files, reporting every problem with its json path
+use json::JsonValue;
+
+use crate::error::{ErrorKind, IslandError, IslandResult};
+
+use super::{
+    components::is_convex,
+    storage::{Registry, MAX_ENTITY_ID},
+    systems::JsonConvert,
+};
+
+/** Collects the problems found in a json document */
+pub struct Problems {
+    problems: Vec<String>,
+}
+
+impl Problems {
+    /** Construct an empty problem list */
+    pub fn new() -> Self {
+        return Self { problems: vec![] };
+    }
+
+    /** Add a problem at the json path */
+    pub fn push<T>(&mut self, path: &str, problem: T)
+    where
+        T: Into<String>,
+    {
+        self.problems.push(format!("{}: {}", path, problem.into()));
+    }
+
+    /** Turn the problems into an error of the kind if there are any */
+    pub fn into_result<T>(self, kind: ErrorKind, what: T) -> IslandResult<()>
+    where
+        T: Into<String>,
+    {
+        if self.problems.is_empty() {
+            return Ok(());
+        }
+        return Err(IslandError::with_kind(kind, format!(
+            "{} has {} problem(s):\n  {}",
+            what.into(),
+            self.problems.len(),
+            self.problems.join("\n  ")
+        )));
+    }
+
+    /** Check that the value is a number */
+    pub fn expect_number(&mut self, path: &str, json: &JsonValue) {
+        if !json.is_number() {
+            self.push(path, format!("expected a number, found {}", describe(json)));
+        }
+    }
+
+    /** Check that the value is a number, if it is set */
+    pub fn expect_optional_number(&mut self, path: &str, json: &JsonValue) {
+        if !json.is_null() {
+            self.expect_number(path, json);
+        }
+    }
+
+    /** Check that the value is a string */
+    pub fn expect_string(&mut self, path: &str, json: &JsonValue) {
+        if !json.is_string() {
+            self.push(path, format!("expected a string, found {}", describe(json)));
+        }
+    }
+
+    /** Check that the value is a boolean, if it is set */
+    pub fn expect_optional_bool(&mut self, path: &str, json: &JsonValue) {
+        if !json.is_null() && !json.is_boolean() {
+            self.push(path, format!("expected a boolean, found {}", describe(json)));
+        }
+    }
+
+    /** Check that the value is an object with number fields */
+    pub fn expect_fields(&mut self, path: &str, json: &JsonValue, fields: &[&str]) {
+        if !json.is_object() {
+            self.push(
+                path,
+                format!("expected an object with {}, found {}", fields.join(", "), describe(json)),
+            );
+            return;
+        }
+        for field in fields {
+            self.expect_number(&format!("{}.{}", path, field), &json[*field]);
+        }
+    }
+
+    /** Check that the value is a list of strings */
+    pub fn expect_string_list(&mut self, path: &str, json: &JsonValue) {
+        if !json.is_array() {
+            self.push(path, format!("expected a list of strings, found {}", describe(json)));
+            return;
+        }
+        for (i, value) in json.members().enumerate() {
+            self.expect_string(&format!("{}[{}]", path, i), value);
+        }
+    }
+}
+
+impl Default for Problems {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/** Describe the type and value of a json value for problem messages */
+fn describe(json: &JsonValue) -> String {
+    return match json {
+        JsonValue::Null => "nothing".into(),
+        JsonValue::Short(_) | JsonValue::String(_) => format!("string {}", json.dump()),
+        JsonValue::Number(_) => format!("number {}", json.dump()),
+        JsonValue::Boolean(_) => format!("boolean {}", json.dump()),
+        JsonValue::Object(_) => "an object".into(),
+        JsonValue::Array(_) => "a list".into(),
+    };
+}
+
+/** Check the shape of a scene and its entities' components */
+pub fn validate_scene(scene: &JsonValue, registry: &Registry) -> Problems {
+    let mut problems = Problems::new();
+    if !scene.is_object() {
+        problems.push("scene", format!("expected an object, found {}", describe(scene)));
+        return problems;
+    }
+
+    if scene.has_key("scripts") {
+        problems.expect_string_list("scripts", &scene["scripts"]);
+    }
+    problems.expect_optional_bool("y_sort", &scene["y_sort"]);
+    if scene.has_key("camera") {
+        validate_camera(&mut problems, "camera", &scene["camera"]);
+    }
+    if scene.has_key("entities") && !scene["entities"].is_array() {
+        problems.push(
+            "entities",
+            format!("expected a list of entities, found {}", describe(&scene["entities"])),
+        );
+    }
+    for (i, entity) in scene["entities"].members().enumerate() {
+        validate_entity(&mut problems, &format!("entities[{}]", i), entity, registry);
+    }
+
+    return problems;
+}
+
+/** Check the shape of an entity's components */
+pub fn validate_entity(problems: &mut Problems, path: &str, entity: &JsonValue, registry: &Registry) {
+    if !entity.is_object() {
+        problems.push(path, format!("expected an entity object, found {}", describe(entity)));
+        return;
+    }
+
+    for (key, value) in entity.entries() {
+        let component_path = format!("{}.{}", path, key);
+        match key {
+            "id" => {
+                if value.as_u64().filter(|id| *id <= MAX_ENTITY_ID).is_none() {
+                    problems.push(
+                        &component_path,
+                        format!("expected a whole number from 0 to {}, found {}", MAX_ENTITY_ID, describe(value)),
+                    );
+                }
+            }
+            "position" | "scale" => problems.expect_fields(&component_path, value, &["x", "y"]),
+            "sprite" => validate_sprite(problems, &component_path, value),
+            "animation" => validate_animation(problems, &component_path, value),
+            "tilemap" => validate_tilemap(problems, &component_path, value),
+            "collider" => validate_collider(problems, &component_path, value),
+            "name" | "tag" | "prefab" | "subscene" | "script" => problems.expect_string(&component_path, value),
+            _ => {
+                // Let registered components check themselves
+                if let Some(Err(e)) = registry.validate_component(key, value) {
+                    problems.push(&component_path, format!("{:#}", e));
+                }
+            }
+        }
+    }
+}
+
+/** Check the shape of a sprite component */
+fn validate_sprite(problems: &mut Problems, path: &str, sprite: &JsonValue) {
+    if !sprite.is_object() {
+        problems.push(path, format!("expected a sprite object, found {}", describe(sprite)));
+        return;
+    }
+    problems.expect_string(&format!("{}.texture", path), &sprite["texture"]);
+    problems.expect_fields(&format!("{}.dest_size", path), &sprite["dest_size"], &["x", "y"]);
+    if sprite.has_key("source_rec") {
+        problems.expect_fields(
+            &format!("{}.source_rec", path),
+            &sprite["source_rec"],
+            &["x", "y", "w", "h"],
+        );
+    }
+    problems.expect_optional_bool(&format!("{}.flip_x", path), &sprite["flip_x"]);
+    problems.expect_optional_bool(&format!("{}.flip_y", path), &sprite["flip_y"]);
+    problems.expect_optional_number(&format!("{}.rotation", path), &sprite["rotation"]);
+    if sprite.has_key("pivot") {
+        problems.expect_fields(&format!("{}.pivot", path), &sprite["pivot"], &["x", "y"]);
+    }
+    if sprite.has_key("color") {
+        let color_path = format!("{}.color", path);
+        problems.expect_fields(&color_path, &sprite["color"], &["r", "g", "b"]);
+        problems.expect_optional_number(&format!("{}.a", color_path), &sprite["color"]["a"]);
+    }
+    if !sprite["layer"].is_null() && sprite["layer"].as_i32().is_none() {
+        problems.push(
+            &format!("{}.layer", path),
+            format!("expected a whole number, found {}", describe(&sprite["layer"])),
+        );
+    }
+}
+
+/** Check the shape of an animation component, the clip is looked up when it plays */
+fn validate_animation(problems: &mut Problems, path: &str, animation: &JsonValue) {
+    if !animation.is_object() {
+        problems.push(path, format!("expected an animation object, found {}", describe(animation)));
+        return;
+    }
+    problems.expect_string(&format!("{}.clip", path), &animation["clip"]);
+    if !animation["frame"].is_null() && animation["frame"].as_usize().is_none() {
+        problems.push(
+            &format!("{}.frame", path),
+            format!("expected a positive whole number, found {}", describe(&animation["frame"])),
+        );
+    }
+    problems.expect_optional_number(&format!("{}.time", path), &animation["time"]);
+    problems.expect_optional_bool(&format!("{}.playing", path), &animation["playing"]);
+    problems.expect_optional_bool(&format!("{}.backwards", path), &animation["backwards"]);
+}
+
+/** Check the shape of a tilemap component, the tilesets are looked up when it is drawn */
+fn validate_tilemap(problems: &mut Problems, path: &str, tilemap: &JsonValue) {
+    if !tilemap.is_object() {
+        problems.push(path, format!("expected a tilemap object, found {}", describe(tilemap)));
+        return;
+    }
+    problems.expect_fields(&format!("{}.tile_size", path), &tilemap["tile_size"], &["x", "y"]);
+    for field in ["width", "height"].iter() {
+        if tilemap[*field].as_usize().is_none() {
+            problems.push(
+                &format!("{}.{}", path, field),
+                format!("expected a positive whole number, found {}", describe(&tilemap[*field])),
+            );
+        }
+    }
+
+    if !tilemap["tilesets"].is_array() {
+        problems.push(
+            &format!("{}.tilesets", path),
+            format!("expected a list of tilesets, found {}", describe(&tilemap["tilesets"])),
+        );
+    }
+    for (i, tileset) in tilemap["tilesets"].members().enumerate() {
+        let tileset_path = format!("{}.tilesets[{}]", path, i);
+        if tileset["first_gid"].as_u32().map(|gid| gid > 0).unwrap_or(false) {
+            problems.expect_string(&format!("{}.name", tileset_path), &tileset["name"]);
+        } else {
+            problems.push(
+                &format!("{}.first_gid", tileset_path),
+                format!("expected a whole number above 0, found {}", describe(&tileset["first_gid"])),
+            );
+        }
+    }
+
+    if !tilemap["layers"].is_array() {
+        problems.push(
+            &format!("{}.layers", path),
+            format!("expected a list of layers, found {}", describe(&tilemap["layers"])),
+        );
+    }
+    let (width, height) = (tilemap["width"].as_usize(), tilemap["height"].as_usize());
+    let tiles = match width.zip(height).map(|(width, height)| width.checked_mul(height)) {
+        Some(Some(tiles)) => tiles,
+        Some(None) => {
+            problems.push(path, "expected a width and height with a tile count that fits in memory");
+            return;
+        }
+        None => 0,
+    };
+    for (i, layer) in tilemap["layers"].members().enumerate() {
+        let layer_path = format!("{}.layers[{}]", path, i);
+        problems.expect_string(&format!("{}.name", layer_path), &layer["name"]);
+        let data = &layer["data"];
+        if !data.is_array() || data.len() != tiles {
+            problems.push(
+                &format!("{}.data", layer_path),
+                format!("expected a list of {} tiles, found {}", tiles, describe(data)),
+            );
+        } else if let Some(gid) = data.members().position(|gid| gid.as_u32().is_none()) {
+            problems.push(
+                &format!("{}.data[{}]", layer_path, gid),
+                format!("expected a tile gid, found {}", describe(&data[gid])),
+            );
+        }
+        if !layer["layer"].is_null() && layer["layer"].as_i32().is_none() {
+            problems.push(
+                &format!("{}.layer", layer_path),
+                format!("expected a whole number, found {}", describe(&layer["layer"])),
+            );
+        }
+        problems.expect_optional_bool(&format!("{}.visible", layer_path), &layer["visible"]);
+        problems.expect_optional_number(&format!("{}.opacity", layer_path), &layer["opacity"]);
+    }
+}
+
+/** Check the shape of a collider component */
+fn validate_collider(problems: &mut Problems, path: &str, collider: &JsonValue) {
+    if !collider.is_object() {
+        problems.push(path, format!("expected a collider object, found {}", describe(collider)));
+        return;
+    }
+    match collider["shape"].as_str() {
+        Some("rect") => problems.expect_fields(&format!("{}.size", path), &collider["size"], &["x", "y"]),
+        Some("circle") => {
+            let radius = &collider["radius"];
+            if radius.as_f32().map(|radius| radius < 0.0).unwrap_or(true) {
+                problems.push(
+                    &format!("{}.radius", path),
+                    format!("expected a number of 0 or more, found {}", describe(radius)),
+                );
+            }
+        }
+        Some("polygon") => {
+            let points = &collider["points"];
+            if !points.is_array() || points.len() < 3 {
+                problems.push(
+                    &format!("{}.points", path),
+                    format!("expected a list of at least 3 points, found {}", describe(points)),
+                );
+            }
+            for (i, point) in points.members().enumerate() {
+                problems.expect_fields(&format!("{}.points[{}]", path, i), point, &["x", "y"]);
+            }
+            let vertices = points.members().filter_map(|point| point.as_vec2().ok()).collect::<Vec<_>>();
+            // The points that are not numbers are reported on their own
+            if vertices.len() == points.len() && vertices.len() >= 3 && !is_convex(&vertices) {
+                problems.push(
+                    &format!("{}.points", path),
+                    "expected the points of a convex polygon, going around it without crossing",
+                );
+            }
+        }
+        _ => problems.push(
+            &format!("{}.shape", path),
+            format!("expected \"rect\", \"circle\" or \"polygon\", found {}", describe(&collider["shape"])),
+        ),
+    }
+    if collider.has_key("offset") {
+        problems.expect_fields(&format!("{}.offset", path), &collider["offset"], &["x", "y"]);
+    }
+}
+
+/** Check the shape of the scene camera */
+fn validate_camera(problems: &mut Problems, path: &str, camera: &JsonValue) {
+    if !camera.is_object() {
+        problems.push(path, format!("expected a camera object, found {}", describe(camera)));
+        return;
+    }
+    problems.expect_fields(&format!("{}.position", path), &camera["position"], &["x", "y"]);
+    problems.expect_fields(&format!("{}.zoom", path), &camera["zoom"], &["x", "y"]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::storage::tests::Health;
+
+    fn validation_message(scene: &JsonValue, registry: &Registry) -> String {
+        let error = validate_scene(scene, registry)
+            .into_result(ErrorKind::SceneValidation, "Scene")
+            .unwrap_err();
+        return format!("{:#}", error);
+    }
+
+    #[test]
+    fn valid_scenes_have_no_problems() {
+        let registry = Registry::new();
+        let scene = json::object! {
+            scripts: ["main.py"],
+            y_sort: true,
+            camera: { position: { x: 0, y: 0 }, zoom: { x: 1, y: 1 } },
+            entities: [
+                { id: 0, name: "player", position: { x: 1, y: 2 }, sprite: { texture: "player", dest_size: { x: 16, y: 16 } } },
+                { collider: { shape: "circle", radius: 4 }, unknown: "kept" },
+            ],
+        };
+        assert!(validate_scene(&scene, &registry).into_result(ErrorKind::SceneValidation, "Scene").is_ok());
+    }
+
+    #[test]
+    fn every_problem_is_reported_with_its_path() {
+        let registry = Registry::new();
+        let scene = json::object! {
+            scripts: [1],
+            y_sort: "yes",
+            entities: [
+                { position: { x: "a", y: 0 } },
+                "not an entity",
+                { name: 5, sprite: { texture: 1, dest_size: { x: 16, y: 16 } } },
+            ],
+        };
+        let message = validation_message(&scene, &registry);
+        assert!(message.contains("6 problem(s)"), "{}", message);
+        for problem in [
+            "scripts[0]: expected a string, found number 1",
+            "y_sort: expected a boolean, found string \"yes\"",
+            "entities[0].position.x: expected a number, found string \"a\"",
+            "entities[1]: expected an entity object, found string \"not an entity\"",
+            "entities[2].name: expected a string, found number 5",
+            "entities[2].sprite.texture: expected a string, found number 1",
+        ]
+        .iter()
+        {
+            assert!(message.contains(problem), "{} missing from {}", problem, message);
+        }
+    }
+
+    #[test]
+    fn scenes_and_entity_lists_have_to_have_the_right_type() {
+        let registry = Registry::new();
+        let message = validation_message(&json::array![], &registry);
+        assert!(message.contains("scene: expected an object, found a list"), "{}", message);
+
+        let message = validation_message(&json::object! { entities: { id: 0 } }, &registry);
+        assert!(message.contains("entities: expected a list of entities, found an object"), "{}", message);
+    }
+
+    #[test]
+    fn colliders_have_to_be_convex_with_a_positive_radius() {
+        let registry = Registry::new();
+        let polygon = |points: JsonValue| json::object! { collider: { shape: "polygon", points: points } };
+        let scene = json::object! { entities: [
+            { collider: { shape: "circle", radius: -1 } },
+            // An arrow head, concave at the back
+            polygon(json::array![{ x: 0, y: 0 }, { x: 4, y: 2 }, { x: 0, y: 4 }, { x: 1, y: 2 }]),
+            // A bow tie, crossing itself
+            polygon(json::array![{ x: 0, y: 0 }, { x: 2, y: 2 }, { x: 2, y: 0 }, { x: 0, y: 2 }]),
+            // A line, without an area
+            polygon(json::array![{ x: 0, y: 0 }, { x: 1, y: 1 }, { x: 2, y: 2 }]),
+            // A star, turning the same way at each point but going around twice
+            polygon(json::array![
+                { x: 0, y: -10 }, { x: 6, y: 8 }, { x: -9, y: -3 }, { x: 9, y: -3 }, { x: -6, y: 8 },
+            ]),
+            { collider: { shape: "circle", radius: 0 } },
+            // A square written clockwise, with its first point repeated at the end
+            polygon(json::array![{ x: 0, y: 0 }, { x: 0, y: 2 }, { x: 2, y: 2 }, { x: 2, y: 0 }, { x: 0, y: 0 }]),
+        ]};
+        let message = validation_message(&scene, &registry);
+        assert!(message.contains("5 problem(s)"), "{}", message);
+        assert!(message.contains("entities[0].collider.radius: expected a number of 0 or more"), "{}", message);
+        for i in 1..5 {
+            let problem = format!("entities[{}].collider.points: expected the points of a convex polygon", i);
+            assert!(message.contains(&problem), "{} missing from {}", problem, message);
+        }
+
+        // Scripts setting colliders get the same checks
+        for entity in scene["entities"].members().take(5) {
+            assert!(registry.validate_entity(entity).is_err(), "{}", entity);
+        }
+        for entity in scene["entities"].members().skip(5) {
+            assert!(registry.validate_entity(entity).is_ok(), "{}", entity);
+        }
+    }
+
+    #[test]
+    fn registered_components_report_their_own_problems() {
+        let mut registry = Registry::new();
+        let scene = json::object! { entities: [{ health: 3 }, { health: "full" }] };
+        assert!(validate_scene(&scene, &registry).into_result(ErrorKind::SceneValidation, "Scene").is_ok());
+
+        registry.register::<Health>();
+        let message = validation_message(&scene, &registry);
+        assert!(message.contains("1 problem(s)"), "{}", message);
+        assert!(message.contains("entities[1].health: Invalid component \"health\": Health has to be a whole number"), "{}", message);
+    }
+
+    #[test]
+    fn entity_ids_have_to_fit_in_an_i64() {
+        let registry = Registry::new();
+        let scene = json::object! { entities: [{ id: MAX_ENTITY_ID }, { id: u64::MAX }] };
+        let error = validate_scene(&scene, &registry)
+            .into_result(ErrorKind::SceneValidation, "Scene")
+            .unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.contains("1 problem(s)"), "{}", message);
+        assert!(message.contains("entities[1].id"), "{}", message);
+    }
+
+    #[test]
+    fn huge_tilemaps_are_reported_instead_of_overflowing() {
+        let registry = Registry::new();
+        let scene = json::object! { entities: [{ tilemap: {
+            tile_size: { x: 16, y: 16 },
+            width: u64::MAX,
+            height: 2,
+            tilesets: [],
+            layers: [{ name: "ground", data: [] }],
+        }}]};
+        let error = validate_scene(&scene, &registry)
+            .into_result(ErrorKind::SceneValidation, "Scene")
+            .unwrap_err();
+        assert!(format!("{:#}", error).contains("entities[0].tilemap: expected a width and height"));
+    }
+}
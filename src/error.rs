@@ -1,34 +1,399 @@
+use std::{
+    cell::RefCell,
+    error::Error,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use pyo3::{
+    exceptions::{PySyntaxError, PyValueError},
+    PyErr, PyResult, Python,
+};
+
 /** A result that returns a highground error */
 pub type IslandResult<T> = Result<T, IslandError>;
 
-/** Represents a highground error */
+/** What went wrong, so callers can handle failures differently */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /** A file could not be read or written */
+    Io,
+    /** A file is not valid json */
+    Json,
+    /** A python script failed to load or raised an exception */
+    Python,
+    /** A scene or entity has missing or invalid values */
+    SceneValidation,
+    /** The asset descriptor or an asset is invalid */
+    Asset,
+    /** A plugin returned an error */
+    Plugin,
+    /** The input action map or a binding is invalid */
+    Input,
+    /** Anything else */
+    Other,
+}
+
+/**
+Represents a highground error.
+The `Display` output is the message only, `{:#}` adds the messages of the whole source chain
+and `Debug` prints the kind, the chain and the python traceback.
+*/
 #[derive(Clone)]
 pub struct IslandError {
+    // What went wrong
+    kind: ErrorKind,
+    // The message of this error
     reason: String,
+    // The formatted python traceback, for python errors
+    traceback: Option<String>,
+    // The file and line a python exception was raised at
+    location: Option<(String, u32)>,
+    // The error that caused this one
+    source: Option<Arc<dyn Error + Send + Sync + 'static>>,
 }
 
 impl IslandError {
     /** Construct a new highground error */
     pub fn new<T>(reason: T) -> Self
+    where
+        T: Into<String>,
+    {
+        return Self::with_kind(ErrorKind::Other, reason);
+    }
+
+    /** Construct a new highground error of a kind */
+    pub fn with_kind<T>(kind: ErrorKind, reason: T) -> Self
     where
         T: Into<String>,
     {
         return Self {
+            kind,
             reason: reason.into(),
+            traceback: None,
+            location: None,
+            source: None,
+        };
+    }
+
+    /** Construct a new highground error of a kind, caused by another error */
+    pub fn with_source<T, E>(kind: ErrorKind, reason: T, source: E) -> Self
+    where
+        T: Into<String>,
+        E: Error + Send + Sync + 'static,
+    {
+        return Self {
+            source: Some(Arc::new(source)),
+            ..Self::with_kind(kind, reason)
+        };
+    }
+
+    /**
+    Construct a new python error, keeping the traceback of the exception.
+    The file and line the exception was raised at are added to the message.
+    */
+    pub fn python<T>(py: Python, reason: T, error: PyErr) -> Self
+    where
+        T: Into<String>,
+    {
+        let location = python_location(py, &error).ok();
+        let reason = match &location {
+            Some((file, line)) => format!("{} (\"{}\", line {})", reason.into(), file, line),
+            None => reason.into(),
         };
+        let traceback = py
+            .import("traceback")
+            .and_then(|traceback| {
+                return traceback.call_method1(
+                    "format_exception",
+                    (error.ptype(py), error.pvalue(py), error.ptraceback(py)),
+                );
+            })
+            .and_then(|lines| lines.extract::<Vec<String>>())
+            .map(|lines| lines.concat())
+            .ok();
+        return Self {
+            traceback,
+            location,
+            ..Self::with_source(ErrorKind::Python, reason, error)
+        };
+    }
+
+    /**
+    Wrap the error with a message describing what was being done.
+    The kind is kept and the error becomes the source of the new one.
+    */
+    pub fn context<T>(self, reason: T) -> Self
+    where
+        T: Into<String>,
+    {
+        let kind = self.kind;
+        return Self::with_source(kind, reason, self);
+    }
+
+    /** Get what went wrong */
+    pub fn kind(&self) -> ErrorKind {
+        return self.kind;
+    }
+
+    /** Check if this error or any highground error in its source chain is of the kind */
+    pub fn has_kind(&self, kind: ErrorKind) -> bool {
+        return self.island_chain().any(|error| error.kind == kind);
+    }
+
+    /** Get the message of this error, without its sources */
+    pub fn reason(&self) -> &str {
+        return &self.reason;
+    }
+
+    /** Get the python traceback of this error or the first one in its source chain */
+    pub fn traceback(&self) -> Option<&str> {
+        return self.island_chain().find_map(|error| error.traceback.as_deref());
+    }
+
+    /** Get the file and line of the python exception of this error or the first one in its source chain */
+    pub fn location(&self) -> Option<(&str, u32)> {
+        return self
+            .island_chain()
+            .find_map(|error| error.location.as_ref())
+            .map(|(file, line)| (file.as_str(), *line));
+    }
+
+    /** Iterate over this error and the highground errors in its source chain */
+    fn island_chain(&self) -> impl Iterator<Item = &IslandError> {
+        return std::iter::successors(Some(self), |error| {
+            return error.source.as_ref()?.downcast_ref::<IslandError>();
+        });
     }
 }
 
+thread_local! {
+    // The directory of the scripts of the world on this thread, to find the frame of a script in tracebacks
+    static SCRIPTS_ROOT: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/** Set the directory python exceptions are located in, the scripts root of the world on this thread */
+pub(crate) fn set_scripts_root(root: &Path) {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    SCRIPTS_ROOT.with(|scripts_root| *scripts_root.borrow_mut() = Some(root));
+}
+
+/** Check if a file is in the scripts root, files that do not exist like "<string>" are not */
+fn in_scripts_root(file: &str) -> bool {
+    return SCRIPTS_ROOT.with(|root| match (root.borrow().as_ref(), Path::new(file).canonicalize()) {
+        (Some(root), Ok(file)) => file.starts_with(root),
+        _ => false,
+    });
+}
+
+/**
+Find the file and line a python exception was raised at.
+This is the innermost frame of its traceback that is in the scripts root,
+so errors raised in the standard library point at the script that called it.
+Without such a frame it is the innermost frame.
+*/
+fn python_location(py: Python, error: &PyErr) -> PyResult<(String, u32)> {
+    // Syntax errors happen before there is a frame, they know the location themselves
+    if error.is_instance::<PySyntaxError>(py) {
+        let value = error.pvalue(py);
+        return Ok((value.getattr("filename")?.extract()?, value.getattr("lineno")?.extract()?));
+    }
+
+    let mut traceback = error
+        .ptraceback(py)
+        .ok_or_else(|| PyErr::new::<PyValueError, _>("No traceback"))?;
+    let mut location = None;
+    let mut script_location = None;
+    while !traceback.is_none() {
+        let file: String = traceback.getattr("tb_frame")?.getattr("f_code")?.getattr("co_filename")?.extract()?;
+        let line: u32 = traceback.getattr("tb_lineno")?.extract()?;
+        if in_scripts_root(&file) {
+            script_location = Some((file.clone(), line));
+        }
+        location = Some((file, line));
+        traceback = traceback.getattr("tb_next")?;
+    }
+    return script_location
+        .or(location)
+        .ok_or_else(|| PyErr::new::<PyValueError, _>("No traceback"));
+}
+
 impl std::fmt::Display for IslandError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Highground: \"{}\"", self.reason)
+        write!(f, "{}", self.reason)?;
+        if f.alternate() {
+            let mut source = self.source();
+            while let Some(error) = source {
+                write!(f, ": {}", error)?;
+                source = error.source();
+            }
+        }
+        return Ok(());
     }
 }
 
 impl std::fmt::Debug for IslandError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Highground: \"{}\"", self.reason)
+        write!(f, "{:?} error: {}", self.kind, self.reason)?;
+        let mut source = self.source();
+        if source.is_some() {
+            write!(f, "\n\nCaused by:")?;
+        }
+        while let Some(error) = source {
+            write!(f, "\n    {}", error)?;
+            source = error.source();
+        }
+        if let Some(traceback) = self.traceback() {
+            write!(f, "\n\n{}", traceback.trim_end())?;
+        }
+        return Ok(());
+    }
+}
+
+impl Error for IslandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return self.source.as_ref().map(|source| source.as_ref() as &(dyn Error + 'static));
     }
 }
 
-impl std::error::Error for IslandError {}
+/** Adds context to the error of a result */
+pub trait ErrorContext<T> {
+    /** Wrap the error with a message describing what was being done */
+    fn context<C>(self, reason: C) -> IslandResult<T>
+    where
+        C: Into<String>;
+
+    /** Wrap the error with a message that is only built if there is an error */
+    fn with_context<C, F>(self, reason: F) -> IslandResult<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C;
+}
+
+impl<T> ErrorContext<T> for IslandResult<T> {
+    fn context<C>(self, reason: C) -> IslandResult<T>
+    where
+        C: Into<String>,
+    {
+        return self.map_err(|e| e.context(reason));
+    }
+
+    fn with_context<C, F>(self, reason: F) -> IslandResult<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C,
+    {
+        return self.map_err(|e| e.context(reason()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn missing_file() -> IslandError {
+        let e = io::Error::new(io::ErrorKind::NotFound, "file missing");
+        return IslandError::with_source(ErrorKind::Io, "Failed to read \"scene.json\"", e)
+            .context("Failed to load the scene");
+    }
+
+    #[test]
+    fn context_keeps_the_kind_and_the_source_chain() {
+        let e = missing_file();
+        assert_eq!(e.kind(), ErrorKind::Io);
+        assert_eq!(e.reason(), "Failed to load the scene");
+
+        let source = e.source().unwrap().downcast_ref::<IslandError>().unwrap();
+        assert_eq!(source.kind(), ErrorKind::Io);
+        assert_eq!(source.reason(), "Failed to read \"scene.json\"");
+        let io_error = source.source().unwrap().downcast_ref::<io::Error>().unwrap();
+        assert_eq!(io_error.kind(), io::ErrorKind::NotFound);
+        assert!(io_error.source().is_none());
+
+        let result: IslandResult<()> = Err(e);
+        let e = result.with_context(|| "Failed to start").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Io);
+        assert_eq!(e.source().unwrap().to_string(), "Failed to load the scene");
+    }
+
+    #[test]
+    fn has_kind_looks_through_the_chain() {
+        let json = IslandError::with_kind(ErrorKind::Json, "Expected a number");
+        let e = IslandError::with_source(ErrorKind::Plugin, "The plugin failed", json).context("Failed to run plugins");
+        assert_eq!(e.kind(), ErrorKind::Plugin);
+        assert!(e.has_kind(ErrorKind::Plugin));
+        assert!(e.has_kind(ErrorKind::Json));
+        assert!(!e.has_kind(ErrorKind::Io));
+        // Other errors in the chain end the search
+        assert!(missing_file().has_kind(ErrorKind::Io));
+        assert!(!missing_file().has_kind(ErrorKind::Other));
+    }
+
+    #[test]
+    fn errors_format_their_chain() {
+        let e = missing_file();
+        assert_eq!(format!("{}", e), "Failed to load the scene");
+        assert_eq!(
+            format!("{:#}", e),
+            "Failed to load the scene: Failed to read \"scene.json\": file missing"
+        );
+        assert_eq!(
+            format!("{:?}", e),
+            "Io error: Failed to load the scene\n\nCaused by:\n    Failed to read \"scene.json\"\n    file missing"
+        );
+        assert_eq!(format!("{:?}", IslandError::new("Nothing")), "Other error: Nothing");
+    }
+
+    #[test]
+    fn syntax_errors_are_located_without_a_frame() {
+        Python::with_gil(|py| {
+            let error = py.run("compile('x = 1\\ny = (', 'broken.py', 'exec')", None, None).unwrap_err();
+            let e = IslandError::python(py, "Failed to load script", error);
+            assert_eq!(e.kind(), ErrorKind::Python);
+            assert_eq!(e.location(), Some(("broken.py", 2)));
+            assert_eq!(e.reason(), "Failed to load script (\"broken.py\", line 2)");
+            assert!(e.traceback().unwrap().contains("SyntaxError"));
+            assert_eq!(e.context("Failed to reload").location(), Some(("broken.py", 2)));
+        });
+    }
+
+    #[test]
+    fn exceptions_are_located_in_the_innermost_script_frame() {
+        let directory = std::env::temp_dir().join("ie_test_python_location");
+        let scripts = directory.join("scripts");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&scripts).unwrap();
+        std::fs::write(
+            scripts.join("ie_location_script.py"),
+            "import json\n\ndef load():\n    return json.loads('{')\n",
+        )
+        .unwrap();
+
+        Python::with_gil(|py| {
+            let sys_path = py.import("sys").unwrap().getattr("path").unwrap();
+            sys_path.call_method1("insert", (0, scripts.to_string_lossy().as_ref())).unwrap();
+            let load = || {
+                return py
+                    .import("ie_location_script")
+                    .and_then(|script| script.call_method0("load"))
+                    .unwrap_err();
+            };
+
+            // The json decoder raises the exception, the script called it
+            set_scripts_root(&scripts);
+            let e = IslandError::python(py, "Failed to run script", load());
+            let (file, line) = e.location().unwrap();
+            assert!(file.ends_with("ie_location_script.py"), "{}", file);
+            assert_eq!(line, 4);
+            assert!(e.reason().ends_with("ie_location_script.py\", line 4)"), "{}", e.reason());
+
+            // Without a script frame it is the innermost frame
+            set_scripts_root(&directory.join("other"));
+            let e = IslandError::python(py, "Failed to run script", load());
+            let (file, _) = e.location().unwrap();
+            assert!(file.contains("json"), "{}", file);
+            assert!(!file.ends_with("ie_location_script.py"), "{}", file);
+        });
+    }
+}
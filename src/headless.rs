@@ -0,0 +1,112 @@
+//! Runs the world without a window, for gameplay tests on machines without a GPU
+use std::{cell::RefCell, rc::Rc};
+
+use pyo3::Python;
+
+use crate::{
+    assets::load_headless_assets,
+    ecs::{input::ScriptedInput, World},
+    error::IslandResult,
+    IEAppDescriptor,
+};
+
+/**
+Steps a world with a synthetic clock and scripted input, never rendering it.
+Only the animation clips and tilesets of the asset descriptor are loaded, as textures need a window.
+
+```no_run
+use ie::prelude::*;
+use pyo3::Python;
+
+fn main() -> IslandResult<()> {
+    let gil = Python::acquire_gil();
+    let mut runner = HeadlessRunner::new(gil.python(), IEAppDescriptor {
+        first_scene: Some("examples/example_scene.json"),
+        ..Default::default()
+    }, 1.0 / 60.0)?;
+
+    runner.input().press(KeyCode::D);
+    runner.run(60)?;
+    info!("{}", runner.world.scene_json());
+    return Ok(());
+}
+```
+*/
+pub struct HeadlessRunner<'a> {
+    // The world being run
+    pub world: World<'a>,
+    // The input the world reads from
+    input: Rc<ScriptedInput>,
+    // The time each frame takes on the synthetic clock
+    frame_time: f32,
+    debug_mode: bool,
+    // The amount of frames that have run
+    frames: u64,
+}
+
+impl<'a> HeadlessRunner<'a> {
+    /** Construct a runner for the app, with each frame taking `frame_time` seconds */
+    pub fn new(py: Python<'a>, app_descriptor: IEAppDescriptor<'_>, frame_time: f32) -> IslandResult<Self> {
+        let input = Rc::new(ScriptedInput::new());
+        let script_error_policy = app_descriptor.script_error_policy();
+        let actions = app_descriptor.action_map()?;
+        if let Some(assets_descriptor) = app_descriptor.assets_descriptor {
+            load_headless_assets(assets_descriptor)?;
+        }
+
+        // Set up the world before loading the scene, so init already reads the scripted input
+        let mut world = World::new(py, None, app_descriptor.plugins)?;
+        world.input = input.clone();
+        world.time.set_fixed_timestep(app_descriptor.fixed_timestep)?;
+        world.script_error_policy = script_error_policy;
+        world.actions = Rc::new(RefCell::new(actions));
+        if let Some(scripts_root) = app_descriptor.scripts_root {
+            world.set_scripts_root(scripts_root)?;
+        }
+        if let Some(first_scene) = app_descriptor.first_scene {
+            world.load_scene(first_scene)?;
+        }
+
+        return Ok(Self {
+            world,
+            input,
+            frame_time,
+            debug_mode: app_descriptor.debug_mode,
+            frames: 0,
+        });
+    }
+
+    /** Get the scripted input to drive the keys, mouse, text and gamepads */
+    pub fn input(&self) -> &ScriptedInput {
+        return &self.input;
+    }
+
+    /** Run a single frame */
+    pub fn tick(&mut self) -> IslandResult<()> {
+        self.world.step(self.frame_time, self.debug_mode)?;
+        self.input.end_frame();
+        self.frames += 1;
+        return Ok(());
+    }
+
+    /** Run the amount of frames, stopping early if the world quits */
+    pub fn run(&mut self, frames: u32) -> IslandResult<()> {
+        for _ in 0..frames {
+            if !self.world.should_run {
+                break;
+            }
+            self.tick()?;
+        }
+        return Ok(());
+    }
+
+    /** Get the amount of frames that have run */
+    pub fn frames(&self) -> u64 {
+        return self.frames;
+    }
+
+    /** Get the time that has passed on the synthetic clock in seconds */
+    pub fn elapsed(&self) -> f32 {
+        return self.frames as f32 * self.frame_time;
+    }
+}
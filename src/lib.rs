@@ -1,23 +1,31 @@
+use std::{cell::RefCell, rc::Rc};
+
 use assets::load_assets;
 use ecs::World;
 use error::IslandResult;
 use macroquad::prelude::*;
-use prelude::ecs::plugin::Plugin;
+use prelude::ecs::{actions::ActionMap, plugin::Plugin, FixedTimestep, ScriptErrorPolicy};
 use pyo3::Python;
 
 use crate::debug::menu::DebugMenu;
 
 pub mod assets;
+pub mod atlas;
 pub mod debug;
 pub mod ecs;
 pub mod error;
+pub mod headless;
+pub mod tiled;
 
 pub mod prelude {
     pub use crate::{info, warn, error};
     pub use super::assets::*;
+    pub use super::atlas::*;
     pub use super::debug::*;
     pub use super::ecs::*;
     pub use super::error::*;
+    pub use super::headless::*;
+    pub use super::tiled::*;
     pub use super::*;
     pub use macroquad::prelude::*;
 }
@@ -32,6 +40,16 @@ pub struct IEAppDescriptor<'a> {
     pub debug_mode: bool,
     // The plugins for the app
     pub plugins: Vec<Box<dyn Plugin>>,
+    // The fixed-timestep settings, fixed updates are disabled if none
+    pub fixed_timestep: Option<FixedTimestep>,
+    // The directory script module names are relative to and imports are found in, the working directory if none
+    pub scripts_root: Option<&'a str>,
+    // What to do when a script fails, if none failing scripts are disabled in debug mode and abort otherwise
+    pub script_error_policy: Option<ScriptErrorPolicy>,
+    // The path to the input action map
+    pub input_actions: Option<&'a str>,
+    // The path to the user settings file rebound inputs are saved to and loaded from
+    pub input_settings: Option<&'a str>,
 }
 
 impl Default for IEAppDescriptor<'_> {
@@ -41,6 +59,30 @@ impl Default for IEAppDescriptor<'_> {
             assets_descriptor: None,
             debug_mode: false,
             plugins: vec![],
+            fixed_timestep: None,
+            scripts_root: None,
+            script_error_policy: None,
+            input_actions: None,
+            input_settings: None,
+        };
+    }
+}
+
+impl IEAppDescriptor<'_> {
+    /** Get the script error policy the app runs with */
+    pub(crate) fn script_error_policy(&self) -> ScriptErrorPolicy {
+        return match self.script_error_policy {
+            Some(policy) => policy,
+            None if self.debug_mode => ScriptErrorPolicy::Disable,
+            None => ScriptErrorPolicy::Abort,
+        };
+    }
+
+    /** Load the input action map of the app, an empty one if there is none */
+    pub(crate) fn action_map(&self) -> IslandResult<ActionMap> {
+        return match self.input_actions {
+            Some(path) => ActionMap::load(path, self.input_settings),
+            None => Ok(ActionMap::new()),
         };
     }
 }
@@ -57,8 +99,19 @@ pub async fn start(app_descriptor: IEAppDescriptor<'_>) -> IslandResult<()> {
     // Create the debug menu
     let mut debug_menu = DebugMenu::new();
 
-    // Create the world
-    let mut world = World::new(py, app_descriptor.first_scene, app_descriptor.plugins)?;
+    // Create the world, setting it up before loading the scene so script errors during init follow the policy
+    let script_error_policy = app_descriptor.script_error_policy();
+    let actions = app_descriptor.action_map()?;
+    let mut world = World::new(py, None, app_descriptor.plugins)?;
+    world.time.set_fixed_timestep(app_descriptor.fixed_timestep)?;
+    world.script_error_policy = script_error_policy;
+    world.actions = Rc::new(RefCell::new(actions));
+    if let Some(scripts_root) = app_descriptor.scripts_root {
+        world.set_scripts_root(scripts_root)?;
+    }
+    if let Some(first_scene) = app_descriptor.first_scene {
+        world.load_scene(first_scene)?;
+    }
 
     // The debug mode flag
     let debug_mode = app_descriptor.debug_mode;
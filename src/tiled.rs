@@ -0,0 +1,550 @@
+//! Imports maps made in the Tiled editor from its JSON format
+use std::path::{Component, Path, PathBuf};
+
+use json::JsonValue;
+
+use crate::{
+    ecs::components::{TILE_FLIPPED_HORIZONTALLY, TILE_FLIPPED_VERTICALLY, TILE_GID_MASK},
+    error::{ErrorKind, IslandError, IslandResult},
+    warn,
+};
+
+/**
+A Tiled map converted to island engine json.
+The tile layers become a tilemap entity, the objects of object layers become entities after it,
+and the tilesets become sprites and tilesets of the asset descriptor.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct TiledMap {
+    pub entities: Vec<JsonValue>,
+    // The sprites of the tilesets for the asset descriptor
+    pub sprites: Vec<JsonValue>,
+    // The tilesets for the asset descriptor, with the properties of their tiles
+    pub tilesets: Vec<JsonValue>,
+}
+
+/** A tileset of the map, for turning gids into tiles */
+struct MapTileset {
+    first_gid: u32,
+    name: String,
+    // The path of the tileset's image
+    image: String,
+    json: JsonValue,
+}
+
+impl TiledMap {
+    /**
+    Import a map saved as JSON by Tiled, external tilesets are read relative to it.
+    Only orthogonal, finite maps with CSV encoded layers are supported.
+    */
+    pub fn import<T>(path: T) -> IslandResult<Self>
+    where
+        T: Into<PathBuf>,
+    {
+        let path = path.into();
+        let map = read_tiled_json(&path)?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let name = path.file_stem().and_then(|name| name.to_str()).unwrap_or("tilemap");
+        return Self::from_json(&map, directory, name).map_err(|e| {
+            return e.context(format!("Failed to import Tiled map \"{}\"", path.display()));
+        });
+    }
+
+    /** Convert a parsed Tiled map, paths in it are relative to the directory and the tilemap entity gets the name */
+    pub fn from_json(map: &JsonValue, directory: &Path, name: &str) -> IslandResult<Self> {
+        if map["orientation"].as_str().unwrap_or("orthogonal") != "orthogonal" {
+            return Err(asset_error(format!("Only orthogonal maps are supported, found {}", map["orientation"])));
+        }
+        if map["infinite"].as_bool().unwrap_or(false) {
+            return Err(asset_error("Infinite maps are not supported"));
+        }
+
+        // Read the tilesets
+        let mut tilesets = vec![];
+        for tileset in map["tilesets"].members() {
+            let first_gid = tileset["firstgid"]
+                .as_u32()
+                .ok_or_else(|| asset_error(format!("Tileset has no firstgid: {}", tileset)))?;
+            let (json, tileset_directory) = match tileset["source"].as_str() {
+                Some(source) => {
+                    let path = directory.join(source);
+                    let json = read_tiled_json(&path)?;
+                    (json, path.parent().map(Path::to_path_buf).unwrap_or_default())
+                }
+                None => (tileset.clone(), directory.to_path_buf()),
+            };
+            tilesets.push(convert_tileset(first_gid, &json, &tileset_directory)?);
+        }
+
+        let mut result = Self {
+            entities: vec![],
+            sprites: vec![],
+            tilesets: vec![],
+        };
+        let mut tilemap = json::object! {
+            tile_size: {
+                x: map["tilewidth"].as_f32().unwrap_or(0.0),
+                y: map["tileheight"].as_f32().unwrap_or(0.0),
+            },
+            width: map["width"].as_usize().unwrap_or(0),
+            height: map["height"].as_usize().unwrap_or(0),
+            tilesets: JsonValue::new_array(),
+            layers: JsonValue::new_array(),
+        };
+        for tileset in tilesets.iter() {
+            tilemap["tilesets"].push(json::object! {
+                first_gid: tileset.first_gid,
+                name: tileset.name.clone(),
+            })
+            .unwrap();
+            result.sprites.push(json::object! {
+                name: tileset.name.clone(),
+                path: tileset.image.clone(),
+                filter: "nearest",
+            });
+        }
+        result.tilesets = tilesets.iter().map(|tileset| tileset.json.clone()).collect();
+
+        let mut objects = vec![];
+        convert_layers(&map["layers"], &tilesets, &mut tilemap, &mut objects)?;
+        result.entities.push(json::object! {
+            name: name,
+            position: { x: 0.0, y: 0.0 },
+            scale: { x: 1.0, y: 1.0 },
+            tilemap: tilemap,
+        });
+        result.entities.extend(objects);
+        return Ok(result);
+    }
+
+    /** Get a scene with the entities of the map */
+    pub fn scene(&self) -> JsonValue {
+        return json::object! {
+            entities: self.entities.clone(),
+        };
+    }
+
+    /** Add the sprites and tilesets to an asset descriptor, replacing the ones with the same names */
+    pub fn merge_assets(&self, asset_descriptor: &mut JsonValue) -> IslandResult<()> {
+        if !asset_descriptor.is_object() {
+            return Err(asset_error(format!("Asset descriptor has to be a json object: {}", asset_descriptor)));
+        }
+        for (key, values) in [("sprites", &self.sprites), ("tilesets", &self.tilesets)].iter() {
+            if !asset_descriptor[*key].is_array() {
+                asset_descriptor[*key] = JsonValue::new_array();
+            }
+            let list = &mut asset_descriptor[*key];
+            for value in values.iter() {
+                let index = list.members().position(|existing| existing["name"] == value["name"]);
+                match index {
+                    Some(index) => list[index] = value.clone(),
+                    None => list.push(value.clone()).unwrap(),
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+/** Read and parse a Tiled JSON file, the XML formats are refused */
+fn read_tiled_json(path: &Path) -> IslandResult<JsonValue> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+    if extension == "tmx" || extension == "tsx" {
+        return Err(asset_error(format!(
+            "\"{}\" is a Tiled XML file, save it in the JSON format",
+            path.display()
+        )));
+    }
+    let file = std::fs::read_to_string(path).map_err(|e| {
+        return IslandError::with_source(ErrorKind::Io, format!("Failed to read \"{}\"", path.display()), e);
+    })?;
+    return json::parse(&file).map_err(|e| {
+        return IslandError::with_source(ErrorKind::Json, format!("Failed to parse \"{}\"", path.display()), e);
+    });
+}
+
+/** Convert a Tiled tileset into an asset descriptor tileset, its sprite has the name of the tileset */
+fn convert_tileset(first_gid: u32, tileset: &JsonValue, directory: &Path) -> IslandResult<MapTileset> {
+    let name = tileset["name"]
+        .as_str()
+        .ok_or_else(|| asset_error(format!("Tileset has no name: {}", tileset)))?;
+    let image = tileset["image"].as_str().ok_or_else(|| {
+        return asset_error(format!(
+            "Tileset \"{}\" in \"{}\" has no image, image collection tilesets are not supported",
+            name,
+            directory.display()
+        ));
+    })?;
+
+    let mut tiles = JsonValue::new_object();
+    for tile in tileset["tiles"].members() {
+        let properties = convert_properties(&tile["properties"]);
+        if !properties.is_empty() {
+            tiles[tile["id"].as_u32().unwrap_or_default().to_string()] = properties;
+        }
+    }
+    let mut json = json::object! {
+        name: name,
+        sprite: name,
+        tile_size: {
+            x: tileset["tilewidth"].as_f32().unwrap_or(0.0),
+            y: tileset["tileheight"].as_f32().unwrap_or(0.0),
+        },
+        columns: tileset["columns"].as_u32().unwrap_or(1),
+        count: tileset["tilecount"].as_u32().unwrap_or(0),
+        margin: tileset["margin"].as_f32().unwrap_or(0.0),
+        spacing: tileset["spacing"].as_f32().unwrap_or(0.0),
+    };
+    if !tiles.is_empty() {
+        json["tiles"] = tiles;
+    }
+    return Ok(MapTileset {
+        first_gid,
+        name: name.into(),
+        image: join_path(directory, image),
+        json,
+    });
+}
+
+/** Join a path relative to a directory, resolving `..` where it can, with forward slashes like asset paths */
+fn join_path(directory: &Path, path: &str) -> String {
+    let mut result = PathBuf::new();
+    for component in directory.join(path).components() {
+        match component {
+            Component::ParentDir if matches!(result.components().next_back(), Some(Component::Normal(_))) => {
+                result.pop();
+            }
+            Component::CurDir => {}
+            component => result.push(component),
+        }
+    }
+    return result.to_string_lossy().replace('\\', "/");
+}
+
+/** Convert a list of Tiled properties into an object of the values by name */
+fn convert_properties(properties: &JsonValue) -> JsonValue {
+    let mut result = JsonValue::new_object();
+    for property in properties.members() {
+        if let Some(name) = property["name"].as_str() {
+            result[name] = property["value"].clone();
+        }
+    }
+    return result;
+}
+
+/**
+Add the tile layers to the tilemap and the objects of object layers to the entities, in the order of the map.
+Group layers are flattened. The integer property `layer` of a layer sets the layer its tiles and sprites are drawn on.
+*/
+fn convert_layers(
+    layers: &JsonValue,
+    tilesets: &[MapTileset],
+    tilemap: &mut JsonValue,
+    objects: &mut Vec<JsonValue>,
+) -> IslandResult<()> {
+    for layer in layers.members() {
+        let name = layer["name"].as_str().unwrap_or_default();
+        let properties = convert_properties(&layer["properties"]);
+        let draw_layer = properties["layer"].as_i32().unwrap_or(0);
+        match layer["type"].as_str().unwrap_or_default() {
+            "tilelayer" => {
+                if !layer["data"].is_array() {
+                    return Err(asset_error(format!(
+                        "Tile layer \"{}\" is not CSV encoded, change its tile layer format",
+                        name
+                    )));
+                }
+                let mut json = json::object! {
+                    name: name,
+                    data: layer["data"].clone(),
+                };
+                if draw_layer != 0 {
+                    json["layer"] = draw_layer.into();
+                }
+                if layer["visible"].as_bool() == Some(false) {
+                    json["visible"] = false.into();
+                }
+                if let Some(opacity) = layer["opacity"].as_f32().filter(|opacity| *opacity != 1.0) {
+                    json["opacity"] = opacity.into();
+                }
+                tilemap["layers"].push(json).unwrap();
+            }
+            "objectgroup" => {
+                for object in layer["objects"].members() {
+                    objects.push(convert_object(object, tilesets, draw_layer));
+                }
+            }
+            "group" => convert_layers(&layer["layers"], tilesets, tilemap, objects)?,
+            other => warn!("Skipped Tiled layer \"{}\", {} layers are not supported", name, other),
+        }
+    }
+    return Ok(());
+}
+
+/**
+Convert a Tiled object into an entity.
+The class becomes the tag, the shape is kept in a `shape` component and the custom properties in `properties`.
+Tile objects get a sprite of their tile, their position is the bottom left corner like in Tiled.
+*/
+fn convert_object(object: &JsonValue, tilesets: &[MapTileset], draw_layer: i32) -> JsonValue {
+    let mut entity = json::object! {
+        position: {
+            x: object["x"].as_f32().unwrap_or(0.0),
+            y: object["y"].as_f32().unwrap_or(0.0),
+        },
+        scale: { x: 1.0, y: 1.0 },
+    };
+    if let Some(name) = object["name"].as_str().filter(|name| !name.is_empty()) {
+        entity["name"] = name.into();
+    }
+    // Tiled calls the type class since 1.9
+    let class = object["class"].as_str().or_else(|| object["type"].as_str());
+    if let Some(class) = class.filter(|class| !class.is_empty()) {
+        entity["tag"] = class.into();
+    }
+
+    let size = json::object! {
+        x: object["width"].as_f32().unwrap_or(0.0),
+        y: object["height"].as_f32().unwrap_or(0.0),
+    };
+    let points = |key: &str| {
+        return object[key]
+            .members()
+            .map(|point| json::object! { x: point["x"].clone(), y: point["y"].clone() })
+            .collect::<Vec<_>>();
+    };
+    entity["shape"] = if object["point"].as_bool() == Some(true) {
+        json::object! { kind: "point" }
+    } else if object["ellipse"].as_bool() == Some(true) {
+        json::object! { kind: "ellipse", size: size.clone() }
+    } else if object.has_key("polygon") {
+        json::object! { kind: "polygon", points: points("polygon") }
+    } else if object.has_key("polyline") {
+        json::object! { kind: "polyline", points: points("polyline") }
+    } else {
+        json::object! { kind: "rectangle", size: size.clone() }
+    };
+    if object["rotation"].as_f32().unwrap_or(0.0) != 0.0 {
+        entity["shape"]["rotation"] = object["rotation"].as_f32().unwrap_or(0.0).to_radians().into();
+    }
+
+    // Tile objects show their tile
+    if let Some(gid) = object["gid"].as_u32() {
+        let tile = gid & TILE_GID_MASK;
+        let tileset = tilesets
+            .iter()
+            .filter(|tileset| tileset.first_gid <= tile)
+            .max_by_key(|tileset| tileset.first_gid);
+        if let Some(tileset) = tileset {
+            let id = tile - tileset.first_gid;
+            let columns = tileset.json["columns"].as_u32().unwrap_or(1).max(1);
+            let (tile_width, tile_height) = (
+                tileset.json["tile_size"]["x"].as_f32().unwrap_or(0.0),
+                tileset.json["tile_size"]["y"].as_f32().unwrap_or(0.0),
+            );
+            let (margin, spacing) = (
+                tileset.json["margin"].as_f32().unwrap_or(0.0),
+                tileset.json["spacing"].as_f32().unwrap_or(0.0),
+            );
+            let mut sprite = json::object! {
+                texture: tileset.name.clone(),
+                dest_size: size,
+                source_rec: {
+                    x: margin + (id % columns) as f32 * (tile_width + spacing),
+                    y: margin + (id / columns) as f32 * (tile_height + spacing),
+                    w: tile_width,
+                    h: tile_height,
+                },
+                flip_x: gid & TILE_FLIPPED_HORIZONTALLY != 0,
+                flip_y: gid & TILE_FLIPPED_VERTICALLY != 0,
+                pivot: { x: 0.0, y: 1.0 },
+            };
+            if entity["shape"].has_key("rotation") {
+                sprite["rotation"] = entity["shape"]["rotation"].clone();
+            }
+            if draw_layer != 0 {
+                sprite["layer"] = draw_layer.into();
+            }
+            entity["sprite"] = sprite;
+        }
+    }
+
+    let properties = convert_properties(&object["properties"]);
+    if !properties.is_empty() {
+        entity["properties"] = properties;
+    }
+    return entity;
+}
+
+/** Construct an asset error */
+fn asset_error<T>(reason: T) -> IslandError
+where
+    T: Into<String>,
+{
+    return IslandError::with_kind(ErrorKind::Asset, reason);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::components::{Component, Tilemap};
+
+    /** A 2x2 map with two tilesets, a flipped tile and an object layer */
+    fn map() -> JsonValue {
+        return json::object! {
+            orientation: "orthogonal",
+            width: 2,
+            height: 2,
+            tilewidth: 16,
+            tileheight: 16,
+            tilesets: [
+                {
+                    firstgid: 1,
+                    name: "ground",
+                    image: "../images/ground.png",
+                    tilewidth: 16,
+                    tileheight: 16,
+                    columns: 2,
+                    tilecount: 4,
+                    tiles: [{ id: 3, properties: [{ name: "collision", type: "bool", value: true }] }],
+                },
+                {
+                    firstgid: 5,
+                    name: "props",
+                    image: "props.png",
+                    tilewidth: 8,
+                    tileheight: 8,
+                    columns: 4,
+                    tilecount: 8,
+                    margin: 1,
+                    spacing: 2,
+                },
+            ],
+            layers: [
+                {
+                    type: "group",
+                    name: "level",
+                    layers: [{
+                        type: "tilelayer",
+                        name: "floor",
+                        properties: [{ name: "layer", type: "int", value: -1 }],
+                        data: [1, 0, 4, TILE_FLIPPED_HORIZONTALLY | 6],
+                    }],
+                },
+                {
+                    type: "objectgroup",
+                    name: "things",
+                    objects: [
+                        {
+                            name: "door",
+                            class: "exit",
+                            x: 10,
+                            y: 20,
+                            width: 16,
+                            height: 32,
+                            rotation: 90,
+                            properties: [{ name: "target", type: "string", value: "level2.json" }],
+                        },
+                        { type: "spawn", x: 1, y: 2, point: true },
+                        { x: 0, y: 0, ellipse: true, width: 4, height: 6 },
+                        { x: 0, y: 0, polygon: [{ x: 0, y: 0 }, { x: 4, y: 0 }, { x: 0, y: 4 }] },
+                        { x: 30, y: 40, width: 8, height: 8, gid: TILE_FLIPPED_VERTICALLY | 7 },
+                    ],
+                },
+            ],
+        };
+    }
+
+    fn import() -> TiledMap {
+        return TiledMap::from_json(&map(), Path::new("maps/levels"), "level1").unwrap();
+    }
+
+    #[test]
+    fn tile_layers_keep_their_flip_flags_and_resolve_to_the_right_tileset() {
+        let imported = import();
+        let tilemap = &imported.entities[0];
+        assert_eq!(tilemap["name"], "level1");
+        let layer = &tilemap["tilemap"]["layers"][0];
+        assert_eq!(layer["name"], "floor");
+        assert_eq!(layer["layer"], -1);
+        assert_eq!(layer["data"][3].as_u32(), Some(TILE_FLIPPED_HORIZONTALLY | 6));
+
+        let tilemap = Tilemap::from_json(&tilemap["tilemap"]).unwrap();
+        let resolve = |gid: u32| tilemap.resolve(gid).map(|(tileset, id)| (tileset.name.clone(), id));
+        assert_eq!(resolve(0), None);
+        assert_eq!(resolve(1), Some(("ground".to_string(), 0)));
+        assert_eq!(resolve(4), Some(("ground".to_string(), 3)));
+        assert_eq!(resolve(5), Some(("props".to_string(), 0)));
+        assert_eq!(resolve(TILE_FLIPPED_HORIZONTALLY | 6), Some(("props".to_string(), 1)));
+    }
+
+    #[test]
+    fn tilesets_become_sprites_and_tilesets_with_tile_properties() {
+        let imported = import();
+        assert_eq!(imported.sprites.len(), 2);
+        assert_eq!(imported.sprites[0]["path"], "maps/images/ground.png");
+        assert_eq!(imported.sprites[1]["path"], "maps/levels/props.png");
+        assert_eq!(imported.tilesets[0]["tiles"]["3"]["collision"], true);
+        assert_eq!(imported.tilesets[1]["margin"], 1.0);
+        assert!(!imported.tilesets[1].has_key("tiles"));
+    }
+
+    #[test]
+    fn objects_become_entities_with_their_shape_and_properties() {
+        let imported = import();
+        assert_eq!(imported.entities.len(), 6);
+
+        let door = &imported.entities[1];
+        assert_eq!(door["name"], "door");
+        assert_eq!(door["tag"], "exit");
+        assert_eq!(door["position"]["x"], 10.0);
+        assert_eq!(door["shape"]["kind"], "rectangle");
+        assert_eq!(door["shape"]["size"]["y"], 32.0);
+        assert!((door["shape"]["rotation"].as_f32().unwrap() - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+        assert_eq!(door["properties"]["target"], "level2.json");
+        assert!(!door.has_key("sprite"));
+
+        // The type is the old name of the class
+        assert_eq!(imported.entities[2]["tag"], "spawn");
+        assert_eq!(imported.entities[2]["shape"]["kind"], "point");
+        assert_eq!(imported.entities[3]["shape"]["kind"], "ellipse");
+        assert_eq!(imported.entities[4]["shape"]["kind"], "polygon");
+        assert_eq!(imported.entities[4]["shape"]["points"].len(), 3);
+    }
+
+    #[test]
+    fn tile_objects_get_a_sprite_of_their_tile_with_its_flip_flags() {
+        let imported = import();
+        let sprite = &imported.entities[5]["sprite"];
+        assert_eq!(sprite["texture"], "props");
+        assert_eq!(sprite["flip_x"], false);
+        assert_eq!(sprite["flip_y"], true);
+        // The third tile of the props tileset, after the margin and a tile and its spacing
+        assert_eq!(sprite["source_rec"]["x"], 1.0 + 2.0 * 10.0);
+        assert_eq!(sprite["source_rec"]["y"], 1.0);
+        assert_eq!(sprite["source_rec"]["w"], 8.0);
+    }
+
+    #[test]
+    fn unsupported_maps_are_refused() {
+        let mut map = map();
+        map["orientation"] = "isometric".into();
+        assert!(TiledMap::from_json(&map, Path::new(""), "map").is_err());
+
+        let mut map = self::map();
+        map["layers"][0]["layers"][0]["data"] = "eJxjYGBgAAAABAAB".into();
+        assert!(TiledMap::from_json(&map, Path::new(""), "map").is_err());
+    }
+
+    #[test]
+    fn merging_assets_replaces_entries_with_the_same_name() {
+        let imported = import();
+        let mut assets = json::object! {
+            sprites: [{ name: "ground", path: "old.png" }, { name: "player", path: "player.png" }],
+        };
+        imported.merge_assets(&mut assets).unwrap();
+        assert_eq!(assets["sprites"].len(), 3);
+        assert_eq!(assets["sprites"][0]["path"], "maps/images/ground.png");
+        assert_eq!(assets["tilesets"].len(), 2);
+    }
+}
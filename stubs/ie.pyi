@@ -0,0 +1,493 @@
+"""The engine types for the python scripts"""
+# The type stubs of the scripting api of ie 0.1.0, generated from the engine source by its build script.
+# Don't edit them by hand, write them out with `cargo run --bin ie-stubs` instead.
+from typing import Any, ClassVar, Dict, List, Optional, Tuple, Union
+
+__version__: str
+
+
+class Vec2:
+    """A 2d vector"""
+    x: float
+    y: float
+    def __init__(self, x: float = 0.0, y: float = 0.0) -> None:
+        """Construct a new vector"""
+    @staticmethod
+    def from_dict(dict: Any) -> Vec2:
+        """Read a vector from a `{"x": ..., "y": ...}` dict, like the position of an entity"""
+    def to_dict(self) -> Any:
+        """Get the vector as a `{"x": ..., "y": ...}` dict"""
+    def length(self) -> float:
+        """Get the length of the vector"""
+    def normalized(self) -> Vec2:
+        """Get the vector scaled to a length of 1, or zero if it has no length"""
+    def dot(self, other: Vec2) -> float:
+        """Get the dot product with another vector"""
+    def distance(self, other: Vec2) -> float:
+        """Get the distance to another vector"""
+    def lerp(self, other: Vec2, t: float) -> Vec2:
+        """Get the vector `t` of the way to another vector"""
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __add__(self, other: Vec2) -> Vec2: ...
+    def __sub__(self, other: Vec2) -> Vec2: ...
+    def __mul__(self, other: float) -> Vec2: ...
+    def __rmul__(self, other: float) -> Vec2: ...
+    def __truediv__(self, other: float) -> Vec2: ...
+    def __neg__(self) -> Vec2: ...
+
+
+class Rect:
+    """A rectangle from its top left corner and size"""
+    x: float
+    y: float
+    w: float
+    h: float
+    def __init__(self, x: float = 0.0, y: float = 0.0, w: float = 0.0, h: float = 0.0) -> None:
+        """Construct a new rectangle"""
+    @staticmethod
+    def from_dict(dict: Any) -> Rect:
+        """Read a rectangle from a `{"x": ..., "y": ..., "w": ..., "h": ...}` dict, like the source of a sprite"""
+    def to_dict(self) -> Any:
+        """Get the rectangle as a `{"x": ..., "y": ..., "w": ..., "h": ...}` dict"""
+    def center(self) -> Vec2:
+        """Get the center of the rectangle"""
+    def contains(self, point: Vec2) -> bool:
+        """Check if a point is inside the rectangle"""
+    def overlaps(self, other: Rect) -> bool:
+        """Check if the rectangle overlaps another one"""
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+
+
+class Color:
+    """A color with channels from 0 to 1"""
+    r: float
+    g: float
+    b: float
+    a: float
+    def __init__(self, r: float, g: float, b: float, a: float = 1.0) -> None:
+        """Construct a new color"""
+    @staticmethod
+    def from_rgba8(r: int, g: int, b: int, a: int = 255) -> Color:
+        """Construct a color from channels from 0 to 255"""
+    @staticmethod
+    def from_dict(dict: Any) -> Color:
+        """Read a color from a `{"r": ..., "g": ..., "b": ..., "a": ...}` dict, alpha defaults to 1"""
+    def to_dict(self) -> Any:
+        """Get the color as a `{"r": ..., "g": ..., "b": ..., "a": ...}` dict"""
+    WHITE: ClassVar[Color]
+    BLACK: ClassVar[Color]
+    RED: ClassVar[Color]
+    GREEN: ClassVar[Color]
+    BLUE: ClassVar[Color]
+    YELLOW: ClassVar[Color]
+    TRANSPARENT: ClassVar[Color]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+
+
+class Entity:
+    """
+    A handle to an entity.
+    It can be used anywhere an entity id is expected and compares equal to its id.
+    """
+    @property
+    def id(self) -> int: ...
+    def __init__(self, id: Union[Entity, int]) -> None:
+        """Construct a handle to the entity with the id"""
+    def __repr__(self) -> str: ...
+    def __hash__(self) -> int: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __index__(self) -> int: ...
+
+
+class RayHit:
+    """Where a ray cast by the context hit a collider"""
+    @property
+    def entity(self) -> Entity: ...
+    @property
+    def point(self) -> Vec2: ...
+    @property
+    def normal(self) -> Vec2: ...
+    @property
+    def distance(self) -> float: ...
+    def __repr__(self) -> str: ...
+
+
+class WorldState:
+    """The state of the world, it compares equal to its name"""
+    Editor: ClassVar[WorldState]
+    Menu: ClassVar[WorldState]
+    Gameplay: ClassVar[WorldState]
+    def __repr__(self) -> str: ...
+    def __str__(self) -> str: ...
+    def __hash__(self) -> int: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+
+
+class Key:
+    """The names of the keys, for the key functions of the context"""
+    Space: ClassVar[str]
+    Apostrophe: ClassVar[str]
+    Comma: ClassVar[str]
+    Minus: ClassVar[str]
+    Period: ClassVar[str]
+    Slash: ClassVar[str]
+    Key0: ClassVar[str]
+    Key1: ClassVar[str]
+    Key2: ClassVar[str]
+    Key3: ClassVar[str]
+    Key4: ClassVar[str]
+    Key5: ClassVar[str]
+    Key6: ClassVar[str]
+    Key7: ClassVar[str]
+    Key8: ClassVar[str]
+    Key9: ClassVar[str]
+    Semicolon: ClassVar[str]
+    Equal: ClassVar[str]
+    A: ClassVar[str]
+    B: ClassVar[str]
+    C: ClassVar[str]
+    D: ClassVar[str]
+    E: ClassVar[str]
+    F: ClassVar[str]
+    G: ClassVar[str]
+    H: ClassVar[str]
+    I: ClassVar[str]
+    J: ClassVar[str]
+    K: ClassVar[str]
+    L: ClassVar[str]
+    M: ClassVar[str]
+    N: ClassVar[str]
+    O: ClassVar[str]
+    P: ClassVar[str]
+    Q: ClassVar[str]
+    R: ClassVar[str]
+    S: ClassVar[str]
+    T: ClassVar[str]
+    U: ClassVar[str]
+    V: ClassVar[str]
+    W: ClassVar[str]
+    X: ClassVar[str]
+    Y: ClassVar[str]
+    Z: ClassVar[str]
+    LeftBracket: ClassVar[str]
+    Backslash: ClassVar[str]
+    RightBracket: ClassVar[str]
+    GraveAccent: ClassVar[str]
+    World1: ClassVar[str]
+    World2: ClassVar[str]
+    Escape: ClassVar[str]
+    Enter: ClassVar[str]
+    Tab: ClassVar[str]
+    Backspace: ClassVar[str]
+    Insert: ClassVar[str]
+    Delete: ClassVar[str]
+    Right: ClassVar[str]
+    Left: ClassVar[str]
+    Down: ClassVar[str]
+    Up: ClassVar[str]
+    PageUp: ClassVar[str]
+    PageDown: ClassVar[str]
+    Home: ClassVar[str]
+    End: ClassVar[str]
+    CapsLock: ClassVar[str]
+    ScrollLock: ClassVar[str]
+    NumLock: ClassVar[str]
+    PrintScreen: ClassVar[str]
+    Pause: ClassVar[str]
+    F1: ClassVar[str]
+    F2: ClassVar[str]
+    F3: ClassVar[str]
+    F4: ClassVar[str]
+    F5: ClassVar[str]
+    F6: ClassVar[str]
+    F7: ClassVar[str]
+    F8: ClassVar[str]
+    F9: ClassVar[str]
+    F10: ClassVar[str]
+    F11: ClassVar[str]
+    F12: ClassVar[str]
+    F13: ClassVar[str]
+    F14: ClassVar[str]
+    F15: ClassVar[str]
+    F16: ClassVar[str]
+    F17: ClassVar[str]
+    F18: ClassVar[str]
+    F19: ClassVar[str]
+    F20: ClassVar[str]
+    F21: ClassVar[str]
+    F22: ClassVar[str]
+    F23: ClassVar[str]
+    F24: ClassVar[str]
+    F25: ClassVar[str]
+    Kp0: ClassVar[str]
+    Kp1: ClassVar[str]
+    Kp2: ClassVar[str]
+    Kp3: ClassVar[str]
+    Kp4: ClassVar[str]
+    Kp5: ClassVar[str]
+    Kp6: ClassVar[str]
+    Kp7: ClassVar[str]
+    Kp8: ClassVar[str]
+    Kp9: ClassVar[str]
+    KpDecimal: ClassVar[str]
+    KpDivide: ClassVar[str]
+    KpMultiply: ClassVar[str]
+    KpSubtract: ClassVar[str]
+    KpAdd: ClassVar[str]
+    KpEnter: ClassVar[str]
+    KpEqual: ClassVar[str]
+    LeftShift: ClassVar[str]
+    LeftControl: ClassVar[str]
+    LeftAlt: ClassVar[str]
+    LeftSuper: ClassVar[str]
+    RightShift: ClassVar[str]
+    RightControl: ClassVar[str]
+    RightAlt: ClassVar[str]
+    RightSuper: ClassVar[str]
+    Menu: ClassVar[str]
+
+
+class MouseButton:
+    """The names of the mouse buttons, for the mouse functions of the context"""
+    Left: ClassVar[str]
+    Right: ClassVar[str]
+    Middle: ClassVar[str]
+
+
+class GamepadButton:
+    """The names of the gamepad buttons, for the gamepad functions of the context, `South` to `North` are the face buttons by position"""
+    A: ClassVar[str]
+    B: ClassVar[str]
+    X: ClassVar[str]
+    Y: ClassVar[str]
+    South: ClassVar[str]
+    East: ClassVar[str]
+    West: ClassVar[str]
+    North: ClassVar[str]
+    LeftBumper: ClassVar[str]
+    RightBumper: ClassVar[str]
+    Back: ClassVar[str]
+    Start: ClassVar[str]
+    Guide: ClassVar[str]
+    LeftStick: ClassVar[str]
+    RightStick: ClassVar[str]
+    DPadUp: ClassVar[str]
+    DPadDown: ClassVar[str]
+    DPadLeft: ClassVar[str]
+    DPadRight: ClassVar[str]
+
+
+class GamepadAxis:
+    """The names of the gamepad axes, for `gamepad_axis` of the context"""
+    LeftX: ClassVar[str]
+    LeftY: ClassVar[str]
+    RightX: ClassVar[str]
+    RightY: ClassVar[str]
+    LeftTrigger: ClassVar[str]
+    RightTrigger: ClassVar[str]
+
+
+class Scene:
+    """
+    The scene passed to the scripts, a dict-like view of the world with the `camera`, the `entities` and the
+    other scene-level values.
+    Changes to the values and components are written to the world when the script returns,
+    spawning and despawning entities through the entity list happens right away.
+    """
+    def keys(self) -> List[str]:
+        """Get the keys of the scene"""
+    def get(self, key: str, default: Optional[Any] = None) -> Any:
+        """Get the value of a key, the default if the scene does not have it"""
+    def to_dict(self) -> Any:
+        """Copy the whole scene into a dict, the way it is saved"""
+    def __len__(self) -> int: ...
+    def __getitem__(self, other: Any) -> Any: ...
+    def __setitem__(self, key: Any, value: Any) -> None: ...
+    def __delitem__(self, other: Any) -> None: ...
+    def __contains__(self, other: Any) -> bool: ...
+    def __iter__(self) -> Any: ...
+    def __repr__(self) -> str: ...
+
+
+class EntityList:
+    """
+    The entities of the scene, a list-like view of the world in scene order.
+    Appending an entity spawns it right away, removing one despawns it and the last entity takes its place.
+    """
+    def append(self, entity: Any) -> SceneEntity:
+        """Spawn an entity from a dict right away, expanding its prefab, returns the spawned entity"""
+    def extend(self, entities: Any) -> None:
+        """Spawn each entity of an iterable right away"""
+    def remove(self, entity: Any) -> None:
+        """Despawn an entity right away, raising an error if it is not in the scene"""
+    def pop(self, index: int = -1) -> Any:
+        """Despawn the entity at the index right away, returns it as a dict"""
+    def clear(self) -> None:
+        """Despawn all entities right away"""
+    def __len__(self) -> int: ...
+    def __getitem__(self, other: Any) -> Any: ...
+    def __setitem__(self, index: Any, value: Any) -> None: ...
+    def __delitem__(self, other: Any) -> None: ...
+    def __contains__(self, other: Any) -> bool: ...
+    def __iter__(self) -> Any: ...
+    def __repr__(self) -> str: ...
+
+
+class SceneEntity:
+    """
+    An entity of the scene, a dict-like view of its components with its `id`.
+    Changes to the components are written to the world when the script returns, removing a component happens right away.
+    It can be used anywhere an entity id is expected and compares equal to its id.
+    """
+    @property
+    def id(self) -> int: ...
+    def keys(self) -> List[str]:
+        """Get the keys of the entity, the id followed by the names of its components"""
+    def values(self) -> List[Any]:
+        """Get the values of the keys of the entity"""
+    def items(self) -> List[Tuple[str, Any]]:
+        """Get the keys of the entity with their values"""
+    def get(self, key: str, default: Optional[Any] = None) -> Any:
+        """Get a component, the default if the entity does not have it"""
+    def to_dict(self) -> Any:
+        """Copy the entity into a dict, the way it is saved"""
+    def __len__(self) -> int: ...
+    def __getitem__(self, other: Any) -> Any: ...
+    def __setitem__(self, key: Any, value: Any) -> None: ...
+    def __delitem__(self, other: Any) -> None: ...
+    def __contains__(self, other: Any) -> bool: ...
+    def __iter__(self) -> Any: ...
+    def __repr__(self) -> str: ...
+    def __hash__(self) -> int: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __index__(self) -> int: ...
+
+
+class HighgroundCtx:
+    """The context that gets passed into the python scripts"""
+    def delta_time(self) -> float:
+        """Get the delta time, the fixed step inside `fixed_update`"""
+    def fps(self) -> int:
+        """Get the fps"""
+    def frame_time(self) -> float:
+        """Get the frame time"""
+    def interpolation_alpha(self) -> float:
+        """Get how far the clock is between the last and the next fixed update, from 0 to 1"""
+    def log_info(self, log: str) -> None:
+        """Push an info log"""
+    def log_warn(self, log: str) -> None:
+        """Push a warning log"""
+    def log_error(self, log: str) -> None:
+        """Push an error log"""
+    def is_key_pressed(self, key: str) -> bool:
+        """Check if the key is pressed on the current frame"""
+    def is_key_released(self, key: str) -> bool:
+        """Check if the key is released on the current frame"""
+    def is_key_down(self, key: str) -> bool:
+        """Check if the key id held down"""
+    def mouse_position(self) -> Vec2:
+        """Get the mouse position in pixels from the top left of the screen"""
+    def mouse_world_position(self) -> Vec2:
+        """Get the position in the world under the mouse, as seen through the scene camera"""
+    def is_mouse_button_pressed(self, button: str) -> bool:
+        """Check if the mouse button is pressed on the current frame"""
+    def is_mouse_button_released(self, button: str) -> bool:
+        """Check if the mouse button is released on the current frame"""
+    def is_mouse_button_down(self, button: str) -> bool:
+        """Check if the mouse button is held down"""
+    def mouse_wheel(self) -> Vec2:
+        """Get how far the mouse wheel scrolled on the current frame"""
+    def text_input(self) -> str:
+        """Get the text typed on the current frame"""
+    def is_gamepad_connected(self, gamepad: int = 0) -> bool:
+        """Check if the gamepad is connected"""
+    def is_gamepad_button_pressed(self, button: str, gamepad: int = 0) -> bool:
+        """Check if the gamepad button is pressed on the current frame"""
+    def is_gamepad_button_released(self, button: str, gamepad: int = 0) -> bool:
+        """Check if the gamepad button is released on the current frame"""
+    def is_gamepad_button_down(self, button: str, gamepad: int = 0) -> bool:
+        """Check if the gamepad button is held down"""
+    def gamepad_axis(self, axis: str, gamepad: int = 0) -> float:
+        """Get the value of the gamepad axis, sticks go from -1 to 1 and triggers from 0 to 1"""
+    def is_action_pressed(self, action: str) -> bool:
+        """Check if any input of the action is pressed on the current frame"""
+    def is_action_released(self, action: str) -> bool:
+        """Check if any input of the action is released on the current frame"""
+    def is_action_down(self, action: str) -> bool:
+        """Check if any input of the action is held down"""
+    def axis(self, axis: str) -> float:
+        """Get the value of the axis from -1 to 1"""
+    def action_bindings(self, action: str) -> List[str]:
+        """Get the names of the inputs bound to the action"""
+    def axis_bindings(self, axis: str) -> Tuple[List[str], List[str]]:
+        """Get the names of the negative and the positive inputs bound to the axis"""
+    def rebind_action(self, action: str, bindings: List[str]) -> None:
+        """Bind the action to the inputs instead, the bindings are saved to the input settings file"""
+    def rebind_axis(self, axis: str, negative: List[str], positive: List[str]) -> None:
+        """Bind the axis to the negative and positive inputs instead, the bindings are saved to the input settings file"""
+    def spawn_entity(self, entity_input: str) -> Optional[Entity]:
+        """Spawn an entity in the scene, returns the id it will receive"""
+    def despawn_entity(self, id: Union[Entity, int]) -> None:
+        """
+        Despawn the entity with the id.
+        The entity is removed after the script returns, before the next script runs.
+        """
+    def play_animation(self, id: Union[Entity, int], clip: str, restart: bool = False) -> None:
+        """
+        Play an animation clip from the asset descriptor on the entity.
+        A clip that is already playing keeps going unless `restart` is set.
+        The animation is set after the script returns, like despawns.
+        """
+    def finished_animations(self) -> List[Tuple[Entity, str]]:
+        """Get the entities whose animation clip ended this frame, with the name of the clip"""
+    def get_entity(self, id: Union[Entity, int]) -> Optional[SceneEntity]:
+        """Get the entity with the id from the scene, none if it does not exist"""
+    def find_by_name(self, name: str) -> Optional[Entity]:
+        """Find the first entity with the name"""
+    def find_by_tag(self, tag: str) -> List[Entity]:
+        """Find all entities with the tag"""
+    def tiles_at(self, x: float, y: float) -> List[Any]:
+        """
+        Get the tiles of the tilemaps at a position in the world, the topmost first.
+        Each tile is a dict of the `entity`, the `layer` name, the `column`, `row` and `gid` and the `properties` of
+        the tile in its tileset, like `collision`.
+        """
+    def is_solid_at(self, x: float, y: float) -> bool:
+        """Check if any tile at a position in the world has its `collision` property set"""
+    def overlap_point(self, point: Vec2) -> List[Entity]:
+        """
+        Get the entities whose collider contains a point in the world, in scene order.
+        The queries see the colliders as they were when the stage started, moving an entity does not move its collider.
+        """
+    def overlap_rect(self, rect: Rect) -> List[Entity]:
+        """Get the entities whose collider overlaps a rect in the world, in scene order"""
+    def raycast(self, origin: Vec2, direction: Vec2, max_distance: Optional[float] = None) -> Optional[RayHit]:
+        """
+        Cast a ray from an origin along a direction and get the first collider it hits, none if it hits nothing.
+        Without a max distance the ray goes on until it leaves the colliders behind.
+        """
+    def save_scene(self, path: str) -> None:
+        """Save the scene to a file once the current script stage has finished"""
+    def change_scene(self, path: str) -> None:
+        """
+        Switch to another scene at the end of the frame.
+        The scripts of the current scene get their `exit` stage called before it is unloaded.
+        If the scene fails to load, the current scene stays and the error is handled like an exception of the script.
+        """
+    def globals(self) -> Any:
+        """Get the dict of values that are kept when switching scenes"""
+    def entity_id(self) -> Optional[Entity]:
+        """Get the id of the entity the script is attached to, none for scene scripts"""
+    def state(self) -> Optional[Any]:
+        """Get the dict that is kept for the entity script instance between calls, none for scene scripts"""
+    def world_state(self) -> WorldState:
+        """Get the world state"""
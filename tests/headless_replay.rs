@@ -0,0 +1,31 @@
+//! Runs a small scene without a window, replaying scripted input and checking where it leaves the entities
+use ie::{ecs::components::Position, prelude::*};
+use pyo3::Python;
+
+#[test]
+fn scripted_input_drives_the_scene() -> IslandResult<()> {
+    Python::with_gil(|py| {
+        let mut runner = HeadlessRunner::new(py, IEAppDescriptor {
+            first_scene: Some("tests/scenes/walker.json"),
+            ..Default::default()
+        }, 1.0 / 60.0)?;
+
+        // Walk right for 10 frames, jumping on the first one
+        runner.input().press(KeyCode::D);
+        runner.input().press(KeyCode::Space);
+        runner.run(10)?;
+        runner.input().release_all();
+        runner.run(5)?;
+
+        let world = &runner.world;
+        let player = world.find_by_name("player").unwrap();
+        let wall = world.find_by_name("wall").unwrap();
+        assert_eq!(runner.frames(), 15);
+        assert_eq!(world.registry.get::<Position>(player).unwrap().0, vec2(20.0, 0.0));
+        assert_eq!(world.registry.get::<Position>(wall).unwrap().0, vec2(100.0, 0.0));
+        // Holding the key only presses it once
+        assert_eq!(world.registry.untyped(player).unwrap()["jumps"], 1);
+        assert!(world.failed_scripts.is_empty());
+        return Ok(());
+    })
+}
@@ -0,0 +1,14 @@
+import ie
+
+SPEED = 2
+
+def init(ctx, scene):
+    return scene
+
+def update(ctx, scene):
+    player = ctx.get_entity(ctx.find_by_name("player"))
+    if ctx.is_key_down(ie.Key.D):
+        player["position"]["x"] += SPEED
+    if ctx.is_key_pressed(ie.Key.Space):
+        player["jumps"] = player.get("jumps", 0) + 1
+    return scene